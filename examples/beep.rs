@@ -14,6 +14,18 @@ fn main() -> Result<(), anyhow::Error> {
         cpal::SampleFormat::F32 => run::<f32>(&device, &config.into())?,
         cpal::SampleFormat::I16 => run::<i16>(&device, &config.into())?,
         cpal::SampleFormat::U16 => run::<u16>(&device, &config.into())?,
+        cpal::SampleFormat::I8 => run::<i8>(&device, &config.into())?,
+        cpal::SampleFormat::U8 => run::<u8>(&device, &config.into())?,
+        cpal::SampleFormat::I24 => run::<cpal::I24>(&device, &config.into())?,
+        cpal::SampleFormat::I24Packed => run::<cpal::I24Packed>(&device, &config.into())?,
+        cpal::SampleFormat::I32 => run::<i32>(&device, &config.into())?,
+        cpal::SampleFormat::U32 => run::<u32>(&device, &config.into())?,
+        cpal::SampleFormat::F64 => run::<f64>(&device, &config.into())?,
+        cpal::SampleFormat::MuLaw | cpal::SampleFormat::ALaw => {
+            return Err(anyhow::Error::msg(
+                "companded sample formats are not supported by this example",
+            ))
+        }
     }
 
     Ok(())
@@ -30,7 +42,7 @@ where
     let mut sample_clock = 0f32;
     let mut next_value = move || {
         sample_clock = (sample_clock + 1.0) % sample_rate;
-        (sample_clock * 440.0 * 2.0 * 3.141592 / sample_rate).sin()
+        (sample_clock * 440.0 * 2.0 * std::f32::consts::PI / sample_rate).sin()
     };
 
     let err_fn = |err| eprintln!("an error occurred on stream: {}", err);
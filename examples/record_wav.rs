@@ -40,7 +40,8 @@ fn main() -> Result<(), anyhow::Error> {
         eprintln!("an error occurred on stream: {}", err);
     };
 
-    let stream = match config.sample_format() {
+    let sample_format = config.sample_format();
+    let stream = match sample_format {
         cpal::SampleFormat::F32 => device.build_input_stream(
             &config.into(),
             move |data, _: &_| write_input_data::<f32, f32>(data, &writer_2),
@@ -56,6 +57,51 @@ fn main() -> Result<(), anyhow::Error> {
             move |data, _: &_| write_input_data::<u16, i16>(data, &writer_2),
             err_fn,
         )?,
+        cpal::SampleFormat::I8 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<i8, i8>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::U8 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<u8, i8>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::I32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<i32, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::U32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<u32, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::F64 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<f64, f32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::MuLaw | cpal::SampleFormat::ALaw => device.build_input_stream_raw(
+            &config.into(),
+            sample_format,
+            move |data, _: &_| {
+                let mut decoded = vec![0i16; data.len()];
+                data.decode_companded(&mut decoded);
+                write_input_data::<i16, i16>(&decoded, &writer_2)
+            },
+            err_fn,
+        )?,
+        cpal::SampleFormat::I24 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data_i24::<cpal::I24>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::I24Packed => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data_i24::<cpal::I24Packed>(data, &writer_2),
+            err_fn,
+        )?,
     };
 
     stream.play()?;
@@ -70,17 +116,28 @@ fn main() -> Result<(), anyhow::Error> {
 
 fn sample_format(format: cpal::SampleFormat) -> hound::SampleFormat {
     match format {
-        cpal::SampleFormat::U16 => hound::SampleFormat::Int,
-        cpal::SampleFormat::I16 => hound::SampleFormat::Int,
-        cpal::SampleFormat::F32 => hound::SampleFormat::Float,
+        cpal::SampleFormat::U8 | cpal::SampleFormat::U16 => hound::SampleFormat::Int,
+        cpal::SampleFormat::I8 | cpal::SampleFormat::I16 => hound::SampleFormat::Int,
+        cpal::SampleFormat::I24 | cpal::SampleFormat::I24Packed => hound::SampleFormat::Int,
+        cpal::SampleFormat::I32 | cpal::SampleFormat::U32 => hound::SampleFormat::Int,
+        cpal::SampleFormat::MuLaw | cpal::SampleFormat::ALaw => hound::SampleFormat::Int,
+        cpal::SampleFormat::F32 | cpal::SampleFormat::F64 => hound::SampleFormat::Float,
     }
 }
 
 fn wav_spec_from_config(config: &cpal::SupportedStreamConfig) -> hound::WavSpec {
+    let bits_per_sample = match config.sample_format() {
+        cpal::SampleFormat::I24 | cpal::SampleFormat::I24Packed => 24,
+        // hound can only write 32-bit floats, so `f64` input is converted down to `f32`.
+        cpal::SampleFormat::F64 => 32,
+        // Companded input is decoded to linear 16-bit samples before being written.
+        cpal::SampleFormat::MuLaw | cpal::SampleFormat::ALaw => 16,
+        sample_format => sample_format.sample_size() * 8,
+    };
     hound::WavSpec {
         channels: config.channels() as _,
         sample_rate: config.sample_rate().0 as _,
-        bits_per_sample: (config.sample_format().sample_size() * 8) as _,
+        bits_per_sample: bits_per_sample as _,
         sample_format: sample_format(config.sample_format()),
     }
 }
@@ -101,3 +158,16 @@ where
         }
     }
 }
+
+fn write_input_data_i24<T>(input: &[T], writer: &WavWriterHandle)
+where
+    T: cpal::Sample,
+{
+    if let Ok(mut guard) = writer.try_lock() {
+        if let Some(writer) = guard.as_mut() {
+            for &sample in input.iter() {
+                writer.write_sample(sample.to_i24().inner()).ok();
+            }
+        }
+    }
+}
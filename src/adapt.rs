@@ -0,0 +1,877 @@
+//! Adapting streams to devices that do not support the requested configuration.
+//!
+//! An adapted stream is opened with whatever configuration the device supports that is closest to
+//! the one requested, and converts between the two on the fly so that the data callback always
+//! sees the requested configuration.
+
+use crate::mix::ChannelMatrix;
+use crate::resample::{ResampleQuality, Resampler};
+use crate::{
+    convert_samples, BuildStreamError, ChannelCount, ChannelLayout, Data, Dither, Endianness,
+    I24Packed, InputCallbackInfo, InputStreamTimestamp, NoiseShaping, OutputCallbackInfo,
+    OutputStreamTimestamp, Sample, SampleFormat, SampleRate, StreamConfig, StreamInstant,
+    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError, I24,
+};
+use std::cmp::Reverse;
+use std::time::Duration;
+
+/// Describes which differences between a requested stream configuration and the configurations
+/// supported by a device may be adapted when building a stream.
+///
+/// By default no adaptation takes place, in which case building an adapted stream behaves like
+/// building a regular stream.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamAdaptation {
+    sample_format: bool,
+    dither: Option<NoiseShaping>,
+    resample: Option<ResampleQuality>,
+    mix: Option<ChannelMixing>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ChannelMixing {
+    Standard,
+    Custom(ChannelMatrix),
+}
+
+/// The direction of an adapted stream, which determines which way channels are mixed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Input,
+    Output,
+}
+
+/// A stream built with a `StreamAdaptation`, along with the configuration it was opened with on
+/// the device.
+pub struct AdaptedStream<S> {
+    stream: S,
+    device_config: SupportedStreamConfig,
+}
+
+impl StreamAdaptation {
+    /// A `StreamAdaptation` that does not adapt anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to open the device with a different sample format if it does not support the
+    /// requested one, converting samples to and from the requested format.
+    ///
+    /// The device format with the highest precision is chosen.
+    pub fn sample_format(mut self, enabled: bool) -> Self {
+        self.sample_format = enabled;
+        self
+    }
+
+    /// Dither samples with the given noise shaping when converting them to a device format of
+    /// lower bit depth.
+    ///
+    /// This only has an effect on output streams with sample format conversion enabled.
+    pub fn dither(mut self, shaping: NoiseShaping) -> Self {
+        self.dither = Some(shaping);
+        self
+    }
+
+    /// Open the device at the closest supported sample rate if it does not support the requested
+    /// one, converting between the two rates with the given quality.
+    ///
+    /// Resampling adds latency, which is accounted for in the timestamps passed to the data
+    /// callback. Output streams ask the data callback for as many frames as the resampler needs to
+    /// fill each device buffer, so the callback must cope with varying buffer sizes.
+    pub fn resample(mut self, quality: ResampleQuality) -> Self {
+        self.resample = Some(quality);
+        self
+    }
+
+    /// Whether to open the device with a different number of channels if it does not support the
+    /// requested one, mixing between the two with the standard matrix given by
+    /// `ChannelMatrix::standard`.
+    ///
+    /// The supported channel count closest to the requested one is chosen, preferring more
+    /// channels over fewer.
+    pub fn mix_channels(mut self, enabled: bool) -> Self {
+        self.mix = if enabled {
+            Some(ChannelMixing::Standard)
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Mix channels with the given matrix, opening the device with the number of channels on the
+    /// device side of the matrix.
+    ///
+    /// For output streams the matrix maps the requested channels to the device channels, while for
+    /// input streams it maps the device channels to the requested ones. The matrix is applied even
+    /// if both channel counts are the same, so it may also be used to reorder channels.
+    pub fn channel_matrix(mut self, matrix: ChannelMatrix) -> Self {
+        self.mix = Some(ChannelMixing::Custom(matrix));
+        self
+    }
+
+    /// Whether sample format conversion is enabled.
+    pub fn converts_sample_format(&self) -> bool {
+        self.sample_format
+    }
+
+    /// The noise shaping of the dither applied when converting to a lower bit depth, if any.
+    pub fn dither_noise_shaping(&self) -> Option<NoiseShaping> {
+        self.dither
+    }
+
+    /// The quality of sample rate conversion, if enabled.
+    pub fn resample_quality(&self) -> Option<ResampleQuality> {
+        self.resample
+    }
+
+    /// Whether channel mixing is enabled, either with the standard or a custom matrix.
+    pub fn mixes_channels(&self) -> bool {
+        self.mix.is_some()
+    }
+
+    /// The custom matrix used for channel mixing, if any.
+    pub fn custom_channel_matrix(&self) -> Option<&ChannelMatrix> {
+        match self.mix {
+            Some(ChannelMixing::Custom(ref matrix)) => Some(matrix),
+            _ => None,
+        }
+    }
+}
+
+impl<S> AdaptedStream<S> {
+    pub(crate) fn new(stream: S, device_config: SupportedStreamConfig) -> Self {
+        AdaptedStream {
+            stream,
+            device_config,
+        }
+    }
+
+    /// The stream itself.
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    /// The configuration the stream was opened with on the device.
+    ///
+    /// Any differences to the requested configuration are adapted by the stream.
+    pub fn device_config(&self) -> &SupportedStreamConfig {
+        &self.device_config
+    }
+
+    /// Unwraps the stream itself.
+    pub fn into_stream(self) -> S {
+        self.stream
+    }
+}
+
+/// Chooses the device configuration to open an adapted stream with.
+///
+/// A configuration matching `config` and `sample_format` exactly is always preferred. Otherwise,
+/// the permitted adaptations are used to find the closest supported configuration, where the
+/// channel count is considered more important than the sample rate, and the sample rate more
+/// important than the sample format.
+pub(crate) fn choose_device_config<I>(
+    ranges: I,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    adaptation: &StreamAdaptation,
+    direction: Direction,
+) -> Result<SupportedStreamConfig, BuildStreamError>
+where
+    I: IntoIterator<Item = SupportedStreamConfigRange>,
+{
+    if config.channel_selection.is_some()
+        || config.period_size.is_some()
+        || config.period_count.is_some()
+    {
+        return Err(BuildStreamError::StreamConfigNotSupported);
+    }
+    let channels = config.channels;
+    // The device channel count required by a custom matrix.
+    let matrix_channels = match adaptation.mix {
+        Some(ChannelMixing::Custom(ref matrix)) => {
+            let (requested, device) = match direction {
+                Direction::Input => (matrix.outputs(), matrix.inputs()),
+                Direction::Output => (matrix.inputs(), matrix.outputs()),
+            };
+            if requested != channels {
+                return Err(BuildStreamError::InvalidArgument);
+            }
+            Some(device)
+        }
+        _ => None,
+    };
+    let rate = config.sample_rate;
+    let resample = adaptation.resample.is_some();
+    ranges
+        .into_iter()
+        .filter(|range| adaptation.sample_format || range.sample_format == sample_format)
+        .filter_map(|range| {
+            let device_channels = match (matrix_channels, &adaptation.mix) {
+                (Some(device), _) => device,
+                (None, &Some(ChannelMixing::Standard)) => {
+                    channels.max(range.min_channels).min(range.max_channels)
+                }
+                (None, _) => channels,
+            };
+            let device_rate = if resample {
+                range.sample_rates.closest(rate)
+            } else {
+                rate
+            };
+            if range.supports_channels(device_channels) && range.supports_sample_rate(device_rate) {
+                Some((range, device_channels, device_rate))
+            } else {
+                None
+            }
+        })
+        .max_by_key(|&(ref range, device_channels, device_rate)| {
+            (
+                Reverse((device_channels as i32 - channels as i32).abs()),
+                device_channels >= channels,
+                Reverse((device_rate.0 as i64 - rate.0 as i64).abs()),
+                range.sample_format == sample_format,
+                format_precision(range.sample_format),
+                range.endianness == Endianness::native(),
+            )
+        })
+        .and_then(|(range, device_channels, device_rate)| {
+            range
+                .with_channels(device_channels)
+                .and_then(|range| range.try_with_sample_rate(device_rate))
+        })
+        .ok_or(BuildStreamError::StreamConfigNotSupported)
+}
+
+/// Maps errors from enumerating supported configurations while building an adapted stream.
+pub(crate) fn configs_err(err: SupportedStreamConfigsError) -> BuildStreamError {
+    match err {
+        SupportedStreamConfigsError::DeviceNotAvailable => BuildStreamError::DeviceNotAvailable,
+        SupportedStreamConfigsError::InvalidArgument => BuildStreamError::InvalidArgument,
+        SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+    }
+}
+
+// Ranks sample formats by how much of the signal they preserve.
+fn format_precision(format: SampleFormat) -> u8 {
+    match format {
+        SampleFormat::MuLaw | SampleFormat::ALaw => 0,
+        SampleFormat::U8 => 1,
+        SampleFormat::I8 => 2,
+        SampleFormat::U16 => 3,
+        SampleFormat::I16 => 4,
+        SampleFormat::I24Packed => 5,
+        SampleFormat::I24 => 6,
+        SampleFormat::U32 => 7,
+        SampleFormat::I32 => 8,
+        SampleFormat::F32 => 9,
+        SampleFormat::F64 => 10,
+    }
+}
+
+/// Converts the data delivered by an input stream in the device configuration into the requested
+/// one.
+pub(crate) struct InputAdapter<T> {
+    // The number of channels requested.
+    channels: usize,
+    scratch: Vec<T>,
+    // The device data as `f32`, when it is resampled or mixed.
+    float: Vec<f32>,
+    resampling: Option<Resampling>,
+    mixing: Option<Mixing>,
+}
+
+/// Converts the data rendered by the data callback of an output stream in the requested
+/// configuration into the device one.
+pub(crate) struct OutputAdapter<T> {
+    // The number of channels requested and opened on the device.
+    channels: usize,
+    device_channels: usize,
+    scratch: Vec<T>,
+    // The rendered data as `f32`, when it is resampled or mixed.
+    float: Vec<f32>,
+    dither: Option<Dither>,
+    resampling: Option<Resampling>,
+    mixing: Option<Mixing>,
+}
+
+// The resampling stage of an adapted stream, along with its output buffer.
+struct Resampling {
+    resampler: Resampler,
+    channels: usize,
+    // The sample rate of the input of the resampler.
+    input_rate: f64,
+    output: Vec<f32>,
+}
+
+// The channel mixing stage of an adapted stream, along with its output buffer.
+struct Mixing {
+    matrix: ChannelMatrix,
+    output: Vec<f32>,
+}
+
+impl<T> InputAdapter<T>
+where
+    T: Sample,
+{
+    pub(crate) fn new(
+        config: &StreamConfig,
+        device_config: &SupportedStreamConfig,
+        adaptation: &StreamAdaptation,
+    ) -> Self {
+        let capacity = initial_capacity(device_config);
+        InputAdapter {
+            channels: config.channels as usize,
+            scratch: Vec::with_capacity(capacity),
+            float: Vec::with_capacity(capacity),
+            resampling: Resampling::new(
+                device_config.sample_rate(),
+                config.sample_rate,
+                device_config,
+                adaptation,
+            ),
+            mixing: Mixing::new(
+                (device_config.channels(), device_config.channel_layout()),
+                (config.channels, config.channel_layout.as_ref()),
+                capacity,
+                adaptation,
+            ),
+        }
+    }
+
+    /// Converts `data` and passes the result to `callback`.
+    pub(crate) fn process<F>(&mut self, data: &Data, info: &InputCallbackInfo, mut callback: F)
+    where
+        F: FnMut(&[T], &InputCallbackInfo),
+    {
+        if self.resampling.is_none() && self.mixing.is_none() {
+            if let Some(samples) = data.as_slice::<T>() {
+                return callback(samples, info);
+            }
+            let scratch = resize_scratch(&mut self.scratch, data.len());
+            read_data(data, scratch);
+            return callback(scratch, info);
+        }
+
+        let input = resize_scratch(&mut self.float, data.len());
+        read_data(data, input);
+        let mut delay = 0.0;
+        let resampled: &[f32] = match self.resampling {
+            Some(ref mut resampling) => {
+                // The first frame of `data` was captured this many frames after the next frame
+                // pulled.
+                delay = resampling.resampler.pending_input_frames() / resampling.input_rate;
+                resampling.resampler.push(input);
+                let frames = resampling.resampler.output_frames_available();
+                if frames == 0 {
+                    return;
+                }
+                let output = resize_scratch(&mut resampling.output, frames * resampling.channels);
+                resampling.resampler.pull(output);
+                output
+            }
+            None => input,
+        };
+        let mixed: &[f32] = match self.mixing {
+            Some(ref mut mixing) => mixing.apply(resampled),
+            None => resampled,
+        };
+        debug_assert_eq!(mixed.len() % self.channels, 0);
+        let scratch = resize_scratch(&mut self.scratch, mixed.len());
+        convert_samples(mixed, scratch);
+
+        let timestamp = InputStreamTimestamp {
+            callback: info.timestamp.callback,
+            capture: offset_instant(info.timestamp.capture, -delay),
+        };
+        callback(scratch, &InputCallbackInfo { timestamp });
+    }
+}
+
+impl<T> OutputAdapter<T>
+where
+    T: Sample,
+{
+    pub(crate) fn new(
+        config: &StreamConfig,
+        device_config: &SupportedStreamConfig,
+        adaptation: &StreamAdaptation,
+    ) -> Self {
+        let capacity = initial_capacity(device_config);
+        OutputAdapter {
+            channels: config.channels as usize,
+            device_channels: device_config.channels() as usize,
+            scratch: Vec::with_capacity(capacity),
+            float: Vec::with_capacity(capacity),
+            dither: adaptation
+                .dither
+                .map(|shaping| Dither::new(device_config.channels, shaping)),
+            resampling: Resampling::new(
+                config.sample_rate,
+                device_config.sample_rate(),
+                device_config,
+                adaptation,
+            ),
+            mixing: Mixing::new(
+                (config.channels, config.channel_layout.as_ref()),
+                (device_config.channels(), device_config.channel_layout()),
+                capacity,
+                adaptation,
+            ),
+        }
+    }
+
+    /// Lets `callback` render into a buffer in the requested configuration and converts the
+    /// result into `data`.
+    pub(crate) fn process<F>(&mut self, data: &mut Data, info: &OutputCallbackInfo, mut callback: F)
+    where
+        F: FnMut(&mut [T], &OutputCallbackInfo),
+    {
+        if self.resampling.is_none() && self.mixing.is_none() {
+            if let Some(samples) = data.as_slice_mut::<T>() {
+                return callback(samples, info);
+            }
+            let scratch = resize_scratch(&mut self.scratch, data.len());
+            callback(scratch, info);
+            return write_data(scratch, data, self.dither.as_mut());
+        }
+
+        // Only render as much as the resampler needs, so the latency stays as low as possible.
+        let frames = data.len() / self.device_channels;
+        let (needed, delay) = match self.resampling {
+            Some(ref resampling) => (
+                resampling.resampler.input_frames_needed(frames),
+                resampling.resampler.pending_input_frames() / resampling.input_rate,
+            ),
+            None => (frames, 0.0),
+        };
+        if needed > 0 {
+            // Rendered frames are played after the frames still pending in the resampler.
+            let timestamp = OutputStreamTimestamp {
+                callback: info.timestamp.callback,
+                playback: offset_instant(info.timestamp.playback, delay),
+            };
+            let scratch = resize_scratch(&mut self.scratch, needed * self.channels);
+            callback(scratch, &OutputCallbackInfo { timestamp });
+            let input = resize_scratch(&mut self.float, scratch.len());
+            convert_samples(scratch, input);
+            let mixed = match self.mixing {
+                Some(ref mut mixing) => mixing.apply(input),
+                None => input,
+            };
+            match self.resampling {
+                Some(ref mut resampling) => resampling.resampler.push(mixed),
+                None => return write_data(mixed, data, self.dither.as_mut()),
+            }
+        }
+        if let Some(ref mut resampling) = self.resampling {
+            let output = resize_scratch(&mut resampling.output, data.len());
+            resampling.resampler.pull(output);
+            write_data(output, data, self.dither.as_mut());
+        }
+    }
+}
+
+impl Resampling {
+    // Creates the resampling stage if the rates differ.
+    fn new(
+        from: SampleRate,
+        to: SampleRate,
+        device_config: &SupportedStreamConfig,
+        adaptation: &StreamAdaptation,
+    ) -> Option<Self> {
+        if from == to {
+            return None;
+        }
+        let quality = adaptation
+            .resample
+            .expect("rates only differ if resampling is enabled");
+        let channels = device_config.channels() as usize;
+        Some(Resampling {
+            resampler: Resampler::new(channels, from, to, quality),
+            channels,
+            input_rate: from.0 as f64,
+            output: Vec::with_capacity(initial_capacity(device_config)),
+        })
+    }
+}
+
+impl Mixing {
+    // Creates the mixing stage if channels are mixed with a custom matrix or the channel counts
+    // differ. Standard mixing uses the speaker layouts on both sides if they are known.
+    fn new(
+        from: (ChannelCount, Option<&ChannelLayout>),
+        to: (ChannelCount, Option<&ChannelLayout>),
+        capacity: usize,
+        adaptation: &StreamAdaptation,
+    ) -> Option<Self> {
+        // A layout that does not match its channel count says nothing about the channels.
+        let layout = |(channels, layout): (ChannelCount, Option<&ChannelLayout>)| {
+            layout
+                .filter(|layout| layout.channels() == channels)
+                .cloned()
+        };
+        let matrix = match adaptation.mix {
+            Some(ChannelMixing::Custom(ref matrix)) => matrix.clone(),
+            _ if from.0 == to.0 => return None,
+            Some(ChannelMixing::Standard) => match (layout(from), layout(to)) {
+                (Some(from), Some(to)) => ChannelMatrix::from_layouts(&from, &to),
+                _ => ChannelMatrix::standard(from.0, to.0),
+            },
+            None => unreachable!("channel counts only differ if mixing is enabled"),
+        };
+        Some(Mixing {
+            matrix,
+            output: Vec::with_capacity(capacity),
+        })
+    }
+
+    // Mixes the interleaved frames of `input`, returning the mixed frames.
+    fn apply(&mut self, input: &[f32]) -> &mut [f32] {
+        let frames = input.len() / self.matrix.inputs() as usize;
+        let output = resize_scratch(&mut self.output, frames * self.matrix.outputs() as usize);
+        self.matrix.apply(input, output);
+        output
+    }
+}
+
+// Moves `instant` by the given number of seconds, which may be negative.
+fn offset_instant(instant: StreamInstant, secs: f64) -> StreamInstant {
+    let offset = Duration::from_secs_f64(secs.abs());
+    let moved = if secs < 0.0 {
+        instant.sub(offset)
+    } else {
+        instant.add(offset)
+    };
+    moved.unwrap_or(instant)
+}
+
+// Preallocates the scratch buffer for the largest buffer the device is expected to deliver.
+fn initial_capacity(device_config: &SupportedStreamConfig) -> usize {
+    // Some hosts report implausibly large maximums, so don't allocate more than a second's worth.
+    let max_frames = match *device_config.buffer_size() {
+        crate::SupportedBufferSize::Range { max, .. } => max.min(device_config.sample_rate().0),
+        crate::SupportedBufferSize::Unknown => 0,
+    };
+    max_frames as usize * device_config.channels() as usize
+}
+
+// Resizes the scratch buffer to `len` samples. This only allocates if the host delivers a larger
+// buffer than anticipated.
+fn resize_scratch<T>(scratch: &mut Vec<T>, len: usize) -> &mut [T]
+where
+    T: Sample,
+{
+    if scratch.len() < len {
+        scratch.resize(len, T::EQUILIBRIUM);
+    }
+    &mut scratch[..len]
+}
+
+/// Converts the samples of `data` into `output`, which must be of the same length.
+pub(crate) fn read_data<T>(data: &Data, output: &mut [T])
+where
+    T: Sample,
+{
+    fn read<S: Sample, T: Sample>(data: &Data, output: &mut [T]) {
+        let input = data.as_slice::<S>().expect("sample format was checked");
+        convert_samples(input, output);
+    }
+    match data.sample_format() {
+        SampleFormat::I8 => read::<i8, T>(data, output),
+        SampleFormat::I16 => read::<i16, T>(data, output),
+        SampleFormat::I24 => read::<I24, T>(data, output),
+        SampleFormat::I24Packed => read::<I24Packed, T>(data, output),
+        SampleFormat::I32 => read::<i32, T>(data, output),
+        SampleFormat::U8 => read::<u8, T>(data, output),
+        SampleFormat::U16 => read::<u16, T>(data, output),
+        SampleFormat::U32 => read::<u32, T>(data, output),
+        SampleFormat::F32 => read::<f32, T>(data, output),
+        SampleFormat::F64 => read::<f64, T>(data, output),
+        SampleFormat::MuLaw | SampleFormat::ALaw => data
+            .decode_companded(output)
+            .expect("sample format was checked"),
+    }
+}
+
+/// Converts `input` into the samples of `data`, which must be of the same length, optionally
+/// applying dither.
+pub(crate) fn write_data<T>(input: &[T], data: &mut Data, dither: Option<&mut Dither>)
+where
+    T: Sample,
+{
+    fn write<T: Sample, D: Sample>(input: &[T], data: &mut Data, dither: Option<&mut Dither>) {
+        let output = data.as_slice_mut::<D>().expect("sample format was checked");
+        match dither {
+            Some(dither) => dither.convert(input, output),
+            None => convert_samples(input, output),
+        }
+    }
+    match data.sample_format() {
+        SampleFormat::I8 => write::<T, i8>(input, data, dither),
+        SampleFormat::I16 => write::<T, i16>(input, data, dither),
+        SampleFormat::I24 => write::<T, I24>(input, data, dither),
+        SampleFormat::I24Packed => write::<T, I24Packed>(input, data, dither),
+        SampleFormat::I32 => write::<T, i32>(input, data, dither),
+        SampleFormat::U8 => write::<T, u8>(input, data, dither),
+        SampleFormat::U16 => write::<T, u16>(input, data, dither),
+        SampleFormat::U32 => write::<T, u32>(input, data, dither),
+        SampleFormat::F32 => write::<T, f32>(input, data, dither),
+        SampleFormat::F64 => write::<T, f64>(input, data, dither),
+        SampleFormat::MuLaw | SampleFormat::ALaw => data
+            .encode_companded(input)
+            .expect("sample format was checked"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        choose_device_config, read_data, write_data, Direction, InputAdapter, OutputAdapter,
+        StreamAdaptation,
+    };
+    use crate::mix::ChannelMatrix;
+    use crate::resample::ResampleQuality;
+    use crate::{
+        test_config_range, BufferSize, BuildStreamError, Data, OutputCallbackInfo,
+        OutputStreamTimestamp, SampleFormat, SampleRate, StreamConfig, StreamInstant,
+        SupportedBufferSize, SupportedSampleRates,
+    };
+
+    fn stereo_config() -> StreamConfig {
+        StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(44_100),
+            buffer_size: BufferSize::Default,
+            channel_layout: None,
+            channel_selection: None,
+            period_size: None,
+            period_count: None,
+            strict: false,
+        }
+    }
+
+    #[test]
+    fn requested_format_is_preferred() {
+        let ranges = vec![
+            test_config_range(2, SampleFormat::F64),
+            test_config_range(2, SampleFormat::I16),
+        ];
+        let adaptation = StreamAdaptation::new().sample_format(true);
+        let config = choose_device_config(
+            ranges,
+            &stereo_config(),
+            SampleFormat::I16,
+            &adaptation,
+            Direction::Output,
+        )
+        .unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I16);
+        assert_eq!(config.sample_rate(), SampleRate(44_100));
+    }
+
+    #[test]
+    fn most_precise_format_is_chosen() {
+        let ranges = vec![
+            test_config_range(2, SampleFormat::U8),
+            test_config_range(2, SampleFormat::I24),
+            test_config_range(2, SampleFormat::I16),
+            test_config_range(1, SampleFormat::F64),
+        ];
+        let config = choose_device_config(
+            ranges.clone(),
+            &stereo_config(),
+            SampleFormat::F32,
+            &StreamAdaptation::new().sample_format(true),
+            Direction::Output,
+        )
+        .unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I24);
+
+        let err = choose_device_config(
+            ranges,
+            &stereo_config(),
+            SampleFormat::F32,
+            &StreamAdaptation::new(),
+            Direction::Output,
+        );
+        match err {
+            Err(BuildStreamError::StreamConfigNotSupported) => (),
+            _ => panic!("expected StreamConfigNotSupported"),
+        }
+    }
+
+    #[test]
+    fn periods_are_not_supported() {
+        let config = StreamConfig {
+            period_count: Some(4),
+            ..stereo_config()
+        };
+        let err = choose_device_config(
+            vec![test_config_range(2, SampleFormat::F32)],
+            &config,
+            SampleFormat::F32,
+            &StreamAdaptation::new(),
+            Direction::Output,
+        );
+        match err {
+            Err(BuildStreamError::StreamConfigNotSupported) => (),
+            _ => panic!("expected StreamConfigNotSupported"),
+        }
+    }
+
+    #[test]
+    fn closest_rate_is_chosen_when_resampling() {
+        let mut low = test_config_range(2, SampleFormat::F32);
+        low.sample_rates = SupportedSampleRates::Range {
+            min: SampleRate(8_000),
+            max: SampleRate(22_050),
+        };
+        let mut high = test_config_range(2, SampleFormat::I16);
+        high.sample_rates =
+            SupportedSampleRates::Discrete(vec![SampleRate(48_000), SampleRate(96_000)]);
+        let ranges = vec![low, high];
+        let adaptation = StreamAdaptation::new()
+            .sample_format(true)
+            .resample(ResampleQuality::Linear);
+        let config = choose_device_config(
+            ranges.clone(),
+            &stereo_config(),
+            SampleFormat::F32,
+            &adaptation,
+            Direction::Output,
+        )
+        .unwrap();
+        assert_eq!(config.sample_rate(), SampleRate(48_000));
+        assert_eq!(config.sample_format(), SampleFormat::I16);
+
+        let without_resampling = StreamAdaptation::new().sample_format(true);
+        assert!(choose_device_config(
+            ranges,
+            &stereo_config(),
+            SampleFormat::F32,
+            &without_resampling,
+            Direction::Output,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn data_round_trips_through_device_format() {
+        let input = [-0.5f32, -0.25, 0.0, -1.0];
+        let mut device = [0i16; 4];
+        let mut data = unsafe {
+            Data::from_parts(
+                device.as_mut_ptr() as *mut (),
+                device.len(),
+                SampleFormat::I16,
+            )
+        };
+        write_data(&input, &mut data, None);
+        let mut output = [0.0f32; 4];
+        read_data(&data, &mut output);
+        assert_eq!(output, input);
+        assert_eq!(device, [-16384, -8192, 0, i16::MIN]);
+    }
+
+    #[test]
+    fn closest_channel_count_is_chosen_when_mixing() {
+        let ranges = vec![
+            test_config_range(1, SampleFormat::F32),
+            test_config_range(3, SampleFormat::F32),
+            test_config_range(6, SampleFormat::F32),
+        ];
+        let mixing = StreamAdaptation::new().mix_channels(true);
+        let config = choose_device_config(
+            ranges.clone(),
+            &stereo_config(),
+            SampleFormat::F32,
+            &mixing,
+            Direction::Output,
+        )
+        .unwrap();
+        assert_eq!(config.channels(), 3);
+
+        // Within a range of channel counts, the requested count is opened as is.
+        let mut wide = test_config_range(1, SampleFormat::F32);
+        wide.max_channels = 8;
+        let config = choose_device_config(
+            vec![wide],
+            &stereo_config(),
+            SampleFormat::F32,
+            &mixing,
+            Direction::Output,
+        )
+        .unwrap();
+        assert_eq!(config.channels(), 2);
+
+        // A custom matrix fixes the device channel count, in the direction of the stream.
+        let matrix = ChannelMatrix::standard(6, 2);
+        let custom = StreamAdaptation::new().channel_matrix(matrix);
+        let config = choose_device_config(
+            ranges.clone(),
+            &stereo_config(),
+            SampleFormat::F32,
+            &custom,
+            Direction::Input,
+        )
+        .unwrap();
+        assert_eq!(config.channels(), 6);
+        match choose_device_config(
+            ranges,
+            &stereo_config(),
+            SampleFormat::F32,
+            &custom,
+            Direction::Output,
+        ) {
+            Err(BuildStreamError::InvalidArgument) => (),
+            _ => panic!("expected InvalidArgument"),
+        }
+    }
+
+    #[test]
+    fn output_is_mixed_into_device_channels() {
+        let device_config =
+            test_config_range(1, SampleFormat::I16).with_sample_rate(SampleRate(44_100));
+        let adaptation = StreamAdaptation::new().mix_channels(true);
+        let mut adapter = OutputAdapter::<f32>::new(&stereo_config(), &device_config, &adaptation);
+        let mut device = [0i16; 2];
+        let mut data = unsafe {
+            Data::from_parts(
+                device.as_mut_ptr() as *mut (),
+                device.len(),
+                SampleFormat::I16,
+            )
+        };
+        let instant = StreamInstant::new(0, 0);
+        let info = OutputCallbackInfo {
+            timestamp: OutputStreamTimestamp {
+                callback: instant,
+                playback: instant,
+            },
+        };
+        adapter.process(
+            &mut data,
+            &info,
+            |samples: &mut [f32], _: &OutputCallbackInfo| {
+                assert_eq!(samples.len(), 4);
+                samples.copy_from_slice(&[-0.5, -0.5, -0.25, 0.25]);
+            },
+        );
+        assert_eq!(device, [-16384, 0]);
+    }
+
+    #[test]
+    fn scratch_buffers_are_preallocated() {
+        let mut device_range = test_config_range(2, SampleFormat::I16);
+        device_range.buffer_size = SupportedBufferSize::Range { min: 64, max: 512 };
+        let device_config = device_range.with_sample_rate(SampleRate(48_000));
+        let adaptation = StreamAdaptation::new().resample(ResampleQuality::Linear);
+        let input = InputAdapter::<f32>::new(&stereo_config(), &device_config, &adaptation);
+        assert!(input.scratch.capacity() >= 1024);
+        assert!(input.float.capacity() >= 1024);
+        let output = OutputAdapter::<f32>::new(&stereo_config(), &device_config, &adaptation);
+        assert!(output.scratch.capacity() >= 1024);
+        assert!(output.float.capacity() >= 1024);
+    }
+}
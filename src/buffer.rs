@@ -0,0 +1,460 @@
+//! Owned audio buffers for moving audio out of and into stream callbacks.
+
+use crate::{ChannelCount, Data, Frames, FramesMut, Sample, SampleFormat, SampleRate};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// An owned buffer of interleaved audio along with its sample format, channel count and sample
+/// rate.
+///
+/// Unlike `Data`, which is only valid for the duration of a stream callback, an `AudioBuffer` may
+/// be kept around and sent to other threads. Its storage is only ever grown, so a buffer that is
+/// refilled with at most as many samples as it has capacity for never allocates. See
+/// `AudioBufferPool` for reusing buffers across callbacks.
+#[derive(Clone, Debug)]
+pub struct AudioBuffer {
+    // Backing memory, aligned for the largest sample type.
+    storage: Vec<u64>,
+    // The number of samples in the buffer.
+    len: usize,
+    sample_format: SampleFormat,
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+}
+
+impl AudioBuffer {
+    /// Creates an empty buffer.
+    pub fn new(
+        sample_format: SampleFormat,
+        channels: ChannelCount,
+        sample_rate: SampleRate,
+    ) -> Self {
+        Self::with_capacity(sample_format, channels, sample_rate, 0)
+    }
+
+    /// Creates an empty buffer with room for `frames` frames.
+    pub fn with_capacity(
+        sample_format: SampleFormat,
+        channels: ChannelCount,
+        sample_rate: SampleRate,
+        frames: usize,
+    ) -> Self {
+        let mut buffer = AudioBuffer {
+            storage: Vec::new(),
+            len: 0,
+            sample_format,
+            channels,
+            sample_rate,
+        };
+        buffer.reserve_samples(frames * channels as usize);
+        buffer
+    }
+
+    /// Creates a buffer holding a copy of the given interleaved samples.
+    pub fn from_samples<T>(samples: &[T], channels: ChannelCount, sample_rate: SampleRate) -> Self
+    where
+        T: Sample,
+    {
+        let mut buffer = Self::new(T::FORMAT, channels, sample_rate);
+        buffer.copy_from_samples(samples);
+        buffer
+    }
+
+    /// Creates a buffer holding a copy of the given callback data.
+    pub fn from_data(data: &Data, channels: ChannelCount, sample_rate: SampleRate) -> Self {
+        let mut buffer = Self::new(data.sample_format(), channels, sample_rate);
+        buffer.copy_from_data(data);
+        buffer
+    }
+
+    /// The sample format of the audio in the buffer.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of interleaved channels in the buffer.
+    pub fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    /// The sample rate of the audio in the buffer.
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Changes the number of interleaved channels the samples in the buffer are interpreted as.
+    pub fn set_channels(&mut self, channels: ChannelCount) {
+        self.channels = channels;
+    }
+
+    /// Changes the sample rate the samples in the buffer are interpreted as.
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// The length of the buffer in samples.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether or not the buffer contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of whole frames in the buffer.
+    pub fn frames(&self) -> usize {
+        match self.channels {
+            0 => 0,
+            channels => self.len / channels as usize,
+        }
+    }
+
+    /// The number of samples the buffer can hold without allocating.
+    pub fn capacity(&self) -> usize {
+        self.storage.len() * std::mem::size_of::<u64>() / self.sample_format.sample_size()
+    }
+
+    /// Removes all samples from the buffer, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Replaces the contents of the buffer with a copy of the given interleaved samples.
+    ///
+    /// The sample format of the buffer is changed to `T::FORMAT`.
+    pub fn copy_from_samples<T>(&mut self, samples: &[T])
+    where
+        T: Sample,
+    {
+        self.sample_format = T::FORMAT;
+        self.set_len(samples.len());
+        self.as_slice_mut()
+            .expect("sample format was just set")
+            .copy_from_slice(samples);
+    }
+
+    /// Replaces the contents of the buffer with a copy of the given callback data.
+    ///
+    /// The sample format of the buffer is changed to that of `data`.
+    pub fn copy_from_data(&mut self, data: &Data) {
+        self.sample_format = data.sample_format();
+        self.set_len(data.len());
+        self.bytes_mut().copy_from_slice(data.bytes());
+    }
+
+    /// Copies as many samples as fit from the start of the buffer into the given callback data.
+    ///
+    /// Returns the number of samples copied, or `None` if the sample format of `data` differs from
+    /// that of the buffer.
+    pub fn copy_to_data(&self, data: &mut Data) -> Option<usize> {
+        if data.sample_format() != self.sample_format {
+            return None;
+        }
+        let len = self.len.min(data.len());
+        let bytes = len * self.sample_format.sample_size();
+        data.bytes_mut()[..bytes].copy_from_slice(&self.bytes()[..bytes]);
+        Some(len)
+    }
+
+    /// The raw slice of memory representing the samples in the buffer as a slice of bytes.
+    pub fn bytes(&self) -> &[u8] {
+        let len = self.len * self.sample_format.sample_size();
+        // `storage` always holds at least `len` samples, see `set_len`.
+        unsafe { std::slice::from_raw_parts(self.storage.as_ptr() as *const u8, len) }
+    }
+
+    /// The raw slice of memory representing the samples in the buffer as a slice of bytes.
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.len * self.sample_format.sample_size();
+        // `storage` always holds at least `len` samples, see `set_len`.
+        unsafe { std::slice::from_raw_parts_mut(self.storage.as_mut_ptr() as *mut u8, len) }
+    }
+
+    /// Access the samples as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the sample format of the buffer.
+    pub fn as_slice<T>(&self) -> Option<&[T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT == self.sample_format {
+            // `storage` is aligned for every sample type and holds at least `len` samples.
+            unsafe {
+                Some(std::slice::from_raw_parts(
+                    self.storage.as_ptr() as *const T,
+                    self.len,
+                ))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Access the samples as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the sample format of the buffer.
+    pub fn as_slice_mut<T>(&mut self) -> Option<&mut [T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT == self.sample_format {
+            // `storage` is aligned for every sample type and holds at least `len` samples.
+            unsafe {
+                Some(std::slice::from_raw_parts_mut(
+                    self.storage.as_mut_ptr() as *mut T,
+                    self.len,
+                ))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Access the samples as a sequence of frames of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the sample format of the buffer, or if
+    /// the buffer does not hold a whole number of frames.
+    pub fn as_frames<T>(&self) -> Option<Frames<'_, T>>
+    where
+        T: Sample,
+    {
+        let channels = self.channels;
+        self.as_slice()
+            .and_then(|samples| Frames::new(samples, channels))
+    }
+
+    /// Access the samples as a sequence of frames of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the sample format of the buffer, or if
+    /// the buffer does not hold a whole number of frames.
+    pub fn as_frames_mut<T>(&mut self) -> Option<FramesMut<'_, T>>
+    where
+        T: Sample,
+    {
+        let channels = self.channels;
+        self.as_slice_mut()
+            .and_then(|samples| FramesMut::new(samples, channels))
+    }
+
+    // Sets the number of samples in the buffer, growing the storage if necessary. Any samples
+    // beyond the previous length hold unspecified values.
+    fn set_len(&mut self, len: usize) {
+        self.reserve_samples(len);
+        self.len = len;
+    }
+
+    fn reserve_samples(&mut self, len: usize) {
+        // Round up to a whole number of 8 byte words.
+        let words = (len * self.sample_format.sample_size() + 7) >> 3;
+        if self.storage.len() < words {
+            self.storage.resize(words, 0);
+        }
+    }
+}
+
+/// A shared pool of `AudioBuffer`s that can be handed between a stream callback and other threads.
+///
+/// A typical input stream acquires a buffer in its callback, fills it via
+/// `AudioBuffer::copy_from_data` and sends it to a worker thread, which releases it back to the
+/// pool once done. As long as the callback never needs more buffers or samples than the pool was
+/// created with, no allocation takes place in the callback.
+///
+/// The pool never blocks: `acquire` and `release` are lock-free and only scan a fixed number of
+/// slots, so both are safe to call from a real-time audio thread.
+///
+/// Cloning the pool yields another handle to the same set of buffers.
+#[derive(Clone)]
+pub struct AudioBufferPool {
+    slots: Arc<[Slot]>,
+}
+
+// A slot holding at most one buffer of the pool.
+struct Slot {
+    state: AtomicU8,
+    buffer: UnsafeCell<Option<AudioBuffer>>,
+}
+
+// The state of a `Slot`. Only the thread that moved a slot into `SLOT_BUSY` accesses its buffer.
+const SLOT_EMPTY: u8 = 0;
+const SLOT_FULL: u8 = 1;
+const SLOT_BUSY: u8 = 2;
+
+// The buffer of a slot is only accessed by the thread that claimed it, see `Slot::claim`.
+unsafe impl Sync for Slot {}
+
+impl AudioBufferPool {
+    /// Creates a pool of `count` empty buffers, each with room for `frames` frames.
+    pub fn new(
+        sample_format: SampleFormat,
+        channels: ChannelCount,
+        sample_rate: SampleRate,
+        frames: usize,
+        count: usize,
+    ) -> Self {
+        let slots: Vec<Slot> = (0..count)
+            .map(|_| Slot {
+                state: AtomicU8::new(SLOT_FULL),
+                buffer: UnsafeCell::new(Some(AudioBuffer::with_capacity(
+                    sample_format,
+                    channels,
+                    sample_rate,
+                    frames,
+                ))),
+            })
+            .collect();
+        AudioBufferPool {
+            slots: slots.into(),
+        }
+    }
+
+    /// Takes an empty buffer from the pool.
+    ///
+    /// Returns `None` if all buffers are currently in use.
+    pub fn acquire(&self) -> Option<AudioBuffer> {
+        for slot in self.slots.iter() {
+            if slot.claim(SLOT_FULL) {
+                let buffer = unsafe { (*slot.buffer.get()).take() };
+                slot.state.store(SLOT_EMPTY, Ordering::Release);
+                // A full slot always holds a buffer.
+                if let Some(mut buffer) = buffer {
+                    buffer.clear();
+                    return Some(buffer);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns a buffer to the pool so that it may be acquired again.
+    ///
+    /// The pool holds as many buffers as it was created with. If it is already full, for example
+    /// because `buffer` was not acquired from it, the buffer is dropped instead.
+    pub fn release(&self, buffer: AudioBuffer) {
+        for slot in self.slots.iter() {
+            if slot.claim(SLOT_EMPTY) {
+                unsafe { *slot.buffer.get() = Some(buffer) };
+                slot.state.store(SLOT_FULL, Ordering::Release);
+                return;
+            }
+        }
+    }
+
+    /// The number of buffers currently available to `acquire`.
+    pub fn available(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.state.load(Ordering::Acquire) == SLOT_FULL)
+            .count()
+    }
+}
+
+impl fmt::Debug for AudioBufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AudioBufferPool")
+            .field("count", &self.slots.len())
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+impl Slot {
+    // Moves the slot from `state` into `SLOT_BUSY`, returning whether it was in `state`.
+    fn claim(&self, state: u8) -> bool {
+        self.state
+            .compare_exchange(state, SLOT_BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AudioBuffer, AudioBufferPool};
+    use crate::{Data, SampleFormat, SampleRate};
+
+    #[test]
+    fn round_trip_through_data() {
+        let mut samples = [0.5f32, -0.5, 0.25, -0.25];
+        let data = unsafe {
+            Data::from_parts(
+                samples.as_mut_ptr() as *mut (),
+                samples.len(),
+                SampleFormat::F32,
+            )
+        };
+        let buffer = AudioBuffer::from_data(&data, 2, SampleRate(48_000));
+        assert_eq!(buffer.sample_format(), SampleFormat::F32);
+        assert_eq!(buffer.frames(), 2);
+        assert_eq!(buffer.as_slice::<f32>(), Some(&samples[..]));
+        assert_eq!(buffer.as_slice::<i16>(), None);
+
+        let mut output = [0.0f32; 3];
+        let mut data = unsafe {
+            Data::from_parts(
+                output.as_mut_ptr() as *mut (),
+                output.len(),
+                SampleFormat::F32,
+            )
+        };
+        assert_eq!(buffer.copy_to_data(&mut data), Some(3));
+        assert_eq!(output, [0.5, -0.5, 0.25]);
+    }
+
+    #[test]
+    fn refilling_within_capacity_keeps_storage() {
+        let mut buffer = AudioBuffer::with_capacity(SampleFormat::I16, 2, SampleRate(44_100), 4);
+        assert_eq!(buffer.capacity(), 8);
+        let ptr = buffer.bytes().as_ptr();
+        buffer.copy_from_samples(&[1i16, 2, 3, 4, 5, 6, 7, 8]);
+        buffer.copy_from_samples(&[9i16, 10]);
+        assert_eq!(buffer.bytes().as_ptr(), ptr);
+        assert_eq!(buffer.as_slice::<i16>(), Some(&[9, 10][..]));
+        let frames = buffer.as_frames::<i16>().unwrap();
+        assert_eq!(frames.frame(0), Some(&[9, 10][..]));
+    }
+
+    #[test]
+    fn pool_hands_out_and_takes_back_buffers() {
+        let pool = AudioBufferPool::new(SampleFormat::F64, 1, SampleRate(8_000), 16, 2);
+        let mut first = pool.acquire().unwrap();
+        let _second = pool.acquire().unwrap();
+        assert!(pool.acquire().is_none());
+        first.copy_from_samples(&[1.0f64; 16]);
+        pool.release(first);
+        assert_eq!(pool.available(), 1);
+        let reused = pool.acquire().unwrap();
+        assert!(reused.is_empty());
+        assert_eq!(reused.capacity(), 16);
+    }
+
+    #[test]
+    fn pool_drops_buffers_beyond_its_size() {
+        let pool = AudioBufferPool::new(SampleFormat::I16, 2, SampleRate(8_000), 4, 1);
+        let foreign = AudioBuffer::with_capacity(SampleFormat::I16, 2, SampleRate(8_000), 4);
+        pool.release(foreign);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn pool_is_shared_between_threads() {
+        let pool = AudioBufferPool::new(SampleFormat::F32, 2, SampleRate(48_000), 64, 4);
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        if let Some(buffer) = pool.acquire() {
+                            pool.release(buffer);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(pool.available(), 4);
+    }
+}
@@ -0,0 +1,430 @@
+//! Conversion of whole slices of samples between sample formats.
+//!
+//! The result of `convert_samples` is always identical to calling `Sample::from` on each sample.
+//! The most common conversions between `f32` and `i16` use SIMD instructions where the target
+//! supports them (SSE2 or AVX2 on x86, NEON on aarch64), while all other pairs use a plain loop
+//! that the compiler is free to vectorise.
+
+use crate::{Sample, SampleFormat};
+
+/// Converts every sample in `input` to the sample type of `output`.
+///
+/// This produces exactly the same result as `Sample::from` for each sample, but is considerably
+/// faster for large buffers.
+///
+/// **panic!**s if `input` and `output` have different lengths.
+pub fn convert_samples<S, D>(input: &[S], output: &mut [D])
+where
+    S: Sample,
+    D: Sample,
+{
+    assert_eq!(
+        input.len(),
+        output.len(),
+        "input and output must have the same length"
+    );
+    // The `Sample` trait guarantees that `FORMAT` describes the memory representation of the
+    // implementing type, so matching formats allows reinterpreting the slices.
+    match (S::FORMAT, D::FORMAT) {
+        (SampleFormat::F32, SampleFormat::I16) => unsafe {
+            f32_to_i16(cast_slice(input), cast_slice_mut(output))
+        },
+        (SampleFormat::I16, SampleFormat::F32) => unsafe {
+            i16_to_f32(cast_slice(input), cast_slice_mut(output))
+        },
+        _ => convert_scalar(input, output),
+    }
+}
+
+fn convert_scalar<S, D>(input: &[S], output: &mut [D])
+where
+    S: Sample,
+    D: Sample,
+{
+    for (dst, src) in output.iter_mut().zip(input) {
+        *dst = D::from(src);
+    }
+}
+
+fn f32_to_i16(input: &[f32], output: &mut [i16]) {
+    let done = f32_to_i16_simd(input, output);
+    convert_scalar(&input[done..], &mut output[done..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+fn f32_to_i16_simd(input: &[f32], output: &mut [i16]) -> usize {
+    // SSE2 is always available on x86_64.
+    if is_x86_feature_detected!("avx2") {
+        unsafe { x86::f32_to_i16_avx2(input, output) }
+    } else {
+        unsafe { x86::f32_to_i16_sse2(input, output) }
+    }
+}
+
+#[cfg(target_arch = "x86")]
+fn f32_to_i16_simd(input: &[f32], output: &mut [i16]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        unsafe { x86::f32_to_i16_avx2(input, output) }
+    } else if is_x86_feature_detected!("sse2") {
+        unsafe { x86::f32_to_i16_sse2(input, output) }
+    } else {
+        0
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn f32_to_i16_simd(input: &[f32], output: &mut [i16]) -> usize {
+    unsafe { neon::f32_to_i16(input, output) }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn f32_to_i16_simd(_input: &[f32], _output: &mut [i16]) -> usize {
+    0
+}
+
+fn i16_to_f32(input: &[i16], output: &mut [f32]) {
+    let done = i16_to_f32_simd(input, output);
+    convert_scalar(&input[done..], &mut output[done..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+fn i16_to_f32_simd(input: &[i16], output: &mut [f32]) -> usize {
+    // SSE2 is always available on x86_64.
+    if is_x86_feature_detected!("avx2") {
+        unsafe { x86::i16_to_f32_avx2(input, output) }
+    } else {
+        unsafe { x86::i16_to_f32_sse2(input, output) }
+    }
+}
+
+#[cfg(target_arch = "x86")]
+fn i16_to_f32_simd(input: &[i16], output: &mut [f32]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        unsafe { x86::i16_to_f32_avx2(input, output) }
+    } else if is_x86_feature_detected!("sse2") {
+        unsafe { x86::i16_to_f32_sse2(input, output) }
+    } else {
+        0
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn i16_to_f32_simd(input: &[i16], output: &mut [f32]) -> usize {
+    unsafe { neon::i16_to_f32(input, output) }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn i16_to_f32_simd(_input: &[i16], _output: &mut [f32]) -> usize {
+    0
+}
+
+// The SIMD implementations below mirror the scalar `Sample` conversions step by step so that
+// their results are bit-exact:
+//
+// - `f32` to `i16` scales non-negative values by `i16::MAX` and negative values by `-i16::MIN`,
+//   then truncates towards zero. NaN becomes 0 and out of range values saturate, like `as` does.
+// - `i16` to `f32` divides (rather than multiplying by a reciprocal) by `-i16::MIN` for negative
+//   values and by `i16::MAX` otherwise.
+//
+// Each function converts as many whole vectors as fit in the slices and returns the number of
+// samples converted. The caller converts the remainder.
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn f32_to_i16_sse2(input: &[f32], output: &mut [i16]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = _mm_setzero_ps();
+        let pos_scale = _mm_set1_ps(i16::MAX as f32);
+        let neg_scale = _mm_set1_ps(-(i16::MIN as f32));
+        let min = _mm_set1_ps(i16::MIN as f32);
+        let max = _mm_set1_ps(i16::MAX as f32);
+        let convert = |x: __m128| {
+            let non_negative = _mm_cmpge_ps(x, zero);
+            let scale = _mm_or_ps(
+                _mm_and_ps(non_negative, pos_scale),
+                _mm_andnot_ps(non_negative, neg_scale),
+            );
+            let scaled = _mm_mul_ps(x, scale);
+            let scaled = _mm_and_ps(scaled, _mm_cmpord_ps(scaled, scaled));
+            _mm_cvttps_epi32(_mm_min_ps(_mm_max_ps(scaled, min), max))
+        };
+        let mut i = 0;
+        while i < len {
+            let lo = convert(_mm_loadu_ps(input.as_ptr().add(i)));
+            let hi = convert(_mm_loadu_ps(input.as_ptr().add(i + 4)));
+            let packed = _mm_packs_epi32(lo, hi);
+            _mm_storeu_si128(output.as_mut_ptr().add(i) as *mut __m128i, packed);
+            i += 8;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn f32_to_i16_avx2(input: &[f32], output: &mut [i16]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = _mm256_setzero_ps();
+        let pos_scale = _mm256_set1_ps(i16::MAX as f32);
+        let neg_scale = _mm256_set1_ps(-(i16::MIN as f32));
+        let min = _mm256_set1_ps(i16::MIN as f32);
+        let max = _mm256_set1_ps(i16::MAX as f32);
+        let mut i = 0;
+        while i < len {
+            let x = _mm256_loadu_ps(input.as_ptr().add(i));
+            let non_negative = _mm256_cmp_ps(x, zero, _CMP_GE_OQ);
+            let scale = _mm256_blendv_ps(neg_scale, pos_scale, non_negative);
+            let scaled = _mm256_mul_ps(x, scale);
+            let scaled = _mm256_and_ps(scaled, _mm256_cmp_ps(scaled, scaled, _CMP_ORD_Q));
+            let ints = _mm256_cvttps_epi32(_mm256_min_ps(_mm256_max_ps(scaled, min), max));
+            let lo = _mm256_castsi256_si128(ints);
+            let hi = _mm256_extracti128_si256(ints, 1);
+            let packed = _mm_packs_epi32(lo, hi);
+            _mm_storeu_si128(output.as_mut_ptr().add(i) as *mut __m128i, packed);
+            i += 8;
+        }
+        len
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn i16_to_f32_sse2(input: &[i16], output: &mut [f32]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = _mm_setzero_si128();
+        let pos_div = _mm_set1_ps(i16::MAX as f32);
+        let neg_div = _mm_set1_ps(-(i16::MIN as f32));
+        let convert = |x: __m128i| {
+            let negative = _mm_castsi128_ps(_mm_cmplt_epi32(x, zero));
+            let div = _mm_or_ps(
+                _mm_and_ps(negative, neg_div),
+                _mm_andnot_ps(negative, pos_div),
+            );
+            _mm_div_ps(_mm_cvtepi32_ps(x), div)
+        };
+        let mut i = 0;
+        while i < len {
+            let x = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+            // Sign-extend to 32 bits by placing each sample in the upper half and shifting back.
+            let lo = _mm_srai_epi32(_mm_unpacklo_epi16(x, x), 16);
+            let hi = _mm_srai_epi32(_mm_unpackhi_epi16(x, x), 16);
+            _mm_storeu_ps(output.as_mut_ptr().add(i), convert(lo));
+            _mm_storeu_ps(output.as_mut_ptr().add(i + 4), convert(hi));
+            i += 8;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn i16_to_f32_avx2(input: &[i16], output: &mut [f32]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = _mm256_setzero_si256();
+        let pos_div = _mm256_set1_ps(i16::MAX as f32);
+        let neg_div = _mm256_set1_ps(-(i16::MIN as f32));
+        let mut i = 0;
+        while i < len {
+            let x = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+            let x = _mm256_cvtepi16_epi32(x);
+            let negative = _mm256_castsi256_ps(_mm256_cmpgt_epi32(zero, x));
+            let div = _mm256_blendv_ps(pos_div, neg_div, negative);
+            let y = _mm256_div_ps(_mm256_cvtepi32_ps(x), div);
+            _mm256_storeu_ps(output.as_mut_ptr().add(i), y);
+            i += 8;
+        }
+        len
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn f32_to_i16(input: &[f32], output: &mut [i16]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = vdupq_n_f32(0.0);
+        let pos_scale = vdupq_n_f32(i16::MAX as f32);
+        let neg_scale = vdupq_n_f32(-(i16::MIN as f32));
+        let min = vdupq_n_f32(i16::MIN as f32);
+        let max = vdupq_n_f32(i16::MAX as f32);
+        let convert = |x: float32x4_t| {
+            let scale = vbslq_f32(vcgeq_f32(x, zero), pos_scale, neg_scale);
+            let scaled = vmulq_f32(x, scale);
+            let not_nan = vceqq_f32(scaled, scaled);
+            let scaled = vreinterpretq_f32_u32(vandq_u32(vreinterpretq_u32_f32(scaled), not_nan));
+            vqmovn_s32(vcvtq_s32_f32(vminq_f32(vmaxq_f32(scaled, min), max)))
+        };
+        let mut i = 0;
+        while i < len {
+            let lo = convert(vld1q_f32(input.as_ptr().add(i)));
+            let hi = convert(vld1q_f32(input.as_ptr().add(i + 4)));
+            vst1q_s16(output.as_mut_ptr().add(i), vcombine_s16(lo, hi));
+            i += 8;
+        }
+        len
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn i16_to_f32(input: &[i16], output: &mut [f32]) -> usize {
+        let len = input.len().min(output.len()) / 8 * 8;
+        let zero = vdupq_n_s32(0);
+        let pos_div = vdupq_n_f32(i16::MAX as f32);
+        let neg_div = vdupq_n_f32(-(i16::MIN as f32));
+        let convert = |x: int32x4_t| {
+            let div = vbslq_f32(vcltq_s32(x, zero), neg_div, pos_div);
+            vdivq_f32(vcvtq_f32_s32(x), div)
+        };
+        let mut i = 0;
+        while i < len {
+            let x = vld1q_s16(input.as_ptr().add(i));
+            let lo = convert(vmovl_s16(vget_low_s16(x)));
+            let hi = convert(vmovl_s16(vget_high_s16(x)));
+            vst1q_f32(output.as_mut_ptr().add(i), lo);
+            vst1q_f32(output.as_mut_ptr().add(i + 4), hi);
+            i += 8;
+        }
+        len
+    }
+}
+
+/// Reinterpret a slice of samples as a slice of type `T`.
+///
+/// Safety: `S` and `T` must have the same memory representation.
+unsafe fn cast_slice<S, T>(v: &[S]) -> &[T] {
+    debug_assert_eq!(std::mem::size_of::<S>(), std::mem::size_of::<T>());
+    std::slice::from_raw_parts(v.as_ptr() as *const T, v.len())
+}
+
+/// Reinterpret a mutable slice of samples as a mutable slice of type `T`.
+///
+/// Safety: `S` and `T` must have the same memory representation.
+unsafe fn cast_slice_mut<S, T>(v: &mut [S]) -> &mut [T] {
+    debug_assert_eq!(std::mem::size_of::<S>(), std::mem::size_of::<T>());
+    std::slice::from_raw_parts_mut(v.as_mut_ptr() as *mut T, v.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{convert_samples, convert_scalar};
+    use crate::{Sample, I24};
+
+    // Values covering the edges of both conversions, followed by an odd number of regular values
+    // so that both the vectorised loop and the scalar remainder are exercised.
+    fn f32_inputs() -> Vec<f32> {
+        let mut values = vec![
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            0.5,
+            -0.5,
+            1.5,
+            -1.5,
+            f32::NAN,
+            -f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::MIN_POSITIVE,
+            -f32::MIN_POSITIVE,
+            f32::MAX,
+            f32::MIN,
+            1.0 / 32767.0,
+            -1.0 / 32768.0,
+            0.999_999_9,
+            -0.999_999_9,
+        ];
+        values.extend((0..1001).map(|i| (i as f32 * 0.123).sin() * 1.1));
+        values
+    }
+
+    #[test]
+    fn f32_to_i16_is_bit_exact() {
+        let input = f32_inputs();
+        let mut fast = vec![0i16; input.len()];
+        let mut scalar = vec![0i16; input.len()];
+        convert_samples(&input, &mut fast);
+        convert_scalar(&input, &mut scalar);
+        for (i, (a, b)) in fast.iter().zip(scalar.iter()).enumerate() {
+            assert_eq!(a, b, "mismatch for {}", input[i]);
+        }
+    }
+
+    #[test]
+    fn i16_to_f32_is_bit_exact() {
+        let input: Vec<i16> = (i16::MIN..=i16::MAX).collect();
+        let mut fast = vec![0f32; input.len()];
+        convert_samples(&input, &mut fast);
+        for (&src, &dst) in input.iter().zip(fast.iter()) {
+            assert_eq!(
+                dst.to_bits(),
+                src.to_f32().to_bits(),
+                "mismatch for {}",
+                src
+            );
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn x86_implementations_are_bit_exact() {
+        use super::x86;
+
+        let input = f32_inputs();
+        let mut scalar = vec![0i16; input.len()];
+        convert_scalar(&input, &mut scalar);
+        let mut sse2 = vec![0i16; input.len()];
+        let done = unsafe { x86::f32_to_i16_sse2(&input, &mut sse2) };
+        assert_eq!(done, input.len() / 8 * 8);
+        assert_eq!(sse2[..done], scalar[..done]);
+
+        let ints: Vec<i16> = (i16::MIN..=i16::MAX).collect();
+        let bits = |samples: &[f32]| samples.iter().map(|s| s.to_bits()).collect::<Vec<_>>();
+        let mut scalar = vec![0f32; ints.len()];
+        convert_scalar(&ints, &mut scalar);
+        let mut sse2 = vec![0f32; ints.len()];
+        let done = unsafe { x86::i16_to_f32_sse2(&ints, &mut sse2) };
+        assert_eq!(done, ints.len());
+        assert_eq!(bits(&sse2), bits(&scalar));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_implementations_are_bit_exact() {
+        use super::x86;
+
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let input = f32_inputs();
+        let mut scalar = vec![0i16; input.len()];
+        convert_scalar(&input, &mut scalar);
+        let mut avx2 = vec![0i16; input.len()];
+        let done = unsafe { x86::f32_to_i16_avx2(&input, &mut avx2) };
+        assert_eq!(done, input.len() / 8 * 8);
+        for i in 0..done {
+            assert_eq!(avx2[i], scalar[i], "mismatch for {}", input[i]);
+        }
+
+        let ints: Vec<i16> = (i16::MIN..=i16::MAX).collect();
+        let bits = |samples: &[f32]| samples.iter().map(|s| s.to_bits()).collect::<Vec<_>>();
+        let mut scalar = vec![0f32; ints.len()];
+        convert_scalar(&ints, &mut scalar);
+        let mut avx2 = vec![0f32; ints.len()];
+        let done = unsafe { x86::i16_to_f32_avx2(&ints, &mut avx2) };
+        assert_eq!(done, ints.len());
+        assert_eq!(bits(&avx2), bits(&scalar));
+    }
+
+    #[test]
+    fn other_formats_match_sample_from() {
+        let input: Vec<u8> = (0..=255).collect();
+        let mut output = vec![I24::default(); input.len()];
+        convert_samples(&input, &mut output);
+        for (src, dst) in input.iter().zip(output.iter()) {
+            assert_eq!(*dst, src.to_i24());
+        }
+    }
+}
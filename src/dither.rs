@@ -0,0 +1,239 @@
+//! Dithering and noise shaping for reducing the bit depth of samples.
+//!
+//! Converting samples via `Sample::from` simply truncates them to the target bit depth. On quiet
+//! material this produces quantisation distortion that is correlated with the signal. A `Dither`
+//! instead adds triangular (TPDF) noise of one LSB before rounding, which decorrelates the error
+//! from the signal, and can optionally shape the spectrum of that error towards higher
+//! frequencies where it is less audible.
+
+use crate::{convert_samples, ChannelCount, Sample, SampleFormat, I24};
+
+/// The noise shaping filter applied to the quantisation error by a `Dither`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NoiseShaping {
+    /// Leave the spectrum of the dither noise flat.
+    None,
+    /// First-order error feedback. Noise rises by 6dB per octave towards Nyquist.
+    FirstOrder,
+    /// Second-order error feedback. Noise rises by 12dB per octave towards Nyquist.
+    SecondOrder,
+}
+
+/// Converts samples to a lower bit depth with TPDF dither and optional noise shaping.
+///
+/// A `Dither` keeps its random state and quantisation error history per channel, so a single
+/// instance should be used for the whole lifetime of a stream and fed with every buffer in order.
+/// Buffers are expected to contain interleaved samples, and need not end on a frame boundary.
+///
+/// Dither is only applied when the target format is an integer format with fewer bits than the
+/// source format. All other conversions are performed exactly as `convert_samples` would.
+#[derive(Clone, Debug)]
+pub struct Dither {
+    shaping: NoiseShaping,
+    rng: u32,
+    // The last two quantisation errors of each channel, most recent first.
+    errors: Vec<[f64; 2]>,
+    // The channel of the next sample to be converted.
+    channel: usize,
+}
+
+impl Dither {
+    /// Creates a new `Dither` for an interleaved stream with the given number of channels.
+    ///
+    /// **panic!**s if `channels` is 0.
+    pub fn new(channels: ChannelCount, shaping: NoiseShaping) -> Self {
+        assert!(channels > 0, "a `Dither` requires at least one channel");
+        Dither {
+            shaping,
+            rng: RNG_SEED,
+            errors: vec![[0.0; 2]; channels as usize],
+            channel: 0,
+        }
+    }
+
+    /// The number of interleaved channels this `Dither` was created for.
+    pub fn channels(&self) -> ChannelCount {
+        self.errors.len() as ChannelCount
+    }
+
+    /// The noise shaping filter applied to the quantisation error.
+    pub fn noise_shaping(&self) -> NoiseShaping {
+        self.shaping
+    }
+
+    /// Clears the quantisation error history and restarts at the first channel.
+    ///
+    /// This should be called when the stream is discontinuous, e.g. after a seek.
+    pub fn reset(&mut self) {
+        for errors in self.errors.iter_mut() {
+            *errors = [0.0; 2];
+        }
+        self.channel = 0;
+    }
+
+    /// Converts the interleaved `input` samples into `output`.
+    ///
+    /// **panic!**s if `input` and `output` have different lengths.
+    pub fn convert<S, D>(&mut self, input: &[S], output: &mut [D])
+    where
+        S: Sample,
+        D: Sample,
+    {
+        assert_eq!(
+            input.len(),
+            output.len(),
+            "input and output must have the same length"
+        );
+        let bits = match (integer_bits(S::FORMAT), integer_bits(D::FORMAT)) {
+            (_, None) => None,
+            (None, Some(dst)) => Some(dst),
+            (Some(src), Some(dst)) if src > dst => Some(dst),
+            (Some(_), Some(_)) => None,
+        };
+        let bits = match bits {
+            Some(bits) => bits,
+            None => {
+                convert_samples(input, output);
+                self.channel = (self.channel + input.len()) % self.errors.len();
+                return;
+            }
+        };
+
+        let scale = (1u64 << (bits - 1)) as f64;
+        let min = -scale;
+        let max = scale - 1.0;
+        for (dst, src) in output.iter_mut().zip(input) {
+            let value = src.to_f64() * scale;
+            let quantized = self.quantize(value, min, max);
+            *dst = from_integer(quantized as i32, bits);
+            self.channel = (self.channel + 1) % self.errors.len();
+        }
+    }
+
+    // Quantises `value`, given in LSBs of the target format, and updates the error history of the
+    // current channel.
+    fn quantize(&mut self, value: f64, min: f64, max: f64) -> f64 {
+        let errors = self.errors[self.channel];
+        let shaped = match self.shaping {
+            NoiseShaping::None => value,
+            NoiseShaping::FirstOrder => value - errors[0],
+            NoiseShaping::SecondOrder => value - 2.0 * errors[0] + errors[1],
+        };
+        let dither = self.next_random() + self.next_random() - 1.0;
+        let quantized = (shaped + dither).round();
+        let clamped = quantized.max(min).min(max);
+        self.errors[self.channel] = if clamped != quantized {
+            // Feeding back the error of a clipped sample would make the filter unstable.
+            [0.0; 2]
+        } else {
+            [clamped - shaped, errors[0]]
+        };
+        clamped
+    }
+
+    // A uniformly distributed value in `[0, 1)` from a xorshift generator.
+    fn next_random(&mut self) -> f64 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        x as f64 / 4_294_967_296.0
+    }
+}
+
+// Any non-zero value will do.
+const RNG_SEED: u32 = 0x9E37_79B9;
+
+// The number of significant bits of an integer sample format, or `None` for other formats.
+fn integer_bits(format: SampleFormat) -> Option<u32> {
+    match format {
+        SampleFormat::I8 | SampleFormat::U8 => Some(8),
+        SampleFormat::I16 | SampleFormat::U16 => Some(16),
+        SampleFormat::I24 | SampleFormat::I24Packed => Some(24),
+        SampleFormat::I32 | SampleFormat::U32 => Some(32),
+        SampleFormat::F32 | SampleFormat::F64 | SampleFormat::MuLaw | SampleFormat::ALaw => None,
+    }
+}
+
+// Converts a signed integer with the given number of significant bits into the sample type `D`
+// without any further loss of precision.
+fn from_integer<D>(value: i32, bits: u32) -> D
+where
+    D: Sample,
+{
+    match bits {
+        8 => D::from(&(value as i8)),
+        16 => D::from(&(value as i16)),
+        24 => D::from(&I24::new(value).expect("value exceeds 24 bits")),
+        _ => D::from(&value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dither, NoiseShaping};
+
+    #[test]
+    fn float_output_is_not_dithered() {
+        let input = [0.1f32, -0.5, 0.75, 1.0];
+        let mut output = [0.0f64; 4];
+        Dither::new(2, NoiseShaping::SecondOrder).convert(&input, &mut output);
+        for (&dst, &src) in output.iter().zip(input.iter()) {
+            assert_eq!(dst, src as f64);
+        }
+    }
+
+    #[test]
+    fn increasing_bit_depth_is_exact() {
+        let input = [0i16, 1, -1, i16::MAX, i16::MIN];
+        let mut output = [0i32; 5];
+        Dither::new(1, NoiseShaping::FirstOrder).convert(&input, &mut output);
+        assert_eq!(output, [0, 1 << 16, -1 << 16, 32767 << 16, i32::MIN]);
+    }
+
+    #[test]
+    fn dither_preserves_signal_below_one_lsb() {
+        // A quarter of an LSB is lost entirely when truncating, but survives on average when
+        // dithered.
+        let lsb = 1.0 / 32768.0;
+        let input = vec![0.25f32 * lsb; 4096];
+        let mut output = vec![0i16; input.len()];
+        Dither::new(1, NoiseShaping::None).convert(&input, &mut output);
+        let mean = output.iter().map(|&s| s as f64).sum::<f64>() / output.len() as f64;
+        assert!((mean - 0.25).abs() < 0.05, "mean was {}", mean);
+        assert!(output.iter().all(|&s| (-1..=2).contains(&s)));
+    }
+
+    #[test]
+    fn first_order_shaping_bounds_accumulated_error() {
+        // With first-order error feedback the total error telescopes to the most recent error, so
+        // the running sum of the error stays bounded.
+        let input: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.01).sin() * 0.3).collect();
+        let mut output = vec![0i16; input.len()];
+        Dither::new(1, NoiseShaping::FirstOrder).convert(&input, &mut output);
+        let mut accumulated = 0.0f64;
+        for (&dst, &src) in output.iter().zip(input.iter()) {
+            accumulated += dst as f64 - src as f64 * 32768.0;
+            assert!(
+                accumulated.abs() < 2.0,
+                "accumulated error was {}",
+                accumulated
+            );
+        }
+    }
+
+    #[test]
+    fn channels_keep_separate_state_across_buffers() {
+        let input: Vec<f32> = (0..64).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+        let mut whole = vec![0i16; input.len()];
+        Dither::new(2, NoiseShaping::SecondOrder).convert(&input, &mut whole);
+
+        let mut split = vec![0i16; input.len()];
+        let mut dither = Dither::new(2, NoiseShaping::SecondOrder);
+        let (first, second) = input.split_at(17);
+        dither.convert(first, &mut split[..17]);
+        dither.convert(second, &mut split[17..]);
+        assert_eq!(whole, split);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{ChannelCount, RejectedConfig};
 use thiserror::Error;
 
 /// The requested host, although supported on this platform, is unavailable.
@@ -45,6 +46,44 @@ pub enum DeviceNameError {
     },
 }
 
+/// An error that may occur while attempting to retrieve a device identifier.
+#[derive(Debug, Error)]
+pub enum DeviceIdError {
+    /// See the `BackendSpecificError` docs for more information about this error variant.
+    #[error("{err}")]
+    BackendSpecific {
+        #[from]
+        err: BackendSpecificError,
+    },
+}
+
+impl From<DeviceNameError> for DeviceIdError {
+    fn from(err: DeviceNameError) -> Self {
+        match err {
+            DeviceNameError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
+/// An error that may occur while attempting to retrieve a device description.
+#[derive(Debug, Error)]
+pub enum DeviceDescriptionError {
+    /// See the `BackendSpecificError` docs for more information about this error variant.
+    #[error("{err}")]
+    BackendSpecific {
+        #[from]
+        err: BackendSpecificError,
+    },
+}
+
+impl From<DeviceNameError> for DeviceDescriptionError {
+    fn from(err: DeviceNameError) -> Self {
+        match err {
+            DeviceNameError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
 /// Error that can happen when enumerating the list of supported formats.
 #[derive(Debug, Error)]
 pub enum SupportedStreamConfigsError {
@@ -83,6 +122,42 @@ pub enum DefaultStreamConfigError {
     },
 }
 
+/// Error that can happen when negotiating a stream configuration from a `ConfigRequest`.
+#[derive(Debug, Error)]
+pub enum NegotiateConfigError {
+    /// The device no longer exists. This can happen if the device is disconnected while the
+    /// program is running.
+    #[error("The requested device is no longer available. For example, it has been unplugged.")]
+    DeviceNotAvailable,
+    /// We called something the C-Layer did not understand
+    #[error(
+        "Invalid argument passed to the backend. For example, this happens when trying to read capture capabilities when the device does not support it."
+    )]
+    InvalidArgument,
+    /// None of the configurations supported by the device matches the request. Each supported
+    /// configuration is listed along with the reasons it was rejected.
+    #[error("None of the {} supported stream configurations matches the request", rejected.len())]
+    NoMatchingConfig { rejected: Vec<RejectedConfig> },
+    /// See the `BackendSpecificError` docs for more information about this error variant.
+    #[error("{err}")]
+    BackendSpecific {
+        #[from]
+        err: BackendSpecificError,
+    },
+}
+
+impl From<SupportedStreamConfigsError> for NegotiateConfigError {
+    fn from(err: SupportedStreamConfigsError) -> Self {
+        match err {
+            SupportedStreamConfigsError::DeviceNotAvailable => {
+                NegotiateConfigError::DeviceNotAvailable
+            }
+            SupportedStreamConfigsError::InvalidArgument => NegotiateConfigError::InvalidArgument,
+            SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
 /// Error that can happen when creating a `Stream`.
 #[derive(Debug, Error)]
 pub enum BuildStreamError {
@@ -102,6 +177,15 @@ pub enum BuildStreamError {
     /// Occurs if adding a new Stream ID would cause an integer overflow.
     #[error("Adding a new stream ID would cause an overflow")]
     StreamIdOverflow,
+    /// The channel count of the frame type of a frame-typed stream does not match the channel
+    /// count of the requested stream configuration.
+    #[error(
+        "The frame type has {frame_channels} channels but the stream configuration has {config_channels}"
+    )]
+    ChannelCountMismatch {
+        frame_channels: ChannelCount,
+        config_channels: ChannelCount,
+    },
     /// See the `BackendSpecificError` docs for more information about this error variant.
     #[error("{err}")]
     BackendSpecific {
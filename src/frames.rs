@@ -0,0 +1,274 @@
+//! Frame-oriented views of interleaved sample buffers.
+//!
+//! An interleaved buffer holds one sample per channel for each frame, one frame after the other.
+//! `Frames` and `FramesMut` wrap such a buffer along with its channel count, while the `Frame`
+//! trait describes fixed-size frame types like `[f32; 2]` whose channel count is known at compile
+//! time.
+
+use crate::{ChannelCount, Sample};
+use std::iter::StepBy;
+use std::slice::{Chunks, ChunksMut, Iter, IterMut};
+
+/// A frame type with a number of channels known at compile time.
+///
+/// This is implemented for arrays of samples with up to 32 channels, so that a buffer of
+/// interleaved samples can be viewed as a slice of frames, e.g. `&mut [[f32; 2]]` for stereo.
+///
+/// # Safety
+///
+/// The type must have the same memory representation as `CHANNELS` consecutive values of type
+/// `Self::Sample`, without any padding.
+pub unsafe trait Frame: Copy + Send + 'static {
+    /// The type of the sample of each channel.
+    type Sample: Sample;
+    /// The number of channels in the frame.
+    const CHANNELS: ChannelCount;
+}
+
+macro_rules! impl_frame {
+    ($($channels:expr),*) => {
+        $(
+            unsafe impl<S> Frame for [S; $channels]
+            where
+                S: Sample + Send + 'static,
+            {
+                type Sample = S;
+                const CHANNELS: ChannelCount = $channels;
+            }
+        )*
+    };
+}
+
+impl_frame!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32
+);
+
+/// Reinterprets a slice of interleaved samples as a slice of frames.
+///
+/// Returns `None` if the number of samples is not a multiple of `F::CHANNELS`.
+pub(crate) fn as_frames<F>(samples: &[F::Sample]) -> Option<&[F]>
+where
+    F: Frame,
+{
+    let channels = F::CHANNELS as usize;
+    if samples.len() % channels != 0 {
+        return None;
+    }
+    // Safe as `F` has the same representation as `F::CHANNELS` samples, as guaranteed by the
+    // `Frame` trait.
+    unsafe {
+        Some(std::slice::from_raw_parts(
+            samples.as_ptr() as *const F,
+            samples.len() / channels,
+        ))
+    }
+}
+
+/// Reinterprets a slice of interleaved samples as a slice of frames.
+///
+/// Returns `None` if the number of samples is not a multiple of `F::CHANNELS`.
+pub(crate) fn as_frames_mut<F>(samples: &mut [F::Sample]) -> Option<&mut [F]>
+where
+    F: Frame,
+{
+    let channels = F::CHANNELS as usize;
+    if samples.len() % channels != 0 {
+        return None;
+    }
+    // Safe as `F` has the same representation as `F::CHANNELS` samples, as guaranteed by the
+    // `Frame` trait.
+    unsafe {
+        Some(std::slice::from_raw_parts_mut(
+            samples.as_mut_ptr() as *mut F,
+            samples.len() / channels,
+        ))
+    }
+}
+
+/// A view of a buffer of interleaved samples as a sequence of frames.
+#[derive(Clone, Copy, Debug)]
+pub struct Frames<'a, T: 'a> {
+    samples: &'a [T],
+    channels: usize,
+}
+
+/// A mutable view of a buffer of interleaved samples as a sequence of frames.
+#[derive(Debug)]
+pub struct FramesMut<'a, T: 'a> {
+    samples: &'a mut [T],
+    channels: usize,
+}
+
+impl<'a, T> Frames<'a, T> {
+    /// Views `samples` as frames of `channels` samples each.
+    ///
+    /// Returns `None` if `channels` is 0 or the number of samples is not a multiple of it.
+    pub fn new(samples: &'a [T], channels: ChannelCount) -> Option<Self> {
+        let channels = channels as usize;
+        if samples.len().checked_rem(channels) != Some(0) {
+            return None;
+        }
+        Some(Frames { samples, channels })
+    }
+
+    /// The number of channels in each frame.
+    pub fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    /// The number of frames in the buffer.
+    pub fn len(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Whether or not the buffer contains no frames.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The samples of the frame at the given index, one per channel.
+    pub fn frame(&self, index: usize) -> Option<&'a [T]> {
+        let start = index.checked_mul(self.channels)?;
+        self.samples.get(start..start + self.channels)
+    }
+
+    /// An iterator yielding the samples of each frame in turn.
+    pub fn iter(&self) -> Chunks<'a, T> {
+        self.samples.chunks(self.channels)
+    }
+
+    /// An iterator yielding the samples of the given channel in each frame.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel(&self, channel: usize) -> StepBy<Iter<'a, T>> {
+        assert!(channel < self.channels, "channel out of range");
+        let samples = self.samples.get(channel..).unwrap_or(&[]);
+        samples.iter().step_by(self.channels)
+    }
+
+    /// The underlying interleaved samples.
+    pub fn as_samples(&self) -> &'a [T] {
+        self.samples
+    }
+}
+
+impl<'a, T> FramesMut<'a, T> {
+    /// Views `samples` as frames of `channels` samples each.
+    ///
+    /// Returns `None` if `channels` is 0 or the number of samples is not a multiple of it.
+    pub fn new(samples: &'a mut [T], channels: ChannelCount) -> Option<Self> {
+        let channels = channels as usize;
+        if samples.len().checked_rem(channels) != Some(0) {
+            return None;
+        }
+        Some(FramesMut { samples, channels })
+    }
+
+    /// The number of channels in each frame.
+    pub fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    /// The number of frames in the buffer.
+    pub fn len(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Whether or not the buffer contains no frames.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The samples of the frame at the given index, one per channel.
+    pub fn frame(&self, index: usize) -> Option<&[T]> {
+        let start = index.checked_mul(self.channels)?;
+        self.samples.get(start..start + self.channels)
+    }
+
+    /// The samples of the frame at the given index, one per channel.
+    pub fn frame_mut(&mut self, index: usize) -> Option<&mut [T]> {
+        let start = index.checked_mul(self.channels)?;
+        self.samples.get_mut(start..start + self.channels)
+    }
+
+    /// An iterator yielding the samples of each frame in turn.
+    pub fn iter(&self) -> Chunks<'_, T> {
+        self.samples.chunks(self.channels)
+    }
+
+    /// An iterator yielding the samples of each frame in turn.
+    pub fn iter_mut(&mut self) -> ChunksMut<'_, T> {
+        self.samples.chunks_mut(self.channels)
+    }
+
+    /// An iterator yielding the samples of the given channel in each frame.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel(&self, channel: usize) -> StepBy<Iter<'_, T>> {
+        assert!(channel < self.channels, "channel out of range");
+        let samples = self.samples.get(channel..).unwrap_or(&[]);
+        samples.iter().step_by(self.channels)
+    }
+
+    /// An iterator yielding the samples of the given channel in each frame.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel_mut(&mut self, channel: usize) -> StepBy<IterMut<'_, T>> {
+        assert!(channel < self.channels, "channel out of range");
+        let samples = self.samples.get_mut(channel..).unwrap_or(&mut []);
+        samples.iter_mut().step_by(self.channels)
+    }
+
+    /// The underlying interleaved samples.
+    pub fn as_samples(&self) -> &[T] {
+        self.samples
+    }
+
+    /// The underlying interleaved samples.
+    pub fn as_samples_mut(&mut self) -> &mut [T] {
+        self.samples
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{as_frames, as_frames_mut, Frames, FramesMut};
+
+    #[test]
+    fn frames_iterate_by_frame_and_channel() {
+        let samples = [0i16, 10, 1, 11, 2, 12];
+        let frames = Frames::new(&samples, 2).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames.frame(1), Some(&[1, 11][..]));
+        assert_eq!(frames.frame(3), None);
+        assert_eq!(frames.iter().count(), 3);
+        assert_eq!(frames.channel(1).cloned().collect::<Vec<_>>(), [10, 11, 12]);
+        assert!(Frames::new(&samples, 4).is_none());
+        assert!(Frames::new(&samples, 0).is_none());
+        let empty: [i16; 0] = [];
+        assert_eq!(Frames::new(&empty, 2).unwrap().channel(1).count(), 0);
+    }
+
+    #[test]
+    fn frames_mut_writes_per_channel() {
+        let mut samples = [0.0f32; 6];
+        {
+            let mut frames = FramesMut::new(&mut samples, 3).unwrap();
+            for sample in frames.channel_mut(2) {
+                *sample = 1.0;
+            }
+            frames.frame_mut(0).unwrap()[0] = -1.0;
+        }
+        assert_eq!(samples, [-1.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn fixed_size_frames() {
+        let mut samples = [1u16, 2, 3, 4];
+        assert_eq!(as_frames::<[u16; 2]>(&samples), Some(&[[1, 2], [3, 4]][..]));
+        assert_eq!(as_frames::<[u16; 3]>(&samples), None);
+        as_frames_mut::<[u16; 4]>(&mut samples).unwrap()[0][3] = 5;
+        assert_eq!(samples, [1, 2, 3, 5]);
+    }
+}
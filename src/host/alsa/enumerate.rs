@@ -1,17 +1,32 @@
 use super::alsa;
-use super::Device;
+use super::alsa_sys;
+use super::libc::c_int;
+use super::{card_id, card_index, parse_pcm_name, Device, Enumeration, PcmName, PcmPlugin};
+use std::cmp;
+use std::ffi::CString;
+use std::ptr;
+use std::vec::IntoIter as VecIntoIter;
 use {BackendSpecificError, DevicesError};
 
 /// ALSA implementation for `Devices`.
-pub struct Devices {
-    hint_iter: alsa::device_name::HintIter,
+pub struct Devices(DevicesInner);
+
+enum DevicesInner {
+    Hints(alsa::device_name::HintIter),
+    Hardware(VecIntoIter<Device>),
 }
 
 impl Devices {
-    pub fn new() -> Result<Self, DevicesError> {
-        Ok(Devices {
-            hint_iter: alsa::device_name::HintIter::new_str(None, "pcm")?,
-        })
+    pub fn new(enumeration: Enumeration) -> Result<Self, DevicesError> {
+        let inner = match enumeration {
+            Enumeration::Hints => {
+                DevicesInner::Hints(alsa::device_name::HintIter::new_str(None, "pcm")?)
+            }
+            Enumeration::Hardware { plugins } => {
+                DevicesInner::Hardware(hardware_devices(plugins)?.into_iter())
+            }
+        };
+        Ok(Devices(inner))
     }
 }
 
@@ -22,21 +37,15 @@ impl Iterator for Devices {
     type Item = Device;
 
     fn next(&mut self) -> Option<Device> {
+        let hint_iter = match self.0 {
+            DevicesInner::Hints(ref mut hint_iter) => hint_iter,
+            DevicesInner::Hardware(ref mut devices) => return devices.next(),
+        };
         loop {
-            match self.hint_iter.next() {
+            match hint_iter.next() {
                 None => return None,
                 Some(hint) => {
-                    let name = hint.name;
-
-                    let io = hint.direction;
-
-                    if let Some(io) = io {
-                        if io != alsa::Direction::Playback {
-                            continue;
-                        }
-                    }
-
-                    let name = match name {
+                    let name = match hint.name {
                         Some(name) => {
                             // Ignoring the `null` device.
                             if name == "null" {
@@ -47,37 +56,232 @@ impl Iterator for Devices {
                         _ => continue,
                     };
 
-                    // See if the device has an available output stream.
-                    let has_available_output = {
-                        let playback_handle =
-                            alsa::pcm::PCM::new(&name, alsa::Direction::Playback, true);
-                        playback_handle.is_ok()
-                    };
+                    // The hint tells the direction of the device, so there is no need to open it.
+                    return Some(Device {
+                        name,
+                        direction: hint.direction,
+                    });
+                }
+            }
+        }
+    }
+}
 
-                    // See if the device has an available input stream.
-                    let has_available_input = {
-                        let capture_handle =
-                            alsa::pcm::PCM::new(&name, alsa::Direction::Capture, true);
-                        capture_handle.is_ok()
-                    };
+// Walks the PCM devices of every card through its control interface. Cards that cannot be
+// queried, e.g. because they are being unplugged, are skipped.
+fn hardware_devices(plugins: bool) -> Result<Vec<Device>, alsa::Error> {
+    let mut devices = Vec::new();
+    for card in alsa::card::Iter::new() {
+        if let Ok(card_devices) = card_devices(card?.get_index(), plugins) {
+            devices.extend(card_devices);
+        }
+    }
+    Ok(devices)
+}
 
-                    if has_available_output || has_available_input {
-                        return Some(Device(name));
-                    }
-                }
+// The devices of the card with the given index.
+fn card_devices(card: c_int, plugins: bool) -> Result<Vec<Device>, alsa::Error> {
+    let id = card_id(card)?;
+    let ctl = Control::open(card)?;
+    let info = PcmInfo::new()?;
+    let mut devices = Vec::new();
+    let mut device = None;
+    while let Some(index) = ctl.next_device(device)? {
+        device = Some(index);
+        let playback = ctl.subdevices(&info, index, alsa::Direction::Playback);
+        let capture = ctl.subdevices(&info, index, alsa::Direction::Capture);
+        let names = device_names(&id, index, playback, capture, plugins);
+        devices.extend(names.into_iter().map(|(name, direction)| Device {
+            name: name.to_string(),
+            direction,
+        }));
+    }
+    Ok(devices)
+}
+
+// Lists `hw` and `plughw` names for a PCM device with the given number of playback and capture
+// subdevices and, if it has more than one, for each of its subdevices. With `plugins`, the device
+// also gets `dmix` and `dsnoop` names for the directions it supports.
+fn device_names(
+    card: &str,
+    device: u32,
+    playback: Option<u32>,
+    capture: Option<u32>,
+    plugins: bool,
+) -> Vec<(PcmName, Option<alsa::Direction>)> {
+    let mut names = Vec::new();
+    let direction = match direction_of(playback.is_some(), capture.is_some()) {
+        Some(direction) => direction,
+        None => return names,
+    };
+    let subdevices = cmp::max(playback.unwrap_or(0), capture.unwrap_or(0));
+    for &plugin in &[PcmPlugin::Hw, PcmPlugin::PlugHw] {
+        let name = PcmName::new(plugin, card).with_device(device);
+        names.push((name.clone(), direction));
+        if subdevices < 2 {
+            continue;
+        }
+        for subdevice in 0..subdevices {
+            let has_playback = playback.filter(|&n| subdevice < n).is_some();
+            let has_capture = capture.filter(|&n| subdevice < n).is_some();
+            if let Some(direction) = direction_of(has_playback, has_capture) {
+                names.push((name.clone().with_subdevice(subdevice), direction));
+            }
+        }
+    }
+    if plugins && playback.is_some() {
+        let name = PcmName::new(PcmPlugin::Dmix, card).with_device(device);
+        names.push((name, Some(alsa::Direction::Playback)));
+    }
+    if plugins && capture.is_some() {
+        let name = PcmName::new(PcmPlugin::Dsnoop, card).with_device(device);
+        names.push((name, Some(alsa::Direction::Capture)));
+    }
+    names
+}
+
+// The direction of the device with the given name, taken from its hint or, for a device of a card,
+// from the streams its control interface reports, as when listing the devices. Returns `None` if
+// the device supports both directions or its direction is unknown.
+pub(super) fn device_direction(name: &str) -> Option<alsa::Direction> {
+    if let Ok(hints) = alsa::device_name::HintIter::new_str(None, "pcm") {
+        for hint in hints {
+            if hint.name.as_deref() == Some(name) {
+                return hint.direction;
+            }
+        }
+    }
+    let (interface, args) = parse_pcm_name(name)?;
+    match interface {
+        "dmix" => return Some(alsa::Direction::Playback),
+        "dsnoop" => return Some(alsa::Direction::Capture),
+        _ => (),
+    }
+    let arg = |key| {
+        args.iter()
+            .find(|&&(k, _)| k == key)
+            .map(|&(_, value)| value)
+    };
+    let card = card_index(arg("CARD")?)?;
+    let device = arg("DEV").unwrap_or("0").parse().ok()?;
+    let subdevice: u32 = arg("SUBDEV").unwrap_or("0").parse().ok()?;
+    let ctl = Control::open(card).ok()?;
+    let info = PcmInfo::new().ok()?;
+    let has = |direction| {
+        ctl.subdevices(&info, device, direction)
+            .filter(|&n| subdevice < n)
+            .is_some()
+    };
+    let playback = has(alsa::Direction::Playback);
+    let capture = has(alsa::Direction::Capture);
+    direction_of(playback, capture).unwrap_or(None)
+}
+
+// The direction to give a device supporting playback and/or capture, or `None` if it supports
+// neither.
+fn direction_of(playback: bool, capture: bool) -> Option<Option<alsa::Direction>> {
+    match (playback, capture) {
+        (true, true) => Some(None),
+        (true, false) => Some(Some(alsa::Direction::Playback)),
+        (false, true) => Some(Some(alsa::Direction::Capture)),
+        (false, false) => None,
+    }
+}
+
+// A control handle opened through alsa-sys, as the `alsa` crate does not expose the PCM device
+// walk of the control interface.
+struct Control(*mut alsa_sys::snd_ctl_t);
+
+impl Control {
+    fn open(card: c_int) -> Result<Self, alsa::Error> {
+        let name = CString::new(format!("hw:{}", card)).expect("card names have no nul bytes");
+        let mut handle = ptr::null_mut();
+        let res = unsafe { alsa_sys::snd_ctl_open(&mut handle, name.as_ptr(), 0) };
+        check("snd_ctl_open", res)?;
+        Ok(Control(handle))
+    }
+
+    // The PCM device following the given one, or the first one for `None`.
+    fn next_device(&self, device: Option<u32>) -> Result<Option<u32>, alsa::Error> {
+        let mut device = device.map_or(-1, |device| device as c_int);
+        let res = unsafe { alsa_sys::snd_ctl_pcm_next_device(self.0, &mut device) };
+        check("snd_ctl_pcm_next_device", res)?;
+        Ok(if device < 0 {
+            None
+        } else {
+            Some(device as u32)
+        })
+    }
+
+    // The number of subdevices of the PCM device in the given direction, or `None` if the device
+    // does not support the direction.
+    fn subdevices(&self, info: &PcmInfo, device: u32, direction: alsa::Direction) -> Option<u32> {
+        let stream = match direction {
+            alsa::Direction::Playback => alsa_sys::SND_PCM_STREAM_PLAYBACK,
+            alsa::Direction::Capture => alsa_sys::SND_PCM_STREAM_CAPTURE,
+        };
+        unsafe {
+            alsa_sys::snd_pcm_info_set_device(info.0, device);
+            alsa_sys::snd_pcm_info_set_subdevice(info.0, 0);
+            alsa_sys::snd_pcm_info_set_stream(info.0, stream);
+            if alsa_sys::snd_ctl_pcm_info(self.0, info.0) < 0 {
+                return None;
             }
+            Some(alsa_sys::snd_pcm_info_get_subdevices_count(info.0))
+        }
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        unsafe {
+            alsa_sys::snd_ctl_close(self.0);
+        }
+    }
+}
+
+struct PcmInfo(*mut alsa_sys::snd_pcm_info_t);
+
+impl PcmInfo {
+    fn new() -> Result<Self, alsa::Error> {
+        let mut info = ptr::null_mut();
+        check("snd_pcm_info_malloc", unsafe {
+            alsa_sys::snd_pcm_info_malloc(&mut info)
+        })?;
+        Ok(PcmInfo(info))
+    }
+}
+
+impl Drop for PcmInfo {
+    fn drop(&mut self) {
+        unsafe {
+            alsa_sys::snd_pcm_info_free(self.0);
         }
     }
 }
 
+pub(super) fn check(func: &'static str, res: c_int) -> Result<(), alsa::Error> {
+    if res < 0 {
+        Err(alsa::Error::new(func, -res))
+    } else {
+        Ok(())
+    }
+}
+
 #[inline]
 pub fn default_input_device() -> Option<Device> {
-    Some(Device("default".to_owned()))
+    Some(Device {
+        name: "default".to_owned(),
+        direction: None,
+    })
 }
 
 #[inline]
 pub fn default_output_device() -> Option<Device> {
-    Some(Device("default".to_owned()))
+    Some(Device {
+        name: "default".to_owned(),
+        direction: None,
+    })
 }
 
 impl From<alsa::Error> for DevicesError {
@@ -86,3 +290,58 @@ impl From<alsa::Error> for DevicesError {
         err.into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::alsa::Direction;
+    use super::{device_names, direction_of, PcmPlugin};
+
+    #[test]
+    fn directions_follow_the_supported_streams() {
+        assert_eq!(direction_of(true, true), Some(None));
+        assert_eq!(direction_of(true, false), Some(Some(Direction::Playback)));
+        assert_eq!(direction_of(false, true), Some(Some(Direction::Capture)));
+        assert_eq!(direction_of(false, false), None);
+    }
+
+    #[test]
+    fn devices_are_named_per_plugin() {
+        let names: Vec<_> = device_names("PCH", 0, Some(1), Some(1), true)
+            .into_iter()
+            .map(|(name, direction)| (name.to_string(), direction))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("hw:CARD=PCH,DEV=0".to_owned(), None),
+                ("plughw:CARD=PCH,DEV=0".to_owned(), None),
+                ("dmix:CARD=PCH,DEV=0".to_owned(), Some(Direction::Playback)),
+                ("dsnoop:CARD=PCH,DEV=0".to_owned(), Some(Direction::Capture)),
+            ]
+        );
+        assert!(device_names("PCH", 0, None, None, true).is_empty());
+    }
+
+    #[test]
+    fn subdevices_are_listed_per_direction() {
+        let names: Vec<_> = device_names("USB", 1, Some(3), Some(2), false)
+            .into_iter()
+            .filter(|(name, _)| name.plugin() == PcmPlugin::Hw)
+            .map(|(name, direction)| (name.to_string(), direction))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("hw:CARD=USB,DEV=1".to_owned(), None),
+                ("hw:CARD=USB,DEV=1,SUBDEV=0".to_owned(), None),
+                ("hw:CARD=USB,DEV=1,SUBDEV=1".to_owned(), None),
+                (
+                    "hw:CARD=USB,DEV=1,SUBDEV=2".to_owned(),
+                    Some(Direction::Playback)
+                ),
+            ]
+        );
+        let names = device_names("USB", 1, Some(1), None, false);
+        assert_eq!(names.len(), 2);
+    }
+}
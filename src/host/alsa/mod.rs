@@ -1,35 +1,61 @@
 extern crate alsa;
+extern crate alsa_sys;
 extern crate libc;
 
 use self::alsa::poll::Descriptors;
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
-    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, ChannelLayout,
+    ChannelPosition, Data, DefaultStreamConfigError, DeviceDescription, DeviceDescriptionError,
+    DeviceId, DeviceIdError, DeviceInterface, DeviceKind, DeviceNameError, DevicesError,
+    EffectiveConfig, Endianness, FrameCount, InputCallbackInfo, LatencyProfile, OutputCallbackInfo,
+    PauseStreamError, PlanarData, PlayStreamError, SampleFormat, SampleRate, StreamConfig,
+    StreamError, SupportedBufferSize, SupportedPeriods, SupportedSampleRates,
+    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::cmp;
-use std::convert::TryInto;
+use std::ffi::CString;
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use std::vec::IntoIter as VecIntoIter;
 use traits::{DeviceTrait, HostTrait, StreamTrait};
 
+use self::enumerate::device_direction;
 pub use self::enumerate::{default_input_device, default_output_device, Devices};
+pub use self::name::{PcmName, PcmPlugin};
 
 pub type SupportedInputConfigs = VecIntoIter<SupportedStreamConfigRange>;
 pub type SupportedOutputConfigs = VecIntoIter<SupportedStreamConfigRange>;
 
 mod enumerate;
+mod name;
+mod planar;
 
 /// The default linux, dragonfly and freebsd host type.
 #[derive(Debug)]
-pub struct Host;
+pub struct Host {
+    enumeration: Enumeration,
+}
+
+/// How the ALSA host enumerates devices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Enumeration {
+    /// Lists the PCMs named by the hints of the ALSA configuration, such as `default` and
+    /// `front:CARD=PCH,DEV=0`.
+    Hints,
+    /// Walks the cards through their control interfaces, listing `hw` and `plughw` PCMs for every
+    /// device and subdevice, along with `dmix` and `dsnoop` PCMs for every device if `plugins` is
+    /// set.
+    Hardware { plugins: bool },
+}
 
 impl Host {
     pub fn new() -> Result<Self, crate::HostUnavailable> {
-        Ok(Host)
+        Ok(Host::with_enumeration(Enumeration::Hints))
+    }
+
+    /// A host enumerating devices the given way.
+    pub fn with_enumeration(enumeration: Enumeration) -> Self {
+        Host { enumeration }
     }
 }
 
@@ -43,7 +69,7 @@ impl HostTrait for Host {
     }
 
     fn devices(&self) -> Result<Self::Devices, DevicesError> {
-        Devices::new()
+        Devices::new(self.enumeration)
     }
 
     fn default_input_device(&self) -> Option<Self::Device> {
@@ -53,6 +79,29 @@ impl HostTrait for Host {
     fn default_output_device(&self) -> Option<Self::Device> {
         default_output_device()
     }
+
+    fn device_by_id(&self, id: &DeviceId) -> Option<Self::Device> {
+        let listed = self
+            .devices()
+            .ok()
+            .and_then(|mut devices| devices.find(|device| device.id().ok().as_ref() == Some(id)));
+        // Identifiers are PCM names with the card given by its path, so devices missing from the
+        // hints can still be opened by them once the card is found.
+        // The device is not opened, as that fails while another stream uses it.
+        listed.or_else(|| {
+            let name = pcm_name_of_canonical(id.as_str()).ok()?;
+            // A card still given by its path is not plugged in.
+            let unplugged = parse_pcm_name(&name).is_some_and(|(_, args)| {
+                args.iter()
+                    .any(|&(key, value)| key == "CARD" && value.contains('/'))
+            });
+            if unplugged {
+                return None;
+            }
+            let direction = device_direction(&name);
+            Some(Device { name, direction })
+        })
+    }
 }
 
 impl DeviceTrait for Device {
@@ -64,6 +113,22 @@ impl DeviceTrait for Device {
         Device::name(self)
     }
 
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Device::id(self)
+    }
+
+    fn description(&self) -> Result<DeviceDescription, DeviceDescriptionError> {
+        Device::description(self)
+    }
+
+    fn supports_input(&self) -> bool {
+        self.direction != Some(alsa::Direction::Playback)
+    }
+
+    fn supports_output(&self) -> bool {
+        self.direction != Some(alsa::Direction::Capture)
+    }
+
     fn supported_input_configs(
         &self,
     ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
@@ -117,6 +182,34 @@ impl DeviceTrait for Device {
         let stream = Stream::new_output(Arc::new(stream_inner), data_callback, error_callback);
         Ok(stream)
     }
+
+    fn build_input_stream_planar<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        planar::build_input_stream(self, conf, sample_format, data_callback, error_callback)
+    }
+
+    fn build_output_stream_planar<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        planar::build_output_stream(self, conf, sample_format, data_callback, error_callback)
+    }
 }
 
 struct TriggerSender(libc::c_int);
@@ -164,7 +257,11 @@ impl Drop for TriggerReceiver {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Device(String);
+pub struct Device {
+    name: String,
+    // The direction given by the device hint, or `None` if the device supports both.
+    direction: Option<alsa::Direction>,
+}
 
 impl Device {
     fn build_stream_inner(
@@ -173,24 +270,40 @@ impl Device {
         sample_format: SampleFormat,
         stream_type: alsa::Direction,
     ) -> Result<StreamInner, BuildStreamError> {
-        let name = &self.0;
+        let name = &self.name;
 
-        let handle = match alsa::pcm::PCM::new(name, stream_type, true).map_err(|e| (e, e.errno()))
-        {
-            Err((_, Some(nix::errno::Errno::EBUSY))) => {
-                return Err(BuildStreamError::DeviceNotAvailable)
-            }
-            Err((_, Some(nix::errno::Errno::EINVAL))) => {
-                return Err(BuildStreamError::InvalidArgument)
-            }
-            Err((e, _)) => return Err(e.into()),
-            Ok(handle) => handle,
-        };
-        let can_pause = {
-            let hw_params = set_hw_params_from_format(&handle, conf, sample_format)?;
-            hw_params.can_pause()
+        if !conf.channel_selection_is_valid() {
+            return Err(BuildStreamError::InvalidArgument);
+        }
+
+        let handle = alsa::pcm::PCM::new(name, stream_type, true).map_err(open_error)?;
+        let (can_pause, swap_bytes, mut effective) = {
+            let (hw_params, endianness) = set_hw_params_from_format(&handle, conf, sample_format)?;
+            let effective = StreamConfig {
+                channels: hw_params.get_channels()? as ChannelCount,
+                sample_rate: SampleRate(hw_params.get_rate()?),
+                buffer_size: BufferSize::Fixed(hw_params.get_buffer_size()? as FrameCount),
+                channel_layout: None,
+                channel_selection: conf.channel_selection.clone(),
+                period_size: Some(hw_params.get_period_size()? as FrameCount),
+                period_count: Some(hw_params.get_periods()?),
+                strict: conf.strict,
+            };
+            (hw_params.can_pause(), !endianness.is_native(), effective)
         };
-        let (_buffer_len, period_len) = set_sw_params_from_format(&handle, conf)?;
+        if conf.strict && !is_exact(conf, &effective) {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        if let Some(ref layout) = conf.channel_layout {
+            if layout.channels() != conf.channels {
+                return Err(BuildStreamError::InvalidArgument);
+            }
+            set_channel_layout(&handle, layout)?;
+        }
+        effective.channel_layout = handle.get_chmap().ok().and_then(|map| parse_chmap(&map));
+        let (buffer_len, period_len) = set_sw_params_from_format(&handle, &effective)?;
+        let buffer_frames = buffer_len / effective.channels as usize;
+        let latency = frames_to_duration(buffer_frames, effective.sample_rate);
 
         handle.prepare()?;
 
@@ -218,9 +331,11 @@ impl Device {
             channel: handle,
             sample_format,
             num_descriptors,
-            conf: conf.clone(),
+            conf: effective,
+            latency,
             period_len,
             can_pause,
+            swap_bytes,
             creation_instant,
         };
 
@@ -229,14 +344,24 @@ impl Device {
 
     #[inline]
     fn name(&self) -> Result<String, DeviceNameError> {
-        Ok(self.0.clone())
+        Ok(self.name.clone())
+    }
+
+    // Names refer to cards by index or id, both of which change with the order cards are probed
+    // in, so the identifier is the name with its card given by its sysfs path.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(canonical_pcm_name(&self.name)?))
+    }
+
+    fn description(&self) -> Result<DeviceDescription, DeviceDescriptionError> {
+        Ok(describe_pcm(&self.name)?)
     }
 
     fn supported_configs(
         &self,
         stream_t: alsa::Direction,
     ) -> Result<VecIntoIter<SupportedStreamConfigRange>, SupportedStreamConfigsError> {
-        let name = &self.0;
+        let name = &self.name;
 
         let handle = match alsa::pcm::PCM::new(name, stream_t, true).map_err(|e| (e, e.errno())) {
             Err((_, Some(nix::errno::Errno::ENOENT)))
@@ -252,36 +377,29 @@ impl Device {
 
         let hw_params = alsa::pcm::HwParams::any(&handle)?;
 
-        // TODO: check endianess
-        const FORMATS: [(SampleFormat, alsa::pcm::Format); 3] = [
-            //SND_PCM_FORMAT_S8,
-            //SND_PCM_FORMAT_U8,
-            (SampleFormat::I16, alsa::pcm::Format::S16LE),
-            //SND_PCM_FORMAT_S16_BE,
-            (SampleFormat::U16, alsa::pcm::Format::U16LE),
-            //SND_PCM_FORMAT_U16_BE,
-            //SND_PCM_FORMAT_S24_LE,
-            //SND_PCM_FORMAT_S24_BE,
+        // Each format is probed in native byte order first. Devices that only accept the opposite
+        // byte order are still reported, and the stream worker swaps the bytes of each sample.
+        const FORMATS: [SampleFormat; 12] = [
+            SampleFormat::I8,
+            SampleFormat::U8,
+            SampleFormat::I16,
+            SampleFormat::U16,
+            SampleFormat::I24,
+            SampleFormat::I24Packed,
             //SND_PCM_FORMAT_U24_LE,
             //SND_PCM_FORMAT_U24_BE,
-            //SND_PCM_FORMAT_S32_LE,
-            //SND_PCM_FORMAT_S32_BE,
-            //SND_PCM_FORMAT_U32_LE,
-            //SND_PCM_FORMAT_U32_BE,
-            (SampleFormat::F32, alsa::pcm::Format::FloatLE),
-            //SND_PCM_FORMAT_FLOAT_BE,
-            //SND_PCM_FORMAT_FLOAT64_LE,
-            //SND_PCM_FORMAT_FLOAT64_BE,
+            SampleFormat::I32,
+            SampleFormat::U32,
+            SampleFormat::F32,
+            SampleFormat::F64,
+            SampleFormat::MuLaw,
+            SampleFormat::ALaw,
             //SND_PCM_FORMAT_IEC958_SUBFRAME_LE,
             //SND_PCM_FORMAT_IEC958_SUBFRAME_BE,
-            //SND_PCM_FORMAT_MU_LAW,
-            //SND_PCM_FORMAT_A_LAW,
             //SND_PCM_FORMAT_IMA_ADPCM,
             //SND_PCM_FORMAT_MPEG,
             //SND_PCM_FORMAT_GSM,
             //SND_PCM_FORMAT_SPECIAL,
-            //SND_PCM_FORMAT_S24_3LE,
-            //SND_PCM_FORMAT_S24_3BE,
             //SND_PCM_FORMAT_U24_3LE,
             //SND_PCM_FORMAT_U24_3BE,
             //SND_PCM_FORMAT_S20_3LE,
@@ -295,9 +413,9 @@ impl Device {
         ];
 
         let mut supported_formats = Vec::new();
-        for &(sample_format, alsa_format) in FORMATS.iter() {
-            if hw_params.test_format(alsa_format).is_ok() {
-                supported_formats.push(sample_format);
+        for &sample_format in FORMATS.iter() {
+            if let Some(endianness) = supported_endianness(&hw_params, sample_format) {
+                supported_formats.push((sample_format, endianness));
             }
         }
 
@@ -305,40 +423,48 @@ impl Device {
         let max_rate = hw_params.get_rate_max()?;
 
         let sample_rates = if min_rate == max_rate || hw_params.test_rate(min_rate + 1).is_ok() {
-            vec![(min_rate, max_rate)]
+            SupportedSampleRates::Range {
+                min: SampleRate(min_rate),
+                max: SampleRate(max_rate),
+            }
         } else {
             const RATES: [libc::c_uint; 13] = [
                 5512, 8000, 11025, 16000, 22050, 32000, 44100, 48000, 64000, 88200, 96000, 176400,
                 192000,
             ];
 
-            let mut rates = Vec::new();
-            for &rate in RATES.iter() {
-                if hw_params.test_rate(rate).is_ok() {
-                    rates.push((rate, rate));
-                }
-            }
+            let rates: Vec<_> = RATES
+                .iter()
+                .filter(|&&rate| hw_params.test_rate(rate).is_ok())
+                .map(|&rate| SampleRate(rate))
+                .collect();
 
-            if rates.len() == 0 {
-                vec![(min_rate, max_rate)]
+            if rates.is_empty() {
+                SupportedSampleRates::Range {
+                    min: SampleRate(min_rate),
+                    max: SampleRate(max_rate),
+                }
             } else {
-                rates
+                SupportedSampleRates::Discrete(rates)
             }
         };
 
-        let min_channels = hw_params.get_channels_min()?;
-        let max_channels = hw_params.get_channels_max()?;
+        let max_channel_count = ChannelCount::MAX as libc::c_uint;
+        let min_channels = cmp::min(hw_params.get_channels_min()?, max_channel_count);
+        let max_channels = cmp::min(hw_params.get_channels_max()?, max_channel_count);
+        let channel_ranges = channel_ranges(min_channels, max_channels, |channels| {
+            hw_params.test_channels(channels).is_ok()
+        });
 
-        let max_channels = cmp::min(max_channels, 32); // TODO: limiting to 32 channels or too much stuff is returned
-        let supported_channels = (min_channels..max_channels + 1)
-            .filter_map(|num| {
-                if hw_params.test_channels(num).is_ok() {
-                    Some(num as ChannelCount)
-                } else {
-                    None
-                }
+        // Devices without channel maps return none.
+        let channel_layouts: Vec<_> = handle
+            .query_chmaps()
+            .filter_map(|(_, map)| parse_chmap(&map))
+            .filter(|layout| {
+                let channels = layout.channels() as libc::c_uint;
+                min_channels <= channels && channels <= max_channels
             })
-            .collect::<Vec<_>>();
+            .collect();
 
         let min_buffer_size = hw_params.get_buffer_size_min()?;
         let max_buffer_size = hw_params.get_buffer_size_max()?;
@@ -348,20 +474,38 @@ impl Device {
             max: max_buffer_size as u32,
         };
 
-        let mut output = Vec::with_capacity(
-            supported_formats.len() * supported_channels.len() * sample_rates.len(),
-        );
-        for &sample_format in supported_formats.iter() {
-            for channels in supported_channels.iter() {
-                for &(min_rate, max_rate) in sample_rates.iter() {
-                    output.push(SupportedStreamConfigRange {
-                        channels: channels.clone(),
-                        min_sample_rate: SampleRate(min_rate as u32),
-                        max_sample_rate: SampleRate(max_rate as u32),
-                        buffer_size: buffer_size_range.clone(),
-                        sample_format: sample_format,
-                    });
-                }
+        let min_period_size = hw_params.get_period_size_min()?;
+        let max_period_size = hw_params.get_period_size_max()?;
+        let periods = match period_count_range(&hw_params)? {
+            Some((min_count, max_count)) => SupportedPeriods::Range {
+                min_size: min_period_size as u32,
+                max_size: max_period_size as u32,
+                min_count,
+                max_count,
+            },
+            None => SupportedPeriods::Unknown,
+        };
+
+        let mut output = Vec::with_capacity(supported_formats.len() * channel_ranges.len());
+        for &(sample_format, endianness) in supported_formats.iter() {
+            for &(min_channels, max_channels) in channel_ranges.iter() {
+                let channel_layouts = channel_layouts
+                    .iter()
+                    .filter(|layout| {
+                        min_channels <= layout.channels() && layout.channels() <= max_channels
+                    })
+                    .cloned()
+                    .collect();
+                output.push(SupportedStreamConfigRange {
+                    min_channels,
+                    max_channels,
+                    sample_rates: sample_rates.clone(),
+                    buffer_size: buffer_size_range.clone(),
+                    periods: periods.clone(),
+                    sample_format,
+                    endianness,
+                    channel_layouts,
+                });
             }
         }
 
@@ -407,13 +551,17 @@ impl Device {
 
         match formats.into_iter().last() {
             Some(f) => {
-                let min_r = f.min_sample_rate;
-                let max_r = f.max_sample_rate;
-                let mut format = f.with_max_sample_rate();
                 const HZ_44100: SampleRate = SampleRate(44_100);
-                if min_r <= HZ_44100 && HZ_44100 <= max_r {
-                    format.sample_rate = HZ_44100;
-                }
+                let sample_rate = if f.supports_sample_rate(HZ_44100) {
+                    HZ_44100
+                } else {
+                    f.max_sample_rate()
+                };
+                let channels = f.default_channels();
+                let format = f
+                    .with_channels(channels)
+                    .and_then(|f| f.try_with_sample_rate(sample_rate))
+                    .expect("default channels and sample rate are supported");
                 Ok(format)
             }
             None => Err(DefaultStreamConfigError::StreamTypeNotSupported),
@@ -440,9 +588,16 @@ struct StreamInner {
     // Format of the samples.
     sample_format: SampleFormat,
 
-    // The configuration used to open this stream.
+    // Whether the device was opened in non-native byte order, in which case the bytes of every
+    // sample are swapped between the device and the user's callback.
+    swap_bytes: bool,
+
+    // The configuration the device settled on, including its channel map.
     conf: StreamConfig,
 
+    // The time it takes to play or record the whole buffer the device settled on.
+    latency: std::time::Duration,
+
     // Minimum number of samples to put in the buffer.
     period_len: usize,
 
@@ -477,16 +632,57 @@ pub struct Stream {
     thread: Option<JoinHandle<()>>,
 
     /// Handle to the underlying stream for playback controls.
-    inner: Arc<StreamInner>,
+    inner: StreamHandle,
 
     /// Used to signal to stop processing.
     trigger: TriggerSender,
 }
 
+// The PCM of a stream, opened through the `alsa` crate with interleaved access or, for planar
+// streams, through alsa-sys with non-interleaved access.
+enum StreamHandle {
+    Interleaved(Arc<StreamInner>),
+    Planar(Arc<planar::StreamInner>),
+}
+
+impl StreamHandle {
+    fn pause(&self, pause: bool) {
+        match *self {
+            StreamHandle::Interleaved(ref inner) => {
+                inner.channel.pause(pause).ok();
+            }
+            StreamHandle::Planar(ref inner) => inner.pcm.pause(pause),
+        }
+    }
+
+    fn conf(&self) -> &StreamConfig {
+        match *self {
+            StreamHandle::Interleaved(ref inner) => &inner.conf,
+            StreamHandle::Planar(ref inner) => &inner.conf,
+        }
+    }
+
+    fn sample_format(&self) -> SampleFormat {
+        match *self {
+            StreamHandle::Interleaved(ref inner) => inner.sample_format,
+            StreamHandle::Planar(ref inner) => inner.sample_format,
+        }
+    }
+
+    fn latency(&self) -> std::time::Duration {
+        match *self {
+            StreamHandle::Interleaved(ref inner) => inner.latency,
+            StreamHandle::Planar(ref inner) => inner.latency,
+        }
+    }
+}
+
 #[derive(Default)]
 struct StreamWorkerContext {
     descriptors: Vec<libc::pollfd>,
     buffer: Vec<u8>,
+    // The selected channels of `buffer`, if the stream selects a subset of the device's channels.
+    selected: Vec<u8>,
 }
 
 fn input_stream_worker(
@@ -517,13 +713,7 @@ fn input_stream_worker(
                     StreamType::Input,
                     "expected input stream, but polling descriptors indicated output",
                 );
-                let res = process_input(
-                    stream,
-                    &mut ctxt.buffer,
-                    status,
-                    delay_frames,
-                    data_callback,
-                );
+                let res = process_input(stream, &mut ctxt, status, delay_frames, data_callback);
                 report_error(res, error_callback);
             }
         }
@@ -560,7 +750,7 @@ fn output_stream_worker(
                 );
                 let res = process_output(
                     stream,
-                    &mut ctxt.buffer,
+                    &mut ctxt,
                     status,
                     avail_frames,
                     delay_frames,
@@ -609,6 +799,7 @@ fn poll_descriptors_and_prepare_buffer(
     let StreamWorkerContext {
         ref mut descriptors,
         ref mut buffer,
+        ref mut selected,
     } = *ctxt;
 
     descriptors.clear();
@@ -672,6 +863,10 @@ fn poll_descriptors_and_prepare_buffer(
     // Prepare the data buffer.
     let buffer_size = stream.sample_format.sample_size() * available_samples;
     buffer.resize(buffer_size, 0u8);
+    if let Some(ref selection) = stream.conf.channel_selection {
+        let selected_size = stream.sample_format.sample_size() * avail_frames * selection.len();
+        selected.resize(selected_size, 0u8);
+    }
 
     Ok(PollDescriptorsFlow::Ready {
         stream_type,
@@ -684,13 +879,35 @@ fn poll_descriptors_and_prepare_buffer(
 // Read input data from ALSA and deliver it to the user.
 fn process_input(
     stream: &StreamInner,
-    buffer: &mut [u8],
+    ctxt: &mut StreamWorkerContext,
     status: alsa::pcm::Status,
     delay_frames: usize,
     data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static),
 ) -> Result<(), BackendSpecificError> {
-    stream.channel.io().readi(buffer)?;
+    let StreamWorkerContext {
+        ref mut buffer,
+        ref mut selected,
+        ..
+    } = *ctxt;
+    stream.channel.io_bytes().readi(buffer)?;
     let sample_format = stream.sample_format;
+    if stream.swap_bytes {
+        swap_sample_bytes(buffer, sample_format.sample_size());
+    }
+    let buffer = match stream.conf.channel_selection {
+        Some(ref selection) => {
+            let sample_size = sample_format.sample_size();
+            select_channels(
+                buffer,
+                selected,
+                stream.conf.channels,
+                selection,
+                sample_size,
+            );
+            selected
+        }
+        None => buffer,
+    };
     let data = buffer.as_mut_ptr() as *mut ();
     let len = buffer.len() / sample_format.sample_size();
     let data = unsafe { Data::from_parts(data, len, sample_format) };
@@ -711,16 +928,25 @@ fn process_input(
 // Returns `true`
 fn process_output(
     stream: &StreamInner,
-    buffer: &mut [u8],
+    ctxt: &mut StreamWorkerContext,
     status: alsa::pcm::Status,
     available_frames: usize,
     delay_frames: usize,
     data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static),
     error_callback: &mut dyn FnMut(StreamError),
 ) -> Result<(), BackendSpecificError> {
+    let StreamWorkerContext {
+        ref mut buffer,
+        ref mut selected,
+        ..
+    } = *ctxt;
+    let sample_format = stream.sample_format;
     {
         // We're now sure that we're ready to write data.
-        let sample_format = stream.sample_format;
+        let buffer = match stream.conf.channel_selection {
+            Some(_) => &mut *selected,
+            None => &mut *buffer,
+        };
         let data = buffer.as_mut_ptr() as *mut ();
         let len = buffer.len() / sample_format.sample_size();
         let mut data = unsafe { Data::from_parts(data, len, sample_format) };
@@ -733,8 +959,23 @@ fn process_output(
         let info = crate::OutputCallbackInfo { timestamp };
         data_callback(&mut data, &info);
     }
+    if let Some(ref selection) = stream.conf.channel_selection {
+        // Channels that are not selected are kept silent.
+        sample_format.fill_silence(buffer);
+        let sample_size = sample_format.sample_size();
+        spread_channels(
+            selected,
+            buffer,
+            stream.conf.channels,
+            selection,
+            sample_size,
+        );
+    }
+    if stream.swap_bytes {
+        swap_sample_bytes(buffer, sample_format.sample_size());
+    }
     loop {
-        match stream.channel.io().writei(buffer) {
+        match stream.channel.io_bytes().writei(buffer) {
             Err(err) if err.errno() == Some(nix::errno::Errno::EPIPE) => {
                 // buffer underrun
                 // TODO: Notify the user of this.
@@ -767,19 +1008,23 @@ fn process_output(
 fn stream_timestamp(
     status: &alsa::pcm::Status,
     creation_instant: Option<std::time::Instant>,
+) -> Result<crate::StreamInstant, BackendSpecificError> {
+    elapsed_timestamp(
+        status.get_htstamp(),
+        status.get_trigger_htstamp(),
+        creation_instant,
+    )
+}
+
+// The timestamp of a status taken at `ts` of a stream triggered at `trigger_ts`.
+fn elapsed_timestamp(
+    ts: libc::timespec,
+    trigger_ts: libc::timespec,
+    creation_instant: Option<std::time::Instant>,
 ) -> Result<crate::StreamInstant, BackendSpecificError> {
     match creation_instant {
         None => {
-            let trigger_ts = status.get_trigger_htstamp();
-            let ts = status.get_htstamp();
-            let nanos = timespec_diff_nanos(ts, trigger_ts)
-                .try_into()
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "get_htstamp `{:?}` was earlier than get_trigger_htstamp `{:?}`",
-                        ts, trigger_ts
-                    );
-                });
+            let nanos = timespec_diff_nanos(ts, trigger_ts);
             Ok(crate::StreamInstant::from_nanos(nanos))
         }
         Some(creation) => {
@@ -794,6 +1039,9 @@ fn stream_timestamp(
 
 // Adapted from `timestamp2ns` here:
 // https://fossies.org/linux/alsa-lib/test/audio_time.c
+//
+// `time_t` and `c_long` are only 32 bits wide on some targets, hence the casts.
+#[allow(clippy::unnecessary_cast)]
 fn timespec_to_nanos(ts: libc::timespec) -> i64 {
     ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64
 }
@@ -826,11 +1074,11 @@ impl Stream {
         // Clone the handle for passing into worker thread.
         let stream = inner.clone();
         let thread = thread::spawn(move || {
-            input_stream_worker(rx, &*stream, &mut data_callback, &mut error_callback);
+            input_stream_worker(rx, &stream, &mut data_callback, &mut error_callback);
         });
         Stream {
             thread: Some(thread),
-            inner,
+            inner: StreamHandle::Interleaved(inner),
             trigger: tx,
         }
     }
@@ -848,11 +1096,11 @@ impl Stream {
         // Clone the handle for passing into worker thread.
         let stream = inner.clone();
         let thread = thread::spawn(move || {
-            output_stream_worker(rx, &*stream, &mut data_callback, &mut error_callback);
+            output_stream_worker(rx, &stream, &mut data_callback, &mut error_callback);
         });
         Stream {
             thread: Some(thread),
-            inner,
+            inner: StreamHandle::Interleaved(inner),
             trigger: tx,
         }
     }
@@ -867,54 +1115,263 @@ impl Drop for Stream {
 
 impl StreamTrait for Stream {
     fn play(&self) -> Result<(), PlayStreamError> {
-        self.inner.channel.pause(false).ok();
+        self.inner.pause(false);
         Ok(())
     }
     fn pause(&self) -> Result<(), PauseStreamError> {
-        self.inner.channel.pause(true).ok();
+        self.inner.pause(true);
         Ok(())
     }
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.inner.conf().channel_layout.clone()
+    }
+    fn latency(&self) -> Option<std::time::Duration> {
+        Some(self.inner.latency())
+    }
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        Some(EffectiveConfig {
+            config: self.inner.conf().clone(),
+            sample_format: self.inner.sample_format(),
+        })
+    }
 }
 
-fn set_hw_params_from_format<'a>(
-    pcm_handle: &'a alsa::pcm::PCM,
-    config: &StreamConfig,
-    sample_format: SampleFormat,
-) -> Result<alsa::pcm::HwParams<'a>, BackendSpecificError> {
-    let hw_params = alsa::pcm::HwParams::any(pcm_handle)?;
-    hw_params.set_access(alsa::pcm::Access::RWInterleaved)?;
+// The error to report when a PCM cannot be opened.
+fn open_error(err: alsa::Error) -> BuildStreamError {
+    match err.errno() {
+        Some(nix::errno::Errno::EBUSY) => BuildStreamError::DeviceNotAvailable,
+        Some(nix::errno::Errno::EINVAL) => BuildStreamError::InvalidArgument,
+        _ => err.into(),
+    }
+}
+
+// Reverses the byte order of every sample in the given buffer.
+fn swap_sample_bytes(buffer: &mut [u8], sample_size: usize) {
+    for sample in buffer.chunks_exact_mut(sample_size) {
+        sample.reverse();
+    }
+}
+
+// Copies the selected channels of each interleaved frame in `device` into `selected`.
+fn select_channels(
+    device: &[u8],
+    selected: &mut [u8],
+    channels: ChannelCount,
+    selection: &[ChannelCount],
+    sample_size: usize,
+) {
+    let frames = device.chunks(channels as usize * sample_size);
+    let selected_frames = selected.chunks_mut(selection.len() * sample_size);
+    for (frame, selected_frame) in frames.zip(selected_frames) {
+        for (sample, &channel) in selected_frame.chunks_mut(sample_size).zip(selection) {
+            let start = channel as usize * sample_size;
+            sample.copy_from_slice(&frame[start..start + sample_size]);
+        }
+    }
+}
+
+// Copies each frame of selected channels in `selected` into its channels in `device`, leaving the
+// other channels untouched.
+fn spread_channels(
+    selected: &[u8],
+    device: &mut [u8],
+    channels: ChannelCount,
+    selection: &[ChannelCount],
+    sample_size: usize,
+) {
+    let frames = device.chunks_mut(channels as usize * sample_size);
+    let selected_frames = selected.chunks(selection.len() * sample_size);
+    for (frame, selected_frame) in frames.zip(selected_frames) {
+        for (sample, &channel) in selected_frame.chunks(sample_size).zip(selection) {
+            let start = channel as usize * sample_size;
+            frame[start..start + sample_size].copy_from_slice(sample);
+        }
+    }
+}
 
-    let sample_format = if cfg!(target_endian = "big") {
-        match sample_format {
+// The ALSA format corresponding to the given sample format in the given byte order.
+fn alsa_format(sample_format: SampleFormat, endianness: Endianness) -> alsa::pcm::Format {
+    match endianness {
+        Endianness::Big => match sample_format {
+            SampleFormat::I8 => alsa::pcm::Format::S8,
+            SampleFormat::U8 => alsa::pcm::Format::U8,
             SampleFormat::I16 => alsa::pcm::Format::S16BE,
             SampleFormat::U16 => alsa::pcm::Format::U16BE,
+            SampleFormat::I24 => alsa::pcm::Format::S24BE,
+            SampleFormat::I24Packed => alsa::pcm::Format::S243BE,
+            SampleFormat::I32 => alsa::pcm::Format::S32BE,
+            SampleFormat::U32 => alsa::pcm::Format::U32BE,
             SampleFormat::F32 => alsa::pcm::Format::FloatBE,
-        }
-    } else {
-        match sample_format {
+            SampleFormat::F64 => alsa::pcm::Format::Float64BE,
+            SampleFormat::MuLaw => alsa::pcm::Format::MuLaw,
+            SampleFormat::ALaw => alsa::pcm::Format::ALaw,
+        },
+        Endianness::Little => match sample_format {
+            SampleFormat::I8 => alsa::pcm::Format::S8,
+            SampleFormat::U8 => alsa::pcm::Format::U8,
             SampleFormat::I16 => alsa::pcm::Format::S16LE,
             SampleFormat::U16 => alsa::pcm::Format::U16LE,
+            SampleFormat::I24 => alsa::pcm::Format::S24LE,
+            SampleFormat::I24Packed => alsa::pcm::Format::S243LE,
+            SampleFormat::I32 => alsa::pcm::Format::S32LE,
+            SampleFormat::U32 => alsa::pcm::Format::U32LE,
             SampleFormat::F32 => alsa::pcm::Format::FloatLE,
+            SampleFormat::F64 => alsa::pcm::Format::Float64LE,
+            SampleFormat::MuLaw => alsa::pcm::Format::MuLaw,
+            SampleFormat::ALaw => alsa::pcm::Format::ALaw,
+        },
+    }
+}
+
+// Groups the channel counts between `min` and `max` accepted by `test` into runs of consecutive
+// counts, as devices may skip counts (e.g. USB interfaces offering 2 or 8 channels).
+//
+// Only counts up to `MAX_PROBED_CHANNELS` are probed, as plugins accept thousands of channels. A
+// run reaching that count is assumed to extend to `max`, and a device whose `min` lies above it is
+// assumed to accept every count between its bounds.
+fn channel_ranges<F>(
+    min: libc::c_uint,
+    max: libc::c_uint,
+    mut test: F,
+) -> Vec<(ChannelCount, ChannelCount)>
+where
+    F: FnMut(libc::c_uint) -> bool,
+{
+    const MAX_PROBED_CHANNELS: libc::c_uint = 32;
+    let mut ranges: Vec<(ChannelCount, ChannelCount)> = Vec::new();
+    for channels in min..=cmp::min(max, MAX_PROBED_CHANNELS) {
+        if !test(channels) {
+            continue;
         }
-    };
+        let channels = channels as ChannelCount;
+        match ranges.last_mut() {
+            Some(&mut (_, ref mut last)) if *last + 1 == channels => *last = channels,
+            _ => ranges.push((channels, channels)),
+        }
+    }
+    if max > MAX_PROBED_CHANNELS {
+        match ranges.last_mut() {
+            Some(&mut (_, ref mut last)) if *last as libc::c_uint == MAX_PROBED_CHANNELS => {
+                *last = max as ChannelCount;
+            }
+            _ if min > MAX_PROBED_CHANNELS => {
+                ranges.push((min as ChannelCount, max as ChannelCount))
+            }
+            _ => (),
+        }
+    }
+    ranges
+}
 
-    hw_params.set_format(sample_format)?;
+// The byte order in which the device accepts the given sample format, preferring native order.
+//
+// Returns `None` if the device does not support the format in either byte order.
+fn supported_endianness(
+    hw_params: &alsa::pcm::HwParams,
+    sample_format: SampleFormat,
+) -> Option<Endianness> {
+    let native = Endianness::native();
+    [native, native.swapped()]
+        .iter()
+        .cloned()
+        .find(|&endianness| {
+            hw_params
+                .test_format(alsa_format(sample_format, endianness))
+                .is_ok()
+        })
+}
+
+fn set_hw_params_from_format<'a>(
+    pcm_handle: &'a alsa::pcm::PCM,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> Result<(alsa::pcm::HwParams<'a>, Endianness), BackendSpecificError> {
+    let hw_params = alsa::pcm::HwParams::any(pcm_handle)?;
+    hw_params.set_access(alsa::pcm::Access::RWInterleaved)?;
+
+    // Fall back to the native byte order so that `set_format` reports the error if the format is
+    // not supported at all.
+    let endianness =
+        supported_endianness(&hw_params, sample_format).unwrap_or_else(Endianness::native);
+    hw_params.set_format(alsa_format(sample_format, endianness))?;
     hw_params.set_rate(config.sample_rate.0, alsa::ValueOr::Nearest)?;
     hw_params.set_channels(config.channels as u32)?;
 
-    match config.buffer_size {
-        BufferSize::Fixed(v) => hw_params.set_buffer_size(v as alsa::pcm::Frames)?,
-        BufferSize::Default => {
-            // These values together represent a moderate latency and wakeup interval.
-            // Without them we are at the mercy of the device
-            hw_params.set_period_time_near(25_000, alsa::ValueOr::Nearest)?;
-            hw_params.set_buffer_time_near(100_000, alsa::ValueOr::Nearest)?;
+    // A strict config must get exactly the requested periods, anything else settles for the
+    // nearest ones the device accepts.
+    if let Some(period_size) = config.period_size {
+        let period_size = period_size as alsa::pcm::Frames;
+        if config.strict {
+            hw_params.set_period_size(period_size, alsa::ValueOr::Nearest)?;
+        } else {
+            hw_params.set_period_size_near(period_size, alsa::ValueOr::Nearest)?;
+        }
+    }
+    if let Some(period_count) = config.period_count {
+        if config.strict {
+            hw_params.set_periods(period_count, alsa::ValueOr::Nearest)?;
+        } else {
+            set_periods_near(&hw_params, period_count)?;
         }
     }
 
+    match buffer_request(config) {
+        BufferRequest::Frames(frames) => {
+            hw_params.set_buffer_size(frames as alsa::pcm::Frames)?;
+        }
+        BufferRequest::Time {
+            buffer_time,
+            period_time,
+        } => {
+            if let Some(period_time) = period_time {
+                hw_params.set_period_time_near(period_time, alsa::ValueOr::Nearest)?;
+            }
+            hw_params.set_buffer_time_near(buffer_time, alsa::ValueOr::Nearest)?;
+        }
+        BufferRequest::Periods => (),
+    }
+
     pcm_handle.hw_params(&hw_params)?;
 
-    Ok(hw_params)
+    Ok((hw_params, endianness))
+}
+
+// How the size of the device buffer is requested once the period settings have been applied.
+#[derive(Debug, PartialEq)]
+enum BufferRequest {
+    // A buffer of the given number of frames.
+    Frames(FrameCount),
+    // A buffer and, if given, periods of the nearest durations, in microseconds.
+    Time {
+        buffer_time: u32,
+        period_time: Option<u32>,
+    },
+    // The period size and count already determine the buffer.
+    Periods,
+}
+
+fn buffer_request(config: &StreamConfig) -> BufferRequest {
+    // Without a buffer size or latency we are at the mercy of the device, so the default asks for
+    // a moderate latency and wakeup interval.
+    let latency = match config.buffer_size {
+        BufferSize::Fixed(frames) => return BufferRequest::Frames(frames),
+        BufferSize::Latency(latency) => latency,
+        BufferSize::Default => LatencyProfile::Balanced.latency(),
+    };
+    if config.period_size.is_some() && config.period_count.is_some() {
+        return BufferRequest::Periods;
+    }
+    let buffer_time = latency.as_micros().min(u32::MAX as u128) as u32;
+    // Unless told otherwise, the buffer is split into four periods, waking the stream up four
+    // times per buffer.
+    let period_time = match (config.period_size, config.period_count) {
+        (None, None) => Some((buffer_time / 4).max(1)),
+        _ => None,
+    };
+    BufferRequest::Time {
+        buffer_time,
+        period_time,
+    }
 }
 
 fn set_sw_params_from_format(
@@ -947,6 +1404,375 @@ fn set_sw_params_from_format(
     Ok((buffer_len, period_len))
 }
 
+// Splits a PCM name such as `hw:1,0` or `front:CARD=PCH,DEV=0` into its interface and its card,
+// device and subdevice arguments, given by key or by position. Quotes around a value are removed.
+// Returns `None` for names with other arguments, such as plugin definitions.
+fn parse_pcm_name(name: &str) -> Option<(&str, Vec<(&'static str, &str)>)> {
+    const KEYS: [&str; 3] = ["CARD", "DEV", "SUBDEV"];
+    let (interface, args) = match name.find(':') {
+        Some(index) => (&name[..index], &name[index + 1..]),
+        None => return Some((name, Vec::new())),
+    };
+    let mut parsed = Vec::new();
+    for (index, arg) in args.split(',').enumerate() {
+        let (key, value) = match arg.find('=') {
+            Some(split) => (&arg[..split], &arg[split + 1..]),
+            None => (*KEYS.get(index)?, arg),
+        };
+        let key = *KEYS.iter().find(|&&k| k == key)?;
+        // Quoted values may also hold card paths, e.g. `"pci0000:00/0000:00:1f.3"`.
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(value) if value.chars().all(is_quotable) => value,
+            None if value.chars().all(is_plain) => value,
+            _ => return None,
+        };
+        if value.is_empty() {
+            return None;
+        }
+        parsed.push((key, value));
+    }
+    Some((interface, parsed))
+}
+
+// Whether the character can appear unquoted in the arguments of a PCM name.
+fn is_plain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+// Whether the character can appear in a quoted argument of a PCM name, as part of a card path.
+fn is_quotable(c: char) -> bool {
+    is_plain(c) || c == '/' || c == ':' || c == '.'
+}
+
+// Rewrites the card of a PCM name such as `hw:1,0` with `card`, giving every argument by key, e.g.
+// `hw:CARD=USB,DEV=0`. Names without arguments, such as `default`, and names with arguments other
+// than the card, device and subdevice are returned as they are.
+fn replace_pcm_card<F>(name: &str, card: F) -> Result<String, alsa::Error>
+where
+    F: FnOnce(&str) -> Result<String, alsa::Error>,
+{
+    let (interface, args) = match parse_pcm_name(name) {
+        Some((interface, ref args)) if args.is_empty() => return Ok(interface.to_owned()),
+        Some(parsed) => parsed,
+        None => return Ok(name.to_owned()),
+    };
+    let mut card = Some(card);
+    let mut replaced = Vec::with_capacity(args.len());
+    for (key, value) in args {
+        let value = match (key, card.take()) {
+            ("CARD", Some(card)) => card(value)?,
+            _ => value.to_owned(),
+        };
+        if value.chars().all(is_plain) {
+            replaced.push(format!("{}={}", key, value));
+        } else {
+            replaced.push(format!("{}=\"{}\"", key, value));
+        }
+    }
+    Ok(format!("{}:{}", interface, replaced.join(",")))
+}
+
+// Rewrites a PCM name to refer to its card by a stable key, e.g.
+// `hw:CARD="pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0",DEV=0`. Card ids such as `USB` and `USB_1`
+// are handed out in probe order, so the key is the device path of the card in sysfs, which stays
+// the same as long as the card is plugged into the same port. Cards without a device in sysfs
+// keep their id, and unknown cards are left as they are.
+fn canonical_pcm_name(name: &str) -> Result<String, alsa::Error> {
+    replace_pcm_card(name, |card| {
+        let index = match card_index(card) {
+            Some(index) => index,
+            None => return Ok(card.to_owned()),
+        };
+        match card_path(index) {
+            Some(path) => Ok(path),
+            None => card_id(index),
+        }
+    })
+}
+
+// The PCM name a canonical name refers to now, with its card given by its current id.
+fn pcm_name_of_canonical(name: &str) -> Result<String, alsa::Error> {
+    replace_pcm_card(name, |card| {
+        if !card.contains('/') {
+            return Ok(card.to_owned());
+        }
+        for index in alsa::card::Iter::new() {
+            let index = index?.get_index();
+            if card_path(index).as_deref() == Some(card) {
+                return card_id(index);
+            }
+        }
+        Ok(card.to_owned())
+    })
+}
+
+// Describes the PCM with the given name from its hint and the card it refers to, if any.
+fn describe_pcm(name: &str) -> Result<DeviceDescription, alsa::Error> {
+    let (interface, args) = match parse_pcm_name(name) {
+        Some(parsed) => parsed,
+        None => (name, Vec::new()),
+    };
+    let arg = |key| {
+        args.iter()
+            .find(|&&(k, _)| k == key)
+            .map(|&(_, value)| value)
+    };
+
+    // Hint descriptions come in lines such as "HDA Intel PCH, ALC892 Analog" and "Front speakers".
+    let hint = alsa::device_name::HintIter::new_str(None, "pcm")?
+        .find(|hint| hint.name.as_deref() == Some(name))
+        .and_then(|hint| hint.desc)
+        .map(|desc| desc.lines().collect::<Vec<_>>().join(", "));
+
+    let card = match arg("CARD") {
+        Some(card) => {
+            let card = CString::new(card).expect("card names are plain");
+            Some(alsa::card::Card::from_str(&card)?)
+        }
+        None => None,
+    };
+    let info = match card {
+        Some(ref card) => Some(alsa::ctl::Ctl::from_card(card, false)?.card_info()?),
+        None => None,
+    };
+
+    let name = match (hint, info.as_ref()) {
+        (Some(hint), _) => hint,
+        (None, Some(info)) => info.get_longname()?.to_owned(),
+        (None, None) => name.to_owned(),
+    };
+    let driver = match info {
+        Some(ref info) => Some(info.get_driver()?.to_owned()),
+        None => None,
+    };
+    let interface_kind = match card {
+        Some(_) if interface == "hdmi" || name.contains("HDMI") => DeviceInterface::Hdmi,
+        Some(ref card) => card_bus(card.get_index()),
+        None => DeviceInterface::Virtual,
+    };
+    Ok(DeviceDescription {
+        name,
+        driver,
+        interface: interface_kind,
+        card: card.map(|card| card.get_index() as u32),
+        device: arg("DEV").and_then(|dev| dev.parse().ok()),
+        subdevice: arg("SUBDEV").and_then(|subdev| subdev.parse().ok()),
+        kind: if interface == "hw" {
+            DeviceKind::Hardware
+        } else {
+            DeviceKind::Plugin
+        },
+    })
+}
+
+// The bus the card with the given index is attached to, according to sysfs.
+fn card_bus(card: i32) -> DeviceInterface {
+    let subsystem = format!("/sys/class/sound/card{}/device/subsystem", card);
+    let bus = std::fs::read_link(subsystem)
+        .ok()
+        .and_then(|path| path.file_name().map(|name| name.to_owned()));
+    match bus.as_ref().and_then(|bus| bus.to_str()) {
+        Some("usb") => DeviceInterface::Usb,
+        Some("pci") => DeviceInterface::Pci,
+        _ => DeviceInterface::Unknown,
+    }
+}
+
+// The index of the card given by its index or id in a PCM name, or `None` if there is no such
+// card.
+fn card_index(card: &str) -> Option<i32> {
+    if let Ok(index) = card.parse::<i32>() {
+        return Some(index);
+    }
+    let id = CString::new(card).ok()?;
+    alsa::card::Card::from_str(&id)
+        .ok()
+        .map(|card| card.get_index())
+}
+
+// The device path of the card with the given index, relative to `/sys/devices`, e.g.
+// `pci0000:00/0000:00:1f.3`.
+fn card_path(card: i32) -> Option<String> {
+    let path = std::fs::canonicalize(format!("/sys/class/sound/card{}/device", card)).ok()?;
+    let path = path.strip_prefix("/sys/devices").ok()?.to_str()?;
+    // Paths that cannot be parsed back from a PCM name are of no use as a key.
+    if !path.chars().all(is_quotable) {
+        return None;
+    }
+    Some(path.to_owned())
+}
+
+// The id of the card with the given index, e.g. `PCH` or `USB`.
+fn card_id(card: i32) -> Result<String, alsa::Error> {
+    let ctl = alsa::ctl::Ctl::from_card(&alsa::card::Card::new(card), false)?;
+    let info = ctl.card_info()?;
+    Ok(info.get_id()?.to_owned())
+}
+
+// Whether the device settled on exactly the requested channel count, sample rate, fixed buffer size
+// and periods.
+fn is_exact(requested: &StreamConfig, effective: &StreamConfig) -> bool {
+    let buffer_size_matches = match requested.buffer_size {
+        BufferSize::Fixed(_) => requested.buffer_size == effective.buffer_size,
+        BufferSize::Default | BufferSize::Latency(_) => true,
+    };
+    requested.channels == effective.channels
+        && requested.sample_rate == effective.sample_rate
+        && buffer_size_matches
+        && (requested.period_size.is_none() || requested.period_size == effective.period_size)
+        && (requested.period_count.is_none() || requested.period_count == effective.period_count)
+}
+
+// The range of period counts the device accepts, if any.
+//
+// The `alsa` crate exposes neither `snd_pcm_hw_params_get_periods_min/max` nor the raw parameters
+// they take, so the limits are read from the dump of the parameters instead.
+fn period_count_range(hw_params: &alsa::pcm::HwParams) -> Result<Option<(u32, u32)>, alsa::Error> {
+    let mut output = alsa::Output::buffer_open()?;
+    hw_params.dump(&mut output)?;
+    Ok(output.buffer_string(|dump| parse_periods(&String::from_utf8_lossy(dump))))
+}
+
+// Parses the `PERIODS` interval of a hardware parameters dump, e.g. `PERIODS: [2 32]`, where a
+// parenthesis marks an open end. Returns `None` if the interval is missing or empty.
+fn parse_periods(dump: &str) -> Option<(u32, u32)> {
+    let value = dump
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("PERIODS:"))?
+        .trim();
+    if let Ok(count) = value.parse() {
+        return Some((count, count));
+    }
+    let mut bounds = value
+        .get(1..value.len().checked_sub(1)?)?
+        .split_whitespace();
+    let min: u32 = bounds.next()?.parse().ok()?;
+    let max: u32 = bounds.next()?.parse().ok()?;
+    let min = if value.starts_with('(') { min + 1 } else { min };
+    let max = if value.ends_with(')') {
+        max.checked_sub(1)?
+    } else {
+        max
+    };
+    if min <= max {
+        Some((min, max))
+    } else {
+        None
+    }
+}
+
+// Sets the accepted period count nearest to `count`, standing in for
+// `snd_pcm_hw_params_set_periods_near`, which the `alsa` crate does not expose. A rejected count
+// leaves the parameters unchanged, so the next candidate can be tried.
+fn set_periods_near(hw_params: &alsa::pcm::HwParams, count: u32) -> Result<(), alsa::Error> {
+    let (min, max) = match period_count_range(hw_params)? {
+        Some(range) => range,
+        None => return hw_params.set_periods(count, alsa::ValueOr::Nearest),
+    };
+    let mut result = Ok(());
+    for candidate in nearest_first(count, min, max) {
+        result = hw_params.set_periods(candidate, alsa::ValueOr::Nearest);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+// The values between `min` and `max` inclusive, ordered by their distance to `value`.
+fn nearest_first(value: u32, min: u32, max: u32) -> impl Iterator<Item = u32> {
+    let start = value.clamp(min, max);
+    let below = (min..start).rev().map(Some).chain(std::iter::repeat(None));
+    let above = (start + 1..=max).map(Some).chain(std::iter::repeat(None));
+    std::iter::once(start).chain(
+        above
+            .zip(below)
+            .take_while(|pair| *pair != (None, None))
+            .flat_map(|(above, below)| below.into_iter().chain(above)),
+    )
+}
+
+// Selects the channel map with the given layout among those the device offers.
+fn set_channel_layout(
+    pcm_handle: &alsa::pcm::PCM,
+    layout: &ChannelLayout,
+) -> Result<(), BuildStreamError> {
+    let current = pcm_handle
+        .get_chmap()
+        .ok()
+        .and_then(|map| parse_chmap(&map));
+    if current.as_ref() == Some(layout) {
+        return Ok(());
+    }
+    for (_, map) in pcm_handle.query_chmaps() {
+        if parse_chmap(&map).as_ref() == Some(layout) {
+            return pcm_handle
+                .set_chmap(&map)
+                .map_err(|_| BuildStreamError::StreamConfigNotSupported);
+        }
+    }
+    Err(BuildStreamError::StreamConfigNotSupported)
+}
+
+// Reads a channel map from the position names ALSA prints for it.
+//
+// The `ChmapPosition` enum of the `alsa` crate lacks some positions, e.g. the rear right speaker,
+// and converting a map holding one of them panics, so the printed form is parsed instead.
+fn parse_chmap(map: &alsa::pcm::Chmap) -> Option<ChannelLayout> {
+    use std::fmt::Write;
+    let mut printed = String::new();
+    write!(printed, "{}", map).ok()?;
+    Some(parse_printed_chmap(&printed))
+}
+
+// Reads a channel map printed by `snd_pcm_chmap_print`, e.g. `FL FR`.
+fn parse_printed_chmap(printed: &str) -> ChannelLayout {
+    ChannelLayout::new(printed.split_whitespace().map(channel_position).collect())
+}
+
+// The speaker position with the given ALSA name, ignoring any phase inversion.
+fn channel_position(name: &str) -> ChannelPosition {
+    match name.trim_end_matches("[INV]") {
+        "MONO" => ChannelPosition::Mono,
+        "FL" => ChannelPosition::FrontLeft,
+        "FR" => ChannelPosition::FrontRight,
+        "RL" => ChannelPosition::RearLeft,
+        "RR" => ChannelPosition::RearRight,
+        "FC" => ChannelPosition::FrontCenter,
+        "LFE" => ChannelPosition::LowFrequency,
+        "SL" => ChannelPosition::SideLeft,
+        "SR" => ChannelPosition::SideRight,
+        "RC" => ChannelPosition::RearCenter,
+        "FLC" => ChannelPosition::FrontLeftOfCenter,
+        "FRC" => ChannelPosition::FrontRightOfCenter,
+        "RLC" => ChannelPosition::RearLeftOfCenter,
+        "RRC" => ChannelPosition::RearRightOfCenter,
+        "FLW" => ChannelPosition::FrontLeftWide,
+        "FRW" => ChannelPosition::FrontRightWide,
+        "FLH" => ChannelPosition::FrontLeftHigh,
+        "FCH" => ChannelPosition::FrontCenterHigh,
+        "FRH" => ChannelPosition::FrontRightHigh,
+        "TC" => ChannelPosition::TopCenter,
+        "TFL" => ChannelPosition::TopFrontLeft,
+        "TFR" => ChannelPosition::TopFrontRight,
+        "TFC" => ChannelPosition::TopFrontCenter,
+        "TRL" => ChannelPosition::TopRearLeft,
+        "TRR" => ChannelPosition::TopRearRight,
+        "TRC" => ChannelPosition::TopRearCenter,
+        "TFLC" => ChannelPosition::TopFrontLeftOfCenter,
+        "TFRC" => ChannelPosition::TopFrontRightOfCenter,
+        "TSL" => ChannelPosition::TopSideLeft,
+        "TSR" => ChannelPosition::TopSideRight,
+        "LLFE" => ChannelPosition::LowFrequencyLeft,
+        "RLFE" => ChannelPosition::LowFrequencyRight,
+        "BC" => ChannelPosition::BottomCenter,
+        "BLC" => ChannelPosition::BottomLeftOfCenter,
+        "BRC" => ChannelPosition::BottomRightOfCenter,
+        "NA" => ChannelPosition::NotApplicable,
+        _ => ChannelPosition::Unknown,
+    }
+}
+
 impl From<alsa::Error> for BackendSpecificError {
     fn from(err: alsa::Error) -> Self {
         BackendSpecificError {
@@ -962,6 +1788,20 @@ impl From<alsa::Error> for BuildStreamError {
     }
 }
 
+impl From<alsa::Error> for DeviceDescriptionError {
+    fn from(err: alsa::Error) -> Self {
+        let err: BackendSpecificError = err.into();
+        err.into()
+    }
+}
+
+impl From<alsa::Error> for DeviceIdError {
+    fn from(err: alsa::Error) -> Self {
+        let err: BackendSpecificError = err.into();
+        err.into()
+    }
+}
+
 impl From<alsa::Error> for SupportedStreamConfigsError {
     fn from(err: alsa::Error) -> Self {
         let err: BackendSpecificError = err.into();
@@ -989,3 +1829,158 @@ impl From<alsa::Error> for StreamError {
         err.into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        buffer_request, channel_ranges, nearest_first, parse_pcm_name, parse_periods,
+        replace_pcm_card, select_channels, spread_channels, BufferRequest,
+    };
+    use crate::{BufferSize, SampleRate, StreamConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn channel_ranges_skip_unsupported_counts() {
+        assert_eq!(
+            channel_ranges(1, 8, |channels| channels == 2 || channels == 8),
+            [(2, 2), (8, 8)]
+        );
+        assert_eq!(
+            channel_ranges(1, 6, |channels| channels != 3),
+            [(1, 2), (4, 6)]
+        );
+        assert_eq!(channel_ranges(1, 10_000, |_| true), [(1, 10_000)]);
+        assert_eq!(channel_ranges(1, 64, |channels| channels <= 8), [(1, 8)]);
+        assert_eq!(channel_ranges(64, 64, |_| true), [(64, 64)]);
+        assert_eq!(channel_ranges(40, 128, |_| false), [(40, 128)]);
+        assert!(channel_ranges(2, 2, |_| false).is_empty());
+    }
+
+    #[test]
+    fn selected_channels_are_picked_from_device_frames() {
+        // Two frames of four 16-bit channels.
+        let device: Vec<u8> = (0..16).collect();
+        let mut selected = [0u8; 8];
+        select_channels(&device, &mut selected, 4, &[3, 0], 2);
+        assert_eq!(selected, [6, 7, 0, 1, 14, 15, 8, 9]);
+    }
+
+    #[test]
+    fn selected_channels_are_spread_into_device_frames() {
+        let selected = [1u8, 2, 3, 4];
+        let mut device = [0u8; 6];
+        spread_channels(&selected, &mut device, 3, &[2, 0], 1);
+        assert_eq!(device, [2, 0, 1, 4, 0, 3]);
+    }
+
+    #[test]
+    fn periods_are_parsed_from_the_dump() {
+        let dump = "ACCESS:  RW_INTERLEAVED\nPERIOD_SIZE: [32 8192]\nPERIODS: [2 32]\n";
+        assert_eq!(parse_periods(dump), Some((2, 32)));
+        assert_eq!(parse_periods("PERIODS: (2 32]"), Some((3, 32)));
+        assert_eq!(parse_periods("PERIODS: [2 32)"), Some((2, 31)));
+        assert_eq!(parse_periods("PERIODS: 4"), Some((4, 4)));
+        assert_eq!(parse_periods("PERIODS: (4 5)"), None);
+        assert_eq!(parse_periods("PERIODS: NONE"), None);
+        assert_eq!(parse_periods("PERIOD_SIZE: [32 8192]"), None);
+    }
+
+    #[test]
+    fn period_counts_are_tried_nearest_first() {
+        let counts: Vec<_> = nearest_first(4, 2, 7).collect();
+        assert_eq!(counts, [4, 3, 5, 2, 6, 7]);
+        let counts: Vec<_> = nearest_first(1, 2, 4).collect();
+        assert_eq!(counts, [2, 3, 4]);
+        let counts: Vec<_> = nearest_first(9, 2, 4).collect();
+        assert_eq!(counts, [4, 3, 2]);
+    }
+
+    #[test]
+    fn buffer_requests_follow_the_config() {
+        let mut config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(1024),
+            channel_layout: None,
+            channel_selection: None,
+            period_size: None,
+            period_count: None,
+            strict: false,
+        };
+        assert_eq!(buffer_request(&config), BufferRequest::Frames(1024));
+        config.buffer_size = BufferSize::Default;
+        assert_eq!(
+            buffer_request(&config),
+            BufferRequest::Time {
+                buffer_time: 100_000,
+                period_time: Some(25_000),
+            }
+        );
+        config.buffer_size = BufferSize::Latency(Duration::from_millis(20));
+        config.period_count = Some(2);
+        assert_eq!(
+            buffer_request(&config),
+            BufferRequest::Time {
+                buffer_time: 20_000,
+                period_time: None,
+            }
+        );
+        config.period_size = Some(256);
+        assert_eq!(buffer_request(&config), BufferRequest::Periods);
+    }
+
+    #[test]
+    fn pcm_names_are_parsed() {
+        assert_eq!(parse_pcm_name("default"), Some(("default", vec![])));
+        assert_eq!(
+            parse_pcm_name("hw:1,0"),
+            Some(("hw", vec![("CARD", "1"), ("DEV", "0")]))
+        );
+        assert_eq!(
+            parse_pcm_name("front:CARD=PCH,DEV=0"),
+            Some(("front", vec![("CARD", "PCH"), ("DEV", "0")]))
+        );
+        assert_eq!(
+            parse_pcm_name("hw:CARD=\"pci0000:00/0000:00:1f.3\",DEV=0,SUBDEV=1"),
+            Some((
+                "hw",
+                vec![
+                    ("CARD", "pci0000:00/0000:00:1f.3"),
+                    ("DEV", "0"),
+                    ("SUBDEV", "1")
+                ]
+            ))
+        );
+        assert_eq!(parse_pcm_name("hw:CARD=pci0000:00/0000:00:1f.3"), None);
+        assert_eq!(parse_pcm_name("plug:{slave=hw:0}"), None);
+        assert_eq!(parse_pcm_name("dmix:CARD=PCH,RATE=44100"), None);
+        assert_eq!(parse_pcm_name("hw:1,0,0,0"), None);
+    }
+
+    #[test]
+    fn pcm_cards_are_replaced() {
+        let path = |card: &str| {
+            assert_eq!(card, "1");
+            Ok("pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0".to_owned())
+        };
+        assert_eq!(
+            replace_pcm_card("hw:1,0", path).unwrap(),
+            "hw:CARD=\"pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0\",DEV=0"
+        );
+        let id = |card: &str| Ok(card.to_lowercase());
+        assert_eq!(
+            replace_pcm_card("front:CARD=PCH,DEV=0", id).unwrap(),
+            "front:CARD=pch,DEV=0"
+        );
+        let unused = |_: &str| panic!("the card should not be replaced");
+        assert_eq!(replace_pcm_card("default", unused).unwrap(), "default");
+        assert_eq!(
+            replace_pcm_card("plug:{slave=hw:0}", unused).unwrap(),
+            "plug:{slave=hw:0}"
+        );
+        assert_eq!(
+            replace_pcm_card("hdmi:DEV=3", unused).unwrap(),
+            "hdmi:DEV=3"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use super::Device;
+use std::fmt;
+
+/// A typed ALSA PCM name addressing a card, device and subdevice, e.g. `hw:CARD=PCH,DEV=0`.
+///
+/// Convert it into a `Device` to open the PCM, or into a `String` with `to_string`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PcmName {
+    plugin: PcmPlugin,
+    card: String,
+    device: Option<u32>,
+    subdevice: Option<u32>,
+}
+
+/// The plugin a card's PCM is opened through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PcmPlugin {
+    /// Direct hardware access, without any conversions.
+    Hw,
+    /// Hardware access with sample format, rate and channel count conversions.
+    PlugHw,
+    /// Mixes the output of several streams into one playback device.
+    Dmix,
+    /// Shares one capture device between several streams.
+    Dsnoop,
+}
+
+impl PcmName {
+    /// Names the given card, by id (e.g. `PCH`) or by index, opened through the given plugin.
+    pub fn new<S: Into<String>>(plugin: PcmPlugin, card: S) -> Self {
+        PcmName {
+            plugin,
+            card: card.into(),
+            device: None,
+            subdevice: None,
+        }
+    }
+
+    /// Addresses the given device of the card, rather than its first device.
+    pub fn with_device(mut self, device: u32) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Addresses the given subdevice of the device, rather than the first free one.
+    pub fn with_subdevice(mut self, subdevice: u32) -> Self {
+        self.subdevice = Some(subdevice);
+        self
+    }
+
+    pub fn plugin(&self) -> PcmPlugin {
+        self.plugin
+    }
+
+    pub fn card(&self) -> &str {
+        &self.card
+    }
+
+    pub fn device(&self) -> Option<u32> {
+        self.device
+    }
+
+    pub fn subdevice(&self) -> Option<u32> {
+        self.subdevice
+    }
+}
+
+impl PcmPlugin {
+    /// The name of the plugin in PCM names, e.g. `plughw`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PcmPlugin::Hw => "hw",
+            PcmPlugin::PlugHw => "plughw",
+            PcmPlugin::Dmix => "dmix",
+            PcmPlugin::Dsnoop => "dsnoop",
+        }
+    }
+}
+
+impl fmt::Display for PcmName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:CARD={}", self.plugin.name(), self.card)?;
+        // A subdevice can only be given along with its device.
+        let device = self.device.or(self.subdevice.map(|_| 0));
+        if let Some(device) = device {
+            write!(f, ",DEV={}", device)?;
+        }
+        if let Some(subdevice) = self.subdevice {
+            write!(f, ",SUBDEV={}", subdevice)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<PcmName> for Device {
+    fn from(name: PcmName) -> Self {
+        let direction = match name.plugin {
+            PcmPlugin::Dmix => Some(super::alsa::Direction::Playback),
+            PcmPlugin::Dsnoop => Some(super::alsa::Direction::Capture),
+            PcmPlugin::Hw | PcmPlugin::PlugHw => None,
+        };
+        Device {
+            name: name.to_string(),
+            direction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PcmName, PcmPlugin};
+
+    #[test]
+    fn test_pcm_name() {
+        let card = PcmName::new(PcmPlugin::Hw, "PCH");
+        assert_eq!(card.to_string(), "hw:CARD=PCH");
+        let device = PcmName::new(PcmPlugin::PlugHw, "1").with_device(3);
+        assert_eq!(device.to_string(), "plughw:CARD=1,DEV=3");
+        let subdevice = PcmName::new(PcmPlugin::Dmix, "USB").with_subdevice(2);
+        assert_eq!(subdevice.to_string(), "dmix:CARD=USB,DEV=0,SUBDEV=2");
+        assert_eq!(subdevice.device(), None);
+    }
+}
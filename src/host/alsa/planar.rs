@@ -0,0 +1,925 @@
+//! Planar streams, read and written with non-interleaved access.
+//!
+//! The `alsa` crate only reads and writes interleaved buffers and keeps the PCM handle that
+//! `snd_pcm_readn` and `snd_pcm_writen` need to itself, so planar streams are opened and run
+//! through alsa-sys instead. Each channel of the device is read into or written from its own plane,
+//! which is handed to the user's callback without being copied.
+
+use super::alsa;
+use super::alsa_sys;
+use super::enumerate::check;
+use super::libc::{self, c_int, c_uint, c_ushort, c_void};
+use super::{
+    alsa_format, buffer_request, elapsed_timestamp, frames_to_duration, is_exact, nearest_first,
+    open_error, parse_printed_chmap, report_error, swap_sample_bytes, trigger, BufferRequest,
+    Device, Stream, StreamHandle, TriggerReceiver,
+};
+use crate::planar::PlanarBuffer;
+use crate::{
+    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, ChannelLayout, Endianness,
+    FrameCount, InputCallbackInfo, OutputCallbackInfo, PlanarData, SampleFormat, SampleRate,
+    StreamConfig, StreamError,
+};
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A PCM opened through alsa-sys, closed when dropped.
+pub(super) struct Pcm(*mut alsa_sys::snd_pcm_t);
+
+// Assume that the ALSA library is built with thread safe option.
+unsafe impl Send for Pcm {}
+unsafe impl Sync for Pcm {}
+
+impl Pcm {
+    fn open(name: &str, direction: alsa::Direction) -> Result<Self, alsa::Error> {
+        let name =
+            CString::new(name).map_err(|_| alsa::Error::new("snd_pcm_open", libc::EINVAL))?;
+        let stream = match direction {
+            alsa::Direction::Playback => alsa_sys::SND_PCM_STREAM_PLAYBACK,
+            alsa::Direction::Capture => alsa_sys::SND_PCM_STREAM_CAPTURE,
+        };
+        let mut handle = ptr::null_mut();
+        let res = unsafe {
+            alsa_sys::snd_pcm_open(
+                &mut handle,
+                name.as_ptr(),
+                stream,
+                alsa_sys::SND_PCM_NONBLOCK,
+            )
+        };
+        check("snd_pcm_open", res)?;
+        Ok(Pcm(handle))
+    }
+
+    pub(super) fn pause(&self, pause: bool) {
+        unsafe { alsa_sys::snd_pcm_pause(self.0, pause as c_int) };
+    }
+
+    // Recovers from an overrun or underrun.
+    fn recover(&self, err: &alsa::Error) {
+        if let Some(errno) = err.errno() {
+            unsafe { alsa_sys::snd_pcm_recover(self.0, -(errno as c_int), 0) };
+        }
+    }
+
+    // Reads up to `frames` frames into the given planes, returning the number of frames read.
+    fn readn(&self, planes: &[*mut ()], frames: usize) -> Result<usize, alsa::Error> {
+        let planes = planes.as_ptr() as *mut *mut c_void;
+        let res = unsafe {
+            alsa_sys::snd_pcm_readn(self.0, planes, frames as alsa_sys::snd_pcm_uframes_t)
+        };
+        frame_count("snd_pcm_readn", res)
+    }
+
+    // Writes up to `frames` frames from the given planes, returning the number of frames written.
+    fn writen(&self, planes: &[*mut ()], frames: usize) -> Result<usize, alsa::Error> {
+        let planes = planes.as_ptr() as *mut *mut c_void;
+        let res = unsafe {
+            alsa_sys::snd_pcm_writen(self.0, planes, frames as alsa_sys::snd_pcm_uframes_t)
+        };
+        frame_count("snd_pcm_writen", res)
+    }
+
+    // The channel map the PCM currently uses, if the device reports one.
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        let map = unsafe { alsa_sys::snd_pcm_get_chmap(self.0) };
+        if map.is_null() {
+            return None;
+        }
+        let layout = parse_chmap(map);
+        unsafe { libc::free(map as *mut c_void) };
+        layout
+    }
+
+    // Selects the channel map with the given layout among those the device offers.
+    fn set_channel_layout(&self, layout: &ChannelLayout) -> Result<(), BuildStreamError> {
+        if self.channel_layout().as_ref() == Some(layout) {
+            return Ok(());
+        }
+        let maps = unsafe { alsa_sys::snd_pcm_query_chmaps(self.0) };
+        if maps.is_null() {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        let mut result = Err(BuildStreamError::StreamConfigNotSupported);
+        // The queried maps are terminated by a null pointer.
+        for ix in 0.. {
+            let query = unsafe { *maps.add(ix) };
+            if query.is_null() {
+                break;
+            }
+            let map = unsafe { &(*query).map };
+            if parse_chmap(map).as_ref() == Some(layout) {
+                if unsafe { alsa_sys::snd_pcm_set_chmap(self.0, map) } >= 0 {
+                    result = Ok(());
+                }
+                break;
+            }
+        }
+        unsafe { alsa_sys::snd_pcm_free_chmaps(maps) };
+        result
+    }
+}
+
+impl Drop for Pcm {
+    fn drop(&mut self) {
+        unsafe { alsa_sys::snd_pcm_close(self.0) };
+    }
+}
+
+fn frame_count(func: &'static str, res: alsa_sys::snd_pcm_sframes_t) -> Result<usize, alsa::Error> {
+    if res < 0 {
+        Err(alsa::Error::new(func, -res as c_int))
+    } else {
+        Ok(res as usize)
+    }
+}
+
+// Reads a channel map through the position names ALSA prints for it, like `parse_chmap` in the
+// parent module.
+fn parse_chmap(map: *const alsa_sys::snd_pcm_chmap_t) -> Option<ChannelLayout> {
+    let mut buf = [0 as libc::c_char; 512];
+    let res = unsafe { alsa_sys::snd_pcm_chmap_print(map, buf.len(), buf.as_mut_ptr()) };
+    if res < 0 {
+        return None;
+    }
+    let printed = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str().ok()?;
+    Some(parse_printed_chmap(printed))
+}
+
+struct HwParams(*mut alsa_sys::snd_pcm_hw_params_t);
+
+impl HwParams {
+    fn new() -> Result<Self, alsa::Error> {
+        let mut params = ptr::null_mut();
+        check("snd_pcm_hw_params_malloc", unsafe {
+            alsa_sys::snd_pcm_hw_params_malloc(&mut params)
+        })?;
+        Ok(HwParams(params))
+    }
+}
+
+impl Drop for HwParams {
+    fn drop(&mut self) {
+        unsafe { alsa_sys::snd_pcm_hw_params_free(self.0) };
+    }
+}
+
+struct SwParams(*mut alsa_sys::snd_pcm_sw_params_t);
+
+impl SwParams {
+    fn new() -> Result<Self, alsa::Error> {
+        let mut params = ptr::null_mut();
+        check("snd_pcm_sw_params_malloc", unsafe {
+            alsa_sys::snd_pcm_sw_params_malloc(&mut params)
+        })?;
+        Ok(SwParams(params))
+    }
+}
+
+impl Drop for SwParams {
+    fn drop(&mut self) {
+        unsafe { alsa_sys::snd_pcm_sw_params_free(self.0) };
+    }
+}
+
+struct Status(*mut alsa_sys::snd_pcm_status_t);
+
+impl Status {
+    fn new() -> Result<Self, alsa::Error> {
+        let mut status = ptr::null_mut();
+        check("snd_pcm_status_malloc", unsafe {
+            alsa_sys::snd_pcm_status_malloc(&mut status)
+        })?;
+        Ok(Status(status))
+    }
+
+    fn update(&self, pcm: &Pcm) -> Result<(), alsa::Error> {
+        check("snd_pcm_status", unsafe {
+            alsa_sys::snd_pcm_status(pcm.0, self.0)
+        })
+    }
+
+    fn avail(&self) -> usize {
+        unsafe { alsa_sys::snd_pcm_status_get_avail(self.0) as usize }
+    }
+
+    fn delay(&self) -> alsa_sys::snd_pcm_sframes_t {
+        unsafe { alsa_sys::snd_pcm_status_get_delay(self.0) }
+    }
+
+    fn htstamp(&self) -> libc::timespec {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { alsa_sys::snd_pcm_status_get_htstamp(self.0, &mut ts) };
+        ts
+    }
+
+    fn trigger_htstamp(&self) -> libc::timespec {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { alsa_sys::snd_pcm_status_get_trigger_htstamp(self.0, &mut ts) };
+        ts
+    }
+}
+
+impl Drop for Status {
+    fn drop(&mut self) {
+        unsafe { alsa_sys::snd_pcm_status_free(self.0) };
+    }
+}
+
+pub(super) struct StreamInner {
+    pub(super) pcm: Pcm,
+    num_descriptors: usize,
+    pub(super) sample_format: SampleFormat,
+    swap_bytes: bool,
+    pub(super) conf: StreamConfig,
+    pub(super) latency: Duration,
+    // The minimum number of frames to wait for before waking up the stream.
+    period_frames: usize,
+    creation_instant: Option<Instant>,
+}
+
+impl StreamInner {
+    // Opens the device with non-interleaved access, configured like `Device::build_stream_inner`.
+    //
+    // Returns `None` if the device only supports interleaved access.
+    fn open(
+        device: &Device,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        direction: alsa::Direction,
+    ) -> Result<Option<Self>, BuildStreamError> {
+        if !conf.channel_selection_is_valid() {
+            return Err(BuildStreamError::InvalidArgument);
+        }
+        if conf.callback_channels() == 0 {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+
+        let pcm = Pcm::open(&device.name, direction).map_err(open_error)?;
+        let hw_params = HwParams::new()?;
+        let access = alsa_sys::SND_PCM_ACCESS_RW_NONINTERLEAVED;
+        unsafe {
+            check("snd_pcm_hw_params_any", {
+                alsa_sys::snd_pcm_hw_params_any(pcm.0, hw_params.0)
+            })?;
+            if alsa_sys::snd_pcm_hw_params_test_access(pcm.0, hw_params.0, access) < 0 {
+                return Ok(None);
+            }
+        }
+        let endianness = set_hw_params_from_format(&pcm, &hw_params, conf, sample_format)?;
+        let mut effective = effective_config(&hw_params, conf)?;
+        if conf.strict && !is_exact(conf, &effective) {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        if let Some(ref layout) = conf.channel_layout {
+            if layout.channels() != conf.channels {
+                return Err(BuildStreamError::InvalidArgument);
+            }
+            pcm.set_channel_layout(layout)?;
+        }
+        effective.channel_layout = pcm.channel_layout();
+
+        let period_frames = effective.period_size.unwrap_or(1) as usize;
+        set_sw_params(&pcm, period_frames)?;
+        let latency = match effective.buffer_size {
+            BufferSize::Fixed(frames) => frames_to_duration(frames as usize, effective.sample_rate),
+            _ => unreachable!("the effective buffer size is always fixed"),
+        };
+
+        check("snd_pcm_prepare", unsafe {
+            alsa_sys::snd_pcm_prepare(pcm.0)
+        })?;
+
+        let num_descriptors = unsafe { alsa_sys::snd_pcm_poll_descriptors_count(pcm.0) };
+        check("snd_pcm_poll_descriptors_count", num_descriptors)?;
+        if num_descriptors == 0 {
+            let description = "poll descriptor count for stream was 0".to_string();
+            let err = BackendSpecificError { description };
+            return Err(err.into());
+        }
+
+        // Check to see if we can retrieve valid timestamps from the device.
+        // Related: https://bugs.freedesktop.org/show_bug.cgi?id=88503
+        let status = Status::new()?;
+        status.update(&pcm)?;
+        let ts = status.htstamp();
+        let creation_instant = match (ts.tv_sec, ts.tv_nsec) {
+            (0, 0) => Some(Instant::now()),
+            _ => None,
+        };
+
+        check("snd_pcm_start", unsafe { alsa_sys::snd_pcm_start(pcm.0) })?;
+
+        Ok(Some(StreamInner {
+            pcm,
+            num_descriptors: num_descriptors as usize,
+            sample_format,
+            swap_bytes: !endianness.is_native(),
+            conf: effective,
+            latency,
+            period_frames,
+            creation_instant,
+        }))
+    }
+}
+
+// Mirrors `set_hw_params_from_format` in the parent module, with non-interleaved access.
+fn set_hw_params_from_format(
+    pcm: &Pcm,
+    hw_params: &HwParams,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> Result<Endianness, alsa::Error> {
+    let (pcm, params) = (pcm.0, hw_params.0);
+    let format = |endianness| alsa_format(sample_format, endianness) as alsa_sys::snd_pcm_format_t;
+    let endianness = unsafe {
+        check("snd_pcm_hw_params_set_access", {
+            alsa_sys::snd_pcm_hw_params_set_access(
+                pcm,
+                params,
+                alsa_sys::SND_PCM_ACCESS_RW_NONINTERLEAVED,
+            )
+        })?;
+
+        // Fall back to the native byte order so that setting the format reports the error if the
+        // format is not supported at all.
+        let native = Endianness::native();
+        let endianness = [native, native.swapped()]
+            .iter()
+            .cloned()
+            .find(|&endianness| {
+                alsa_sys::snd_pcm_hw_params_test_format(pcm, params, format(endianness)) == 0
+            })
+            .unwrap_or(native);
+        check("snd_pcm_hw_params_set_format", {
+            alsa_sys::snd_pcm_hw_params_set_format(pcm, params, format(endianness))
+        })?;
+        check("snd_pcm_hw_params_set_rate", {
+            alsa_sys::snd_pcm_hw_params_set_rate(pcm, params, config.sample_rate.0, 0)
+        })?;
+        check("snd_pcm_hw_params_set_channels", {
+            alsa_sys::snd_pcm_hw_params_set_channels(pcm, params, config.channels as c_uint)
+        })?;
+
+        // A strict config must get exactly the requested periods, anything else settles for the
+        // nearest ones the device accepts.
+        if let Some(period_size) = config.period_size {
+            let mut frames = period_size as alsa_sys::snd_pcm_uframes_t;
+            let res = if config.strict {
+                alsa_sys::snd_pcm_hw_params_set_period_size(pcm, params, frames, 0)
+            } else {
+                alsa_sys::snd_pcm_hw_params_set_period_size_near(pcm, params, &mut frames, &mut 0)
+            };
+            check("snd_pcm_hw_params_set_period_size", res)?;
+        }
+        if let Some(period_count) = config.period_count {
+            let (mut min, mut max) = (period_count, period_count);
+            if !config.strict {
+                check("snd_pcm_hw_params_get_periods_min", {
+                    alsa_sys::snd_pcm_hw_params_get_periods_min(params, &mut min, ptr::null_mut())
+                })?;
+                check("snd_pcm_hw_params_get_periods_max", {
+                    alsa_sys::snd_pcm_hw_params_get_periods_max(params, &mut max, ptr::null_mut())
+                })?;
+            }
+            let mut res = 0;
+            for candidate in nearest_first(period_count, min, max) {
+                res = alsa_sys::snd_pcm_hw_params_set_periods(pcm, params, candidate, 0);
+                if res == 0 {
+                    break;
+                }
+            }
+            check("snd_pcm_hw_params_set_periods", res)?;
+        }
+
+        match buffer_request(config) {
+            BufferRequest::Frames(frames) => {
+                check("snd_pcm_hw_params_set_buffer_size", {
+                    let frames = frames as alsa_sys::snd_pcm_uframes_t;
+                    alsa_sys::snd_pcm_hw_params_set_buffer_size(pcm, params, frames)
+                })?;
+            }
+            BufferRequest::Time {
+                mut buffer_time,
+                period_time,
+            } => {
+                if let Some(mut period_time) = period_time {
+                    check("snd_pcm_hw_params_set_period_time_near", {
+                        alsa_sys::snd_pcm_hw_params_set_period_time_near(
+                            pcm,
+                            params,
+                            &mut period_time,
+                            &mut 0,
+                        )
+                    })?;
+                }
+                check("snd_pcm_hw_params_set_buffer_time_near", {
+                    alsa_sys::snd_pcm_hw_params_set_buffer_time_near(
+                        pcm,
+                        params,
+                        &mut buffer_time,
+                        &mut 0,
+                    )
+                })?;
+            }
+            BufferRequest::Periods => (),
+        }
+
+        check(
+            "snd_pcm_hw_params",
+            alsa_sys::snd_pcm_hw_params(pcm, params),
+        )?;
+        endianness
+    };
+    Ok(endianness)
+}
+
+// The config the device settled on.
+fn effective_config(
+    hw_params: &HwParams,
+    conf: &StreamConfig,
+) -> Result<StreamConfig, alsa::Error> {
+    let params = hw_params.0;
+    let (mut channels, mut rate, mut periods) = (0, 0, 0);
+    let (mut buffer_size, mut period_size) = (0, 0);
+    unsafe {
+        check("snd_pcm_hw_params_get_channels", {
+            alsa_sys::snd_pcm_hw_params_get_channels(params, &mut channels)
+        })?;
+        check("snd_pcm_hw_params_get_rate", {
+            alsa_sys::snd_pcm_hw_params_get_rate(params, &mut rate, ptr::null_mut())
+        })?;
+        check("snd_pcm_hw_params_get_buffer_size", {
+            alsa_sys::snd_pcm_hw_params_get_buffer_size(params, &mut buffer_size)
+        })?;
+        check("snd_pcm_hw_params_get_period_size", {
+            alsa_sys::snd_pcm_hw_params_get_period_size(params, &mut period_size, ptr::null_mut())
+        })?;
+        check("snd_pcm_hw_params_get_periods", {
+            alsa_sys::snd_pcm_hw_params_get_periods(params, &mut periods, ptr::null_mut())
+        })?;
+    }
+    Ok(StreamConfig {
+        channels: channels as ChannelCount,
+        sample_rate: SampleRate(rate),
+        buffer_size: BufferSize::Fixed(buffer_size as FrameCount),
+        channel_layout: None,
+        channel_selection: conf.channel_selection.clone(),
+        period_size: Some(period_size as FrameCount),
+        period_count: Some(periods),
+        strict: conf.strict,
+    })
+}
+
+// Mirrors `set_sw_params_from_format` in the parent module.
+fn set_sw_params(pcm: &Pcm, period_frames: usize) -> Result<(), alsa::Error> {
+    let sw_params = SwParams::new()?;
+    let (pcm, params) = (pcm.0, sw_params.0);
+    unsafe {
+        check("snd_pcm_sw_params_current", {
+            alsa_sys::snd_pcm_sw_params_current(pcm, params)
+        })?;
+        check("snd_pcm_sw_params_set_start_threshold", {
+            alsa_sys::snd_pcm_sw_params_set_start_threshold(pcm, params, 0)
+        })?;
+        // Set the minimum number of available frames before waking up the stream.
+        check("snd_pcm_sw_params_set_avail_min", {
+            let frames = period_frames as alsa_sys::snd_pcm_uframes_t;
+            alsa_sys::snd_pcm_sw_params_set_avail_min(pcm, params, frames)
+        })?;
+        check("snd_pcm_sw_params_set_tstamp_mode", {
+            alsa_sys::snd_pcm_sw_params_set_tstamp_mode(
+                pcm,
+                params,
+                alsa_sys::SND_PCM_TSTAMP_ENABLE,
+            )
+        })?;
+        check(
+            "snd_pcm_sw_params",
+            alsa_sys::snd_pcm_sw_params(pcm, params),
+        )
+    }
+}
+
+struct StreamWorkerContext {
+    descriptors: Vec<libc::pollfd>,
+    status: Status,
+    // One plane per channel of the device.
+    buffer: PlanarBuffer,
+    // The planes of the selected channels, if the stream selects a subset of the device's
+    // channels.
+    selected: Vec<*mut ()>,
+}
+
+// The pointers in `selected` only ever point into `buffer`, and the status is only used by the
+// stream worker.
+unsafe impl Send for StreamWorkerContext {}
+
+impl StreamWorkerContext {
+    fn new(stream: &StreamInner) -> Result<Self, alsa::Error> {
+        let selected = match stream.conf.channel_selection {
+            Some(ref selection) => selection.len(),
+            None => 0,
+        };
+        Ok(StreamWorkerContext {
+            descriptors: Vec::with_capacity(stream.num_descriptors + 1),
+            status: Status::new()?,
+            buffer: PlanarBuffer::new(stream.conf.channels as usize, stream.sample_format),
+            selected: Vec::with_capacity(selected),
+        })
+    }
+
+    // A view of the planes the user's callback sees.
+    fn planar_data(&mut self, stream: &StreamInner, frames: usize) -> PlanarData {
+        let planes = match stream.conf.channel_selection {
+            Some(ref selection) => {
+                let planes = self.buffer.planes();
+                self.selected.clear();
+                self.selected
+                    .extend(selection.iter().map(|&channel| planes[channel as usize]));
+                &self.selected[..]
+            }
+            None => self.buffer.planes(),
+        };
+        // The planes hold at least `frames` samples each, and a valid selection never names a
+        // channel twice.
+        unsafe {
+            PlanarData::from_parts(planes.as_ptr(), planes.len(), frames, stream.sample_format)
+        }
+    }
+
+    // Reverses the byte order of every sample in the first `frames` frames of each plane.
+    fn swap_sample_bytes(&mut self, stream: &StreamInner, frames: usize) {
+        let sample_size = stream.sample_format.sample_size();
+        for channel in 0..stream.conf.channels as usize {
+            let plane = self.buffer.plane_bytes_mut(channel);
+            swap_sample_bytes(&mut plane[..frames * sample_size], sample_size);
+        }
+    }
+}
+
+enum PollDescriptorsFlow {
+    Continue,
+    Return,
+    Ready {
+        avail_frames: usize,
+        delay_frames: usize,
+    },
+}
+
+// Waits until the device is ready for the given poll events, mirroring
+// `poll_descriptors_and_prepare_buffer` in the parent module.
+fn poll_descriptors_and_prepare_buffer(
+    rx: &TriggerReceiver,
+    stream: &StreamInner,
+    ctxt: &mut StreamWorkerContext,
+    events: libc::c_short,
+) -> Result<PollDescriptorsFlow, BackendSpecificError> {
+    let StreamWorkerContext {
+        ref mut descriptors,
+        ref status,
+        ref mut buffer,
+        ..
+    } = *ctxt;
+
+    descriptors.clear();
+
+    // Add the self-pipe for signaling termination.
+    descriptors.push(libc::pollfd {
+        fd: rx.0,
+        events: libc::POLLIN,
+        revents: 0,
+    });
+
+    // Add ALSA polling fds.
+    let len = descriptors.len();
+    descriptors.resize(
+        stream.num_descriptors + len,
+        libc::pollfd {
+            fd: 0,
+            events: 0,
+            revents: 0,
+        },
+    );
+    let pcm_descriptors = descriptors[len..].as_mut_ptr();
+    let count = stream.num_descriptors as c_uint;
+    let filled =
+        unsafe { alsa_sys::snd_pcm_poll_descriptors(stream.pcm.0, pcm_descriptors, count) };
+    check("snd_pcm_poll_descriptors", filled)?;
+    debug_assert_eq!(filled as usize, stream.num_descriptors);
+
+    // Don't timeout, wait forever.
+    let res = unsafe {
+        libc::poll(
+            descriptors.as_mut_ptr(),
+            descriptors.len() as libc::nfds_t,
+            -1,
+        )
+    };
+    if res < 0 {
+        let err = std::io::Error::last_os_error();
+        let errno = err.raw_os_error().unwrap_or(libc::EIO);
+        return Err(alsa::Error::new("poll", errno).into());
+    }
+    if res == 0 {
+        let description = String::from("`poll()` spuriously returned");
+        return Err(BackendSpecificError { description });
+    }
+
+    if descriptors[0].revents != 0 {
+        // The stream has been requested to be destroyed.
+        rx.clear_pipe();
+        return Ok(PollDescriptorsFlow::Return);
+    }
+
+    let mut revents: c_ushort = 0;
+    let pcm_descriptors = descriptors[len..].as_mut_ptr();
+    check("snd_pcm_poll_descriptors_revents", unsafe {
+        alsa_sys::snd_pcm_poll_descriptors_revents(
+            stream.pcm.0,
+            pcm_descriptors,
+            count,
+            &mut revents,
+        )
+    })?;
+    if revents as libc::c_short != events {
+        // Nothing to process, poll again
+        return Ok(PollDescriptorsFlow::Continue);
+    }
+
+    status.update(&stream.pcm)?;
+    let avail_frames = status.avail();
+    let delay_frames = match status.delay() {
+        // Buffer underrun. TODO: Notify the user.
+        d if d < 0 => 0,
+        d => d as usize,
+    };
+
+    // Only go on if there is at least a period of frames.
+    if avail_frames < stream.period_frames {
+        return Ok(PollDescriptorsFlow::Continue);
+    }
+
+    // Prepare the planes.
+    buffer.resize(avail_frames);
+
+    Ok(PollDescriptorsFlow::Ready {
+        avail_frames,
+        delay_frames,
+    })
+}
+
+fn input_stream_worker(
+    rx: TriggerReceiver,
+    stream: &StreamInner,
+    mut ctxt: StreamWorkerContext,
+    data_callback: &mut (dyn FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static),
+    error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
+) {
+    loop {
+        let flow = report_error(
+            poll_descriptors_and_prepare_buffer(&rx, stream, &mut ctxt, libc::POLLIN),
+            error_callback,
+        )
+        .unwrap_or(PollDescriptorsFlow::Continue);
+
+        match flow {
+            PollDescriptorsFlow::Continue => continue,
+            PollDescriptorsFlow::Return => return,
+            PollDescriptorsFlow::Ready {
+                avail_frames,
+                delay_frames,
+            } => {
+                let res =
+                    process_input(stream, &mut ctxt, avail_frames, delay_frames, data_callback);
+                report_error(res, error_callback);
+            }
+        }
+    }
+}
+
+fn output_stream_worker(
+    rx: TriggerReceiver,
+    stream: &StreamInner,
+    mut ctxt: StreamWorkerContext,
+    data_callback: &mut (dyn FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static),
+    error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
+) {
+    loop {
+        let flow = report_error(
+            poll_descriptors_and_prepare_buffer(&rx, stream, &mut ctxt, libc::POLLOUT),
+            error_callback,
+        )
+        .unwrap_or(PollDescriptorsFlow::Continue);
+
+        match flow {
+            PollDescriptorsFlow::Continue => continue,
+            PollDescriptorsFlow::Return => return,
+            PollDescriptorsFlow::Ready {
+                avail_frames,
+                delay_frames,
+            } => {
+                let res = process_output(
+                    stream,
+                    &mut ctxt,
+                    avail_frames,
+                    delay_frames,
+                    data_callback,
+                    error_callback,
+                );
+                report_error(res, error_callback);
+            }
+        }
+    }
+}
+
+// Read input data from ALSA straight into the planes and deliver them to the user.
+fn process_input(
+    stream: &StreamInner,
+    ctxt: &mut StreamWorkerContext,
+    avail_frames: usize,
+    delay_frames: usize,
+    data_callback: &mut (dyn FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static),
+) -> Result<(), BackendSpecificError> {
+    let frames = match stream.pcm.readn(ctxt.buffer.planes(), avail_frames) {
+        Err(err) if err.errno() == Some(nix::errno::Errno::EPIPE) => {
+            // buffer overrun
+            // TODO: Notify the user of this.
+            stream.pcm.recover(&err);
+            return Ok(());
+        }
+        res => res?,
+    };
+    if stream.swap_bytes {
+        ctxt.swap_sample_bytes(stream, frames);
+    }
+    let callback = elapsed_timestamp(
+        ctxt.status.htstamp(),
+        ctxt.status.trigger_htstamp(),
+        stream.creation_instant,
+    )?;
+    let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
+    let capture = callback
+        .sub(delay_duration)
+        .expect("`capture` is earlier than representation supported by `StreamInstant`");
+    let timestamp = crate::InputStreamTimestamp { callback, capture };
+    let info = crate::InputCallbackInfo { timestamp };
+    data_callback(&ctxt.planar_data(stream, frames), &info);
+
+    Ok(())
+}
+
+// Request data from the user's function and write its planes via ALSA.
+fn process_output(
+    stream: &StreamInner,
+    ctxt: &mut StreamWorkerContext,
+    available_frames: usize,
+    delay_frames: usize,
+    data_callback: &mut (dyn FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static),
+    error_callback: &mut dyn FnMut(StreamError),
+) -> Result<(), BackendSpecificError> {
+    let sample_format = stream.sample_format;
+    if let Some(ref selection) = stream.conf.channel_selection {
+        // Channels that are not selected are kept silent.
+        for channel in 0..stream.conf.channels {
+            if !selection.contains(&channel) {
+                sample_format.fill_silence(ctxt.buffer.plane_bytes_mut(channel as usize));
+            }
+        }
+    }
+    {
+        // We're now sure that we're ready to write data.
+        let callback = elapsed_timestamp(
+            ctxt.status.htstamp(),
+            ctxt.status.trigger_htstamp(),
+            stream.creation_instant,
+        )?;
+        let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
+        let playback = callback
+            .add(delay_duration)
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        let timestamp = crate::OutputStreamTimestamp { callback, playback };
+        let info = crate::OutputCallbackInfo { timestamp };
+        data_callback(&mut ctxt.planar_data(stream, available_frames), &info);
+    }
+    if stream.swap_bytes {
+        ctxt.swap_sample_bytes(stream, available_frames);
+    }
+    loop {
+        match stream.pcm.writen(ctxt.buffer.planes(), available_frames) {
+            Err(err) if err.errno() == Some(nix::errno::Errno::EPIPE) => {
+                // buffer underrun
+                // TODO: Notify the user of this.
+                stream.pcm.recover(&err);
+            }
+            Err(err) => {
+                error_callback(err.into());
+                continue;
+            }
+            Ok(result) if result != available_frames => {
+                let description = format!(
+                    "unexpected number of frames written: expected {}, \
+                     result {} (this should never happen)",
+                    available_frames, result,
+                );
+                error_callback(BackendSpecificError { description }.into());
+                continue;
+            }
+            _ => {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds a planar input stream that reads each channel of the device into its own plane.
+///
+/// Devices that only support interleaved access fall back to deinterleaving the raw stream.
+pub(super) fn build_input_stream<D, E>(
+    device: &Device,
+    conf: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    mut error_callback: E,
+) -> Result<Stream, BuildStreamError>
+where
+    D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let direction = alsa::Direction::Capture;
+    let inner = match StreamInner::open(device, conf, sample_format, direction)? {
+        Some(inner) => Arc::new(inner),
+        None => {
+            return crate::planar::build_input_stream_deinterleaved(
+                device,
+                conf,
+                sample_format,
+                data_callback,
+                error_callback,
+            )
+        }
+    };
+    let ctxt = StreamWorkerContext::new(&inner)?;
+    let (tx, rx) = trigger();
+    // Clone the handle for passing into worker thread.
+    let stream = inner.clone();
+    let thread = thread::spawn(move || {
+        input_stream_worker(rx, &stream, ctxt, &mut data_callback, &mut error_callback);
+    });
+    Ok(Stream {
+        thread: Some(thread),
+        inner: StreamHandle::Planar(inner),
+        trigger: tx,
+    })
+}
+
+/// Builds a planar output stream that writes each channel of the device from its own plane.
+///
+/// Devices that only support interleaved access fall back to interleaving into the raw stream.
+pub(super) fn build_output_stream<D, E>(
+    device: &Device,
+    conf: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    mut error_callback: E,
+) -> Result<Stream, BuildStreamError>
+where
+    D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let direction = alsa::Direction::Playback;
+    let inner = match StreamInner::open(device, conf, sample_format, direction)? {
+        Some(inner) => Arc::new(inner),
+        None => {
+            return crate::planar::build_output_stream_interleaved(
+                device,
+                conf,
+                sample_format,
+                data_callback,
+                error_callback,
+            )
+        }
+    };
+    let ctxt = StreamWorkerContext::new(&inner)?;
+    let (tx, rx) = trigger();
+    // Clone the handle for passing into worker thread.
+    let stream = inner.clone();
+    let thread = thread::spawn(move || {
+        output_stream_worker(rx, &stream, ctxt, &mut data_callback, &mut error_callback);
+    });
+    Ok(Stream {
+        thread: Some(thread),
+        inner: StreamHandle::Planar(inner),
+        trigger: tx,
+    })
+}
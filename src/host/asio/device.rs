@@ -10,9 +10,12 @@ use BackendSpecificError;
 use DefaultStreamConfigError;
 use DeviceNameError;
 use DevicesError;
+use Endianness;
 use SampleFormat;
 use SampleRate;
 use SupportedBufferSize;
+use SupportedPeriods;
+use SupportedSampleRates;
 use SupportedStreamConfig;
 use SupportedStreamConfigRange;
 use SupportedStreamConfigsError;
@@ -66,27 +69,30 @@ impl Device {
             Ok(f) => f,
         };
 
-        // Collect a config for every combination of supported sample rate and number of channels.
-        let mut supported_configs = vec![];
-        for &rate in ::COMMON_SAMPLE_RATES {
-            if !self
-                .driver
-                .can_sample_rate(rate.0.into())
-                .ok()
-                .unwrap_or(false)
-            {
-                continue;
-            }
-            for channels in 1..f.channels + 1 {
-                supported_configs.push(SupportedStreamConfigRange {
-                    channels,
-                    min_sample_rate: rate,
-                    max_sample_rate: rate,
-                    buffer_size: f.buffer_size.clone(),
-                    sample_format: f.sample_format.clone(),
-                })
-            }
+        // Collect a single config covering every supported sample rate and number of channels.
+        let rates: Vec<SampleRate> = ::COMMON_SAMPLE_RATES
+            .iter()
+            .cloned()
+            .filter(|rate| {
+                self.driver
+                    .can_sample_rate(rate.0.into())
+                    .ok()
+                    .unwrap_or(false)
+            })
+            .collect();
+        if rates.is_empty() {
+            return Ok(Vec::new().into_iter());
         }
+        let supported_configs = vec![SupportedStreamConfigRange {
+            min_channels: 1,
+            max_channels: f.channels,
+            sample_rates: SupportedSampleRates::Discrete(rates),
+            buffer_size: f.buffer_size.clone(),
+            sample_format: f.sample_format.clone(),
+            endianness: f.endianness,
+            channel_layouts: Vec::new(),
+            periods: SupportedPeriods::Unknown,
+        }];
         Ok(supported_configs.into_iter())
     }
 
@@ -103,27 +109,30 @@ impl Device {
             Ok(f) => f,
         };
 
-        // Collect a config for every combination of supported sample rate and number of channels.
-        let mut supported_configs = vec![];
-        for &rate in ::COMMON_SAMPLE_RATES {
-            if !self
-                .driver
-                .can_sample_rate(rate.0.into())
-                .ok()
-                .unwrap_or(false)
-            {
-                continue;
-            }
-            for channels in 1..f.channels + 1 {
-                supported_configs.push(SupportedStreamConfigRange {
-                    channels,
-                    min_sample_rate: rate,
-                    max_sample_rate: rate,
-                    buffer_size: f.buffer_size.clone(),
-                    sample_format: f.sample_format.clone(),
-                })
-            }
+        // Collect a single config covering every supported sample rate and number of channels.
+        let rates: Vec<SampleRate> = ::COMMON_SAMPLE_RATES
+            .iter()
+            .cloned()
+            .filter(|rate| {
+                self.driver
+                    .can_sample_rate(rate.0.into())
+                    .ok()
+                    .unwrap_or(false)
+            })
+            .collect();
+        if rates.is_empty() {
+            return Ok(Vec::new().into_iter());
         }
+        let supported_configs = vec![SupportedStreamConfigRange {
+            min_channels: 1,
+            max_channels: f.channels,
+            sample_rates: SupportedSampleRates::Discrete(rates),
+            buffer_size: f.buffer_size.clone(),
+            sample_format: f.sample_format.clone(),
+            endianness: f.endianness,
+            channel_layouts: Vec::new(),
+            periods: SupportedPeriods::Unknown,
+        }];
         Ok(supported_configs.into_iter())
     }
 
@@ -140,11 +149,15 @@ impl Device {
         let data_type = self.driver.input_data_type().map_err(default_config_err)?;
         let sample_format = convert_data_type(&data_type)
             .ok_or(DefaultStreamConfigError::StreamTypeNotSupported)?;
+        let endianness = data_type_endianness(&data_type);
         Ok(SupportedStreamConfig {
             channels,
             sample_rate,
             buffer_size,
             sample_format,
+            endianness,
+            channel_layout: None,
+            periods: SupportedPeriods::Unknown,
         })
     }
 
@@ -160,11 +173,15 @@ impl Device {
         let data_type = self.driver.output_data_type().map_err(default_config_err)?;
         let sample_format = convert_data_type(&data_type)
             .ok_or(DefaultStreamConfigError::StreamTypeNotSupported)?;
+        let endianness = data_type_endianness(&data_type);
         Ok(SupportedStreamConfig {
             channels,
             sample_rate,
             buffer_size,
             sample_format,
+            endianness,
+            channel_layout: None,
+            periods: SupportedPeriods::Unknown,
         })
     }
 }
@@ -213,18 +230,31 @@ pub(crate) fn convert_data_type(ty: &sys::AsioSampleType) -> Option<SampleFormat
         sys::AsioSampleType::ASIOSTInt16LSB => SampleFormat::I16,
         sys::AsioSampleType::ASIOSTFloat32MSB => SampleFormat::F32,
         sys::AsioSampleType::ASIOSTFloat32LSB => SampleFormat::F32,
-        // NOTE: While ASIO does not support these formats directly, the stream callback created by
-        // CPAL supports converting back and forth between the following. This is because many ASIO
-        // drivers only support `Int32` formats, while CPAL does not support this format at all. We
-        // allow for this implicit conversion temporarily until CPAL gets support for an `I32`
-        // format.
-        sys::AsioSampleType::ASIOSTInt32MSB => SampleFormat::I16,
-        sys::AsioSampleType::ASIOSTInt32LSB => SampleFormat::I16,
+        sys::AsioSampleType::ASIOSTInt32MSB => SampleFormat::I32,
+        sys::AsioSampleType::ASIOSTInt32LSB => SampleFormat::I32,
+        sys::AsioSampleType::ASIOSTFloat64MSB => SampleFormat::F64,
+        sys::AsioSampleType::ASIOSTFloat64LSB => SampleFormat::F64,
         _ => return None,
     };
     Some(fmt)
 }
 
+/// The byte order in which the driver exchanges samples of the given type.
+pub(crate) fn data_type_endianness(ty: &sys::AsioSampleType) -> Endianness {
+    match *ty {
+        sys::AsioSampleType::ASIOSTInt16MSB
+        | sys::AsioSampleType::ASIOSTInt24MSB
+        | sys::AsioSampleType::ASIOSTInt32MSB
+        | sys::AsioSampleType::ASIOSTFloat32MSB
+        | sys::AsioSampleType::ASIOSTFloat64MSB
+        | sys::AsioSampleType::ASIOSTInt32MSB16
+        | sys::AsioSampleType::ASIOSTInt32MSB18
+        | sys::AsioSampleType::ASIOSTInt32MSB20
+        | sys::AsioSampleType::ASIOSTInt32MSB24 => Endianness::Big,
+        _ => Endianness::Little,
+    }
+}
+
 fn default_config_err(e: sys::AsioError) -> DefaultStreamConfigError {
     match e {
         sys::AsioError::NoDrivers | sys::AsioError::HardwareMalfunction => {
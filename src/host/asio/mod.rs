@@ -3,8 +3,9 @@ extern crate parking_lot;
 
 use crate::{
     BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat,
-    StreamConfig, StreamError, SupportedStreamConfig, SupportedStreamConfigsError,
+    EffectiveConfig, InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlanarData,
+    PlayStreamError, SampleFormat, StreamConfig, StreamError, SupportedStreamConfig,
+    SupportedStreamConfigsError,
 };
 use traits::{DeviceTrait, HostTrait, StreamTrait};
 
@@ -109,6 +110,46 @@ impl DeviceTrait for Device {
     {
         Device::build_output_stream_raw(self, config, sample_format, data_callback, error_callback)
     }
+
+    fn build_input_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        Device::build_input_stream_planar(
+            self,
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        Device::build_output_stream_planar(
+            self,
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+        )
+    }
 }
 
 impl StreamTrait for Stream {
@@ -119,4 +160,12 @@ impl StreamTrait for Stream {
     fn pause(&self) -> Result<(), PauseStreamError> {
         Stream::pause(self)
     }
+
+    fn latency(&self) -> Option<std::time::Duration> {
+        Some(self.latency)
+    }
+
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        Some(self.effective_config.clone())
+    }
 }
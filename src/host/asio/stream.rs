@@ -4,10 +4,11 @@ extern crate num_traits;
 use self::num_traits::PrimInt;
 use super::parking_lot::Mutex;
 use super::Device;
+use crate::planar::PlanarBuffer;
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, InputCallbackInfo,
-    OutputCallbackInfo, PauseStreamError, PlayStreamError, Sample, SampleFormat, StreamConfig,
-    StreamError,
+    BackendSpecificError, BufferSize, BuildStreamError, Data, EffectiveConfig, FrameCount,
+    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlanarData, PlayStreamError, Sample,
+    SampleFormat, StreamConfig, StreamError,
 };
 use std;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -22,6 +23,7 @@ trait Silence {
 trait AsioSample: Clone + Copy + Silence + std::ops::Add<Self, Output = Self> {
     fn to_cpal_sample<T: Sample>(&self) -> T;
     fn from_cpal_sample<T: Sample>(&T) -> Self;
+    fn swap_bytes(self) -> Self;
 }
 
 // Used to keep track of whether or not the current current asio stream buffer requires
@@ -32,12 +34,22 @@ struct SilenceAsioBuffer {
     second: bool,
 }
 
+// Pointers to the ASIO buffer of each channel, handed to planar stream callbacks.
+//
+// These only ever point into the buffers of the `AsioStreams` that the callback locks before use.
+struct AsioPlanes(Vec<*mut ()>);
+
+unsafe impl Send for AsioPlanes {}
+
 pub struct Stream {
     playing: Arc<AtomicBool>,
     // Ensure the `Driver` does not terminate until the last stream is dropped.
     driver: Arc<sys::Driver>,
     asio_streams: Arc<Mutex<sys::AsioStreams>>,
     callback_id: sys::CallbackId,
+    // The configuration the stream runs with and the time it takes to play its buffer.
+    pub(super) effective_config: EffectiveConfig,
+    pub(super) latency: std::time::Duration,
 }
 
 impl Stream {
@@ -87,6 +99,7 @@ impl Device {
 
         // Set the input callback.
         // This is most performance critical part of the ASIO bindings.
+        let (effective_config, latency) = effective_config(config, sample_format, buffer_size);
         let config = config.clone();
         let callback_id = self.driver.add_callback(move |callback_info| unsafe {
             // If not playing return early.
@@ -178,11 +191,8 @@ impl Device {
                     );
                 }
 
-                // TODO: Add support for the following sample formats to CPAL and simplify the
-                // `process_output_callback` function above by removing the unnecessary sample
-                // conversion function.
-                (&sys::AsioSampleType::ASIOSTInt32LSB, SampleFormat::I16) => {
-                    process_input_callback::<i32, i16, _, _>(
+                (&sys::AsioSampleType::ASIOSTInt32LSB, SampleFormat::I32) => {
+                    process_input_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -191,8 +201,8 @@ impl Device {
                         from_le,
                     );
                 }
-                (&sys::AsioSampleType::ASIOSTInt32MSB, SampleFormat::I16) => {
-                    process_input_callback::<i32, i16, _, _>(
+                (&sys::AsioSampleType::ASIOSTInt32MSB, SampleFormat::I32) => {
+                    process_input_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -203,9 +213,9 @@ impl Device {
                 }
                 // TODO: Handle endianness conversion for floats? We currently use the `PrimInt`
                 // trait for the `to_le` and `to_be` methods, but this does not support floats.
-                (&sys::AsioSampleType::ASIOSTFloat64LSB, SampleFormat::F32)
-                | (&sys::AsioSampleType::ASIOSTFloat64MSB, SampleFormat::F32) => {
-                    process_input_callback::<f64, f32, _, _>(
+                (&sys::AsioSampleType::ASIOSTFloat64LSB, SampleFormat::F64)
+                | (&sys::AsioSampleType::ASIOSTFloat64MSB, SampleFormat::F64) => {
+                    process_input_callback::<f64, f64, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -234,6 +244,8 @@ impl Device {
             driver,
             asio_streams,
             callback_id,
+            effective_config,
+            latency,
         })
     }
 
@@ -270,6 +282,7 @@ impl Device {
         let playing = Arc::clone(&stream_playing);
         let asio_streams = self.asio_streams.clone();
 
+        let (effective_config, latency) = effective_config(config, sample_format, buffer_size);
         let config = config.clone();
         let callback_id = self.driver.add_callback(move |callback_info| unsafe {
             // If not playing, return early.
@@ -401,11 +414,8 @@ impl Device {
                     );
                 }
 
-                // TODO: Add support for the following sample formats to CPAL and simplify the
-                // `process_output_callback` function above by removing the unnecessary sample
-                // conversion function.
-                (SampleFormat::I16, &sys::AsioSampleType::ASIOSTInt32LSB) => {
-                    process_output_callback::<i16, i32, _, _>(
+                (SampleFormat::I32, &sys::AsioSampleType::ASIOSTInt32LSB) => {
+                    process_output_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -415,8 +425,8 @@ impl Device {
                         to_le,
                     );
                 }
-                (SampleFormat::I16, &sys::AsioSampleType::ASIOSTInt32MSB) => {
-                    process_output_callback::<i16, i32, _, _>(
+                (SampleFormat::I32, &sys::AsioSampleType::ASIOSTInt32MSB) => {
+                    process_output_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -428,9 +438,9 @@ impl Device {
                 }
                 // TODO: Handle endianness conversion for floats? We currently use the `PrimInt`
                 // trait for the `to_le` and `to_be` methods, but this does not support floats.
-                (SampleFormat::F32, &sys::AsioSampleType::ASIOSTFloat64LSB)
-                | (SampleFormat::F32, &sys::AsioSampleType::ASIOSTFloat64MSB) => {
-                    process_output_callback::<f32, f64, _, _>(
+                (SampleFormat::F64, &sys::AsioSampleType::ASIOSTFloat64LSB)
+                | (SampleFormat::F64, &sys::AsioSampleType::ASIOSTFloat64MSB) => {
+                    process_output_callback::<f64, f64, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -460,6 +470,244 @@ impl Device {
             driver,
             asio_streams,
             callback_id,
+            effective_config,
+            latency,
+        })
+    }
+
+    pub fn build_input_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        _error_callback: E,
+    ) -> Result<Stream, BuildStreamError>
+    where
+        D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let stream_type = self.driver.input_data_type().map_err(build_stream_err)?;
+
+        // Ensure that the desired sample type is supported.
+        let expected_sample_format = super::device::convert_data_type(&stream_type)
+            .ok_or(BuildStreamError::StreamConfigNotSupported)?;
+        if sample_format != expected_sample_format {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        let swap_bytes = !super::device::data_type_endianness(&stream_type).is_native();
+
+        let num_channels = config.channels as usize;
+        let buffer_size = self.get_or_create_input_stream(config, sample_format)?;
+        let mut planes = AsioPlanes(vec![std::ptr::null_mut(); num_channels]);
+        // Holds the byte swapped samples when the driver does not use the native byte order, as
+        // the ASIO buffers are shared with any other input stream of the driver.
+        let mut swapped = PlanarBuffer::new(num_channels, sample_format);
+        if swap_bytes {
+            swapped.resize(buffer_size);
+        }
+
+        let stream_playing = Arc::new(AtomicBool::new(false));
+        let playing = Arc::clone(&stream_playing);
+        let asio_streams = self.asio_streams.clone();
+
+        // Set the input callback.
+        //
+        // ASIO buffers are already non-interleaved, so they are handed to the user callback
+        // without copying unless their byte order has to be swapped.
+        let (effective_config, latency) = effective_config(config, sample_format, buffer_size);
+        let config = config.clone();
+        let callback_id = self.driver.add_callback(move |callback_info| unsafe {
+            // If not playing return early.
+            if !playing.load(Ordering::SeqCst) {
+                return;
+            }
+
+            // There is 0% chance of lock contention the host only locks when recreating streams.
+            let stream_lock = asio_streams.lock();
+            let ref asio_stream = match stream_lock.input {
+                Some(ref asio_stream) => asio_stream,
+                None => return,
+            };
+
+            let n_frames = asio_stream.buffer_size as usize;
+            let data = asio_planar_data(
+                &mut planes,
+                asio_stream,
+                callback_info.buffer_index as usize,
+                sample_format,
+            );
+
+            let callback = system_time_to_stream_instant(callback_info.system_time);
+            let delay = frames_to_duration(n_frames, config.sample_rate);
+            let capture = callback
+                .sub(delay)
+                .expect("`capture` occurs before origin of alsa `StreamInstant`");
+            let timestamp = crate::InputStreamTimestamp { callback, capture };
+            let info = InputCallbackInfo { timestamp };
+
+            if swap_bytes {
+                swapped.resize(n_frames);
+                let mut swapped = swapped.planar_data();
+                for ch_ix in 0..num_channels {
+                    let channel = swapped.channel_bytes_mut(ch_ix);
+                    channel.copy_from_slice(data.channel_bytes(ch_ix));
+                    swap_sample_bytes(channel, sample_format.sample_size());
+                }
+                data_callback(&swapped, &info);
+            } else {
+                data_callback(&data, &info);
+            }
+        });
+
+        let driver = self.driver.clone();
+        let asio_streams = self.asio_streams.clone();
+
+        // Immediately start the device?
+        self.driver.start().map_err(build_stream_err)?;
+
+        Ok(Stream {
+            playing: stream_playing,
+            driver,
+            asio_streams,
+            callback_id,
+            effective_config,
+            latency,
+        })
+    }
+
+    pub fn build_output_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        _error_callback: E,
+    ) -> Result<Stream, BuildStreamError>
+    where
+        D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let stream_type = self.driver.output_data_type().map_err(build_stream_err)?;
+
+        // Ensure that the desired sample type is supported.
+        let expected_sample_format = super::device::convert_data_type(&stream_type)
+            .ok_or(BuildStreamError::StreamConfigNotSupported)?;
+        if sample_format != expected_sample_format {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        let swap_bytes = !super::device::data_type_endianness(&stream_type).is_native();
+
+        let num_channels = config.channels as usize;
+        let buffer_size = self.get_or_create_output_stream(config, sample_format)?;
+        let mut planes = AsioPlanes(vec![std::ptr::null_mut(); num_channels]);
+        let mut rendered = PlanarBuffer::new(num_channels, sample_format);
+        rendered.resize(buffer_size);
+        let mut silence_asio_buffer = SilenceAsioBuffer::default();
+
+        let stream_playing = Arc::new(AtomicBool::new(false));
+        let playing = Arc::clone(&stream_playing);
+        let asio_streams = self.asio_streams.clone();
+
+        // Set the output callback.
+        //
+        // Like interleaved output streams, planar output streams sum into the ASIO buffers so that
+        // several streams can play through one driver. The first stream to use a buffer half
+        // renders straight into the non-interleaved ASIO buffers, which is equivalent to silencing
+        // them and summing. Any other stream renders into its own buffer and sums it in.
+        let (effective_config, latency) = effective_config(config, sample_format, buffer_size);
+        let config = config.clone();
+        let callback_id = self.driver.add_callback(move |callback_info| unsafe {
+            // If not playing, return early.
+            if !playing.load(Ordering::SeqCst) {
+                return;
+            }
+
+            // There is 0% chance of lock contention the host only locks when recreating streams.
+            let stream_lock = asio_streams.lock();
+            let ref asio_stream = match stream_lock.output {
+                Some(ref asio_stream) => asio_stream,
+                None => return,
+            };
+
+            // Check whether this is the first stream to use the current buffer half, in the same
+            // way as `build_output_stream_raw`.
+            let silence = match callback_info.buffer_index {
+                0 if !silence_asio_buffer.first => {
+                    silence_asio_buffer.first = true;
+                    silence_asio_buffer.second = false;
+                    true
+                }
+                0 => false,
+                1 if !silence_asio_buffer.second => {
+                    silence_asio_buffer.second = true;
+                    silence_asio_buffer.first = false;
+                    true
+                }
+                1 => false,
+                _ => unreachable!("ASIO uses a double-buffer so there should only be 2"),
+            };
+
+            let n_frames = asio_stream.buffer_size as usize;
+            let buffer_index = callback_info.buffer_index as usize;
+            let callback = system_time_to_stream_instant(callback_info.system_time);
+            let delay = frames_to_duration(n_frames, config.sample_rate);
+            let playback = callback
+                .add(delay)
+                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+            let timestamp = crate::OutputStreamTimestamp { callback, playback };
+            let info = OutputCallbackInfo { timestamp };
+
+            if silence {
+                let mut data =
+                    asio_planar_data(&mut planes, asio_stream, buffer_index, sample_format);
+                data_callback(&mut data, &info);
+                if swap_bytes {
+                    for ch_ix in 0..num_channels {
+                        swap_sample_bytes(
+                            data.channel_bytes_mut(ch_ix),
+                            sample_format.sample_size(),
+                        );
+                    }
+                }
+                return;
+            }
+
+            rendered.resize(n_frames);
+            let mut data = rendered.planar_data();
+            data_callback(&mut data, &info);
+            match sample_format {
+                SampleFormat::I16 => {
+                    sum_into_asio_channels::<i16>(&data, asio_stream, buffer_index, swap_bytes)
+                }
+                SampleFormat::I32 => {
+                    sum_into_asio_channels::<i32>(&data, asio_stream, buffer_index, swap_bytes)
+                }
+                SampleFormat::F32 => {
+                    sum_into_asio_channels::<f32>(&data, asio_stream, buffer_index, swap_bytes)
+                }
+                SampleFormat::F64 => {
+                    sum_into_asio_channels::<f64>(&data, asio_stream, buffer_index, swap_bytes)
+                }
+                unsupported_format => unreachable!(
+                    "`build_output_stream_planar` should have returned with unsupported \
+                     format {:?}",
+                    unsupported_format
+                ),
+            }
+        });
+
+        let driver = self.driver.clone();
+        let asio_streams = self.asio_streams.clone();
+
+        // Immediately start the device?
+        self.driver.start().map_err(build_stream_err)?;
+
+        Ok(Stream {
+            playing: stream_playing,
+            driver,
+            asio_streams,
+            callback_id,
+            effective_config,
+            latency,
         })
     }
 
@@ -483,10 +731,10 @@ impl Device {
         let num_channels = config.channels as usize;
         let ref mut streams = *self.asio_streams.lock();
 
-        let buffer_size = match config.buffer_size {
-            BufferSize::Fixed(v) => Some(v as i32),
-            BufferSize::Default => None,
-        };
+        let buffer_size = config
+            .buffer_size
+            .frames(config.sample_rate)
+            .map(|v| v as i32);
 
         // Either create a stream if thers none or had back the
         // size of the current one.
@@ -530,10 +778,10 @@ impl Device {
         let num_channels = config.channels as usize;
         let ref mut streams = *self.asio_streams.lock();
 
-        let buffer_size = match config.buffer_size {
-            BufferSize::Fixed(v) => Some(v as i32),
-            BufferSize::Default => None,
-        };
+        let buffer_size = config
+            .buffer_size
+            .frames(config.sample_rate)
+            .map(|v| v as i32);
 
         // Either create a stream if thers none or had back the
         // size of the current one.
@@ -589,16 +837,20 @@ impl AsioSample for i16 {
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
         Sample::from(t)
     }
+    fn swap_bytes(self) -> Self {
+        i16::swap_bytes(self)
+    }
 }
 
 impl AsioSample for i32 {
     fn to_cpal_sample<T: Sample>(&self) -> T {
-        let s = (*self >> 16) as i16;
-        s.to_cpal_sample()
+        T::from(self)
     }
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
-        let s = i16::from_cpal_sample(t);
-        (s as i32) << 16
+        Sample::from(t)
+    }
+    fn swap_bytes(self) -> Self {
+        i32::swap_bytes(self)
     }
 }
 
@@ -609,16 +861,20 @@ impl AsioSample for f32 {
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
         Sample::from(t)
     }
+    fn swap_bytes(self) -> Self {
+        f32::from_bits(self.to_bits().swap_bytes())
+    }
 }
 
 impl AsioSample for f64 {
     fn to_cpal_sample<T: Sample>(&self) -> T {
-        let f = *self as f32;
-        f.to_cpal_sample()
+        T::from(self)
     }
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
-        let f = f32::from_cpal_sample(t);
-        f as f64
+        Sample::from(t)
+    }
+    fn swap_bytes(self) -> Self {
+        f64::from_bits(self.to_bits().swap_bytes())
     }
 }
 
@@ -645,6 +901,24 @@ fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Dura
     std::time::Duration::new(secs, nanos)
 }
 
+/// The requested configuration with the buffer size of the driver, along with the time it takes
+/// to play or record that buffer.
+fn effective_config(
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    buffer_size: usize,
+) -> (EffectiveConfig, std::time::Duration) {
+    let effective_config = EffectiveConfig {
+        config: StreamConfig {
+            buffer_size: BufferSize::Fixed(buffer_size as FrameCount),
+            ..config.clone()
+        },
+        sample_format,
+    };
+    let latency = frames_to_duration(buffer_size, config.sample_rate);
+    (effective_config, latency)
+}
+
 /// Check whether or not the desired config is supported by the stream.
 ///
 /// Checks sample rate, data type and then finally the number of channels.
@@ -654,10 +928,17 @@ fn check_config(
     sample_format: SampleFormat,
     num_asio_channels: u16,
 ) -> Result<(), BuildStreamError> {
+    if config.channel_selection.is_some()
+        || config.period_size.is_some()
+        || config.period_count.is_some()
+    {
+        return Err(BuildStreamError::StreamConfigNotSupported);
+    }
     let StreamConfig {
         channels,
         sample_rate,
         buffer_size,
+        ..
     } = config;
     // Try and set the sample rate to what the user selected.
     let sample_rate = sample_rate.0.into();
@@ -673,10 +954,17 @@ fn check_config(
             return Err(BuildStreamError::StreamConfigNotSupported);
         }
     }
-    // unsigned formats are not supported by asio
+    // 8-bit, 24-bit, unsigned and companded formats are not supported by asio
     match sample_format {
-        SampleFormat::I16 | SampleFormat::F32 => (),
-        SampleFormat::U16 => return Err(BuildStreamError::StreamConfigNotSupported),
+        SampleFormat::I16 | SampleFormat::I32 | SampleFormat::F32 | SampleFormat::F64 => (),
+        SampleFormat::I8
+        | SampleFormat::U8
+        | SampleFormat::U16
+        | SampleFormat::U32
+        | SampleFormat::I24
+        | SampleFormat::I24Packed
+        | SampleFormat::MuLaw
+        | SampleFormat::ALaw => return Err(BuildStreamError::StreamConfigNotSupported),
     }
     if *channels > num_asio_channels {
         return Err(BuildStreamError::StreamConfigNotSupported);
@@ -738,6 +1026,58 @@ unsafe fn asio_channel_slice_mut<T>(
     std::slice::from_raw_parts_mut(buff_ptr, asio_stream.buffer_size as usize)
 }
 
+/// Points `planes` at the ASIO buffer half for `buffer_index` of each channel and wraps them in a
+/// `PlanarData`.
+///
+/// Safety: the returned `PlanarData` must not outlive the lock on `asio_stream`, and `planes` must
+/// not hold more channels than the stream.
+unsafe fn asio_planar_data(
+    planes: &mut AsioPlanes,
+    asio_stream: &sys::AsioStream,
+    buffer_index: usize,
+    sample_format: SampleFormat,
+) -> PlanarData {
+    for (ch_ix, plane) in planes.0.iter_mut().enumerate() {
+        *plane = asio_stream.buffer_infos[ch_ix].buffers[buffer_index] as *mut ();
+    }
+    PlanarData::from_parts(
+        planes.0.as_ptr(),
+        planes.0.len(),
+        asio_stream.buffer_size as usize,
+        sample_format,
+    )
+}
+
+/// Adds the samples of each channel of `data` to the matching ASIO channel.
+///
+/// Safety: `data` must not hold more channels or frames than the stream.
+unsafe fn sum_into_asio_channels<B>(
+    data: &PlanarData,
+    asio_stream: &sys::AsioStream,
+    buffer_index: usize,
+    swap_bytes: bool,
+) where
+    B: AsioSample + Sample,
+{
+    let swap = |s: B| if swap_bytes { s.swap_bytes() } else { s };
+    for ch_ix in 0..data.channels() {
+        let channel = data
+            .channel::<B>(ch_ix)
+            .expect("the sample format of the stream was checked when it was built");
+        let asio_channel = asio_channel_slice_mut::<B>(asio_stream, buffer_index, ch_ix);
+        for (s, s_asio) in channel.iter().zip(asio_channel) {
+            *s_asio = swap(swap(*s_asio) + *s);
+        }
+    }
+}
+
+/// Reverses the byte order of every sample in the given buffer.
+fn swap_sample_bytes(buffer: &mut [u8], sample_size: usize) {
+    for sample in buffer.chunks_mut(sample_size) {
+        sample.reverse();
+    }
+}
+
 fn build_stream_err(e: sys::AsioError) -> BuildStreamError {
     match e {
         sys::AsioError::NoDrivers | sys::AsioError::HardwareMalfunction => {
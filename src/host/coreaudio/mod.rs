@@ -7,24 +7,25 @@ use self::coreaudio::audio_unit::{AudioUnit, Element, Scope};
 use self::coreaudio::sys::{
     kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyBufferFrameSize,
     kAudioDevicePropertyBufferFrameSizeRange, kAudioDevicePropertyDeviceNameCFString,
-    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyScopeOutput,
-    kAudioDevicePropertyStreamConfiguration, kAudioDevicePropertyStreamFormat,
-    kAudioFormatFlagIsFloat, kAudioFormatFlagIsPacked, kAudioFormatLinearPCM,
-    kAudioObjectPropertyElementMaster, kAudioObjectPropertyScopeGlobal,
+    kAudioDevicePropertyDeviceUID, kAudioDevicePropertyNominalSampleRate,
+    kAudioDevicePropertyScopeOutput, kAudioDevicePropertyStreamConfiguration,
+    kAudioDevicePropertyStreamFormat, kAudioFormatFlagIsFloat, kAudioFormatFlagIsPacked,
+    kAudioFormatLinearPCM, kAudioObjectPropertyElementMaster, kAudioObjectPropertyScopeGlobal,
     kAudioObjectPropertyScopeInput, kAudioObjectPropertyScopeOutput,
     kAudioOutputUnitProperty_CurrentDevice, kAudioOutputUnitProperty_EnableIO,
     kAudioUnitProperty_StreamFormat, kCFStringEncodingUTF8, AudioBuffer, AudioBufferList,
     AudioDeviceID, AudioObjectAddPropertyListener, AudioObjectGetPropertyData,
     AudioObjectGetPropertyDataSize, AudioObjectID, AudioObjectPropertyAddress,
-    AudioObjectPropertyScope, AudioObjectRemovePropertyListener, AudioObjectSetPropertyData,
-    AudioStreamBasicDescription, AudioValueRange, OSStatus,
+    AudioObjectPropertyScope, AudioObjectPropertySelector, AudioObjectRemovePropertyListener,
+    AudioObjectSetPropertyData, AudioStreamBasicDescription, AudioValueRange, OSStatus,
 };
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
     BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
-    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    DefaultStreamConfigError, DeviceId, DeviceIdError, DeviceNameError, DevicesError,
+    EffectiveConfig, Endianness, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
+    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
+    SupportedPeriods, SupportedSampleRates, SupportedStreamConfig, SupportedStreamConfigRange,
     SupportedStreamConfigsError,
 };
 use std::cell::RefCell;
@@ -85,6 +86,10 @@ impl DeviceTrait for Device {
         Device::name(self)
     }
 
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Device::id(self)
+    }
+
     fn supported_input_configs(
         &self,
     ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
@@ -141,8 +146,23 @@ pub struct Device {
 
 impl Device {
     fn name(&self) -> Result<String, DeviceNameError> {
+        Ok(self.string_property(kAudioDevicePropertyDeviceNameCFString)?)
+    }
+
+    // The UID persists across reboots, unlike the `AudioDeviceID`.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(
+            self.string_property(kAudioDevicePropertyDeviceUID)?,
+        ))
+    }
+
+    // Reads a `CFString` property of the device.
+    fn string_property(
+        &self,
+        selector: AudioObjectPropertySelector,
+    ) -> Result<String, BackendSpecificError> {
         let property_address = AudioObjectPropertyAddress {
-            mSelector: kAudioDevicePropertyDeviceNameCFString,
+            mSelector: selector,
             mScope: kAudioDevicePropertyScopeOutput,
             mElement: kAudioObjectPropertyElementMaster,
         };
@@ -178,10 +198,8 @@ impl Device {
                     kCFStringEncodingUTF8,
                 );
                 if result == 0 {
-                    let description =
-                        "core foundation failed to return device name string".to_string();
-                    let err = BackendSpecificError { description };
-                    return Err(err.into());
+                    let description = "core foundation failed to return device string".to_string();
+                    return Err(BackendSpecificError { description });
                 }
                 let name: &CStr = CStr::from_ptr(buf.as_ptr());
                 return Ok(name.to_str().unwrap().to_owned());
@@ -285,11 +303,17 @@ impl Device {
             let mut fmts = vec![];
             for range in ranges {
                 let fmt = SupportedStreamConfigRange {
-                    channels: n_channels as ChannelCount,
-                    min_sample_rate: SampleRate(range.mMinimum as _),
-                    max_sample_rate: SampleRate(range.mMaximum as _),
+                    min_channels: n_channels as ChannelCount,
+                    max_channels: n_channels as ChannelCount,
+                    sample_rates: SupportedSampleRates::Range {
+                        min: SampleRate(range.mMinimum as _),
+                        max: SampleRate(range.mMaximum as _),
+                    },
                     buffer_size: buffer_size.clone(),
                     sample_format: sample_format,
+                    endianness: Endianness::native(),
+                    channel_layouts: Vec::new(),
+                    periods: SupportedPeriods::Unknown,
                 };
                 fmts.push(fmt);
             }
@@ -388,6 +412,9 @@ impl Device {
                 channels: asbd.mChannelsPerFrame as _,
                 buffer_size: buffer_size,
                 sample_format: sample_format,
+                endianness: Endianness::native(),
+                channel_layout: None,
+                periods: SupportedPeriods::Unknown,
             };
             Ok(config)
         }
@@ -420,6 +447,9 @@ struct StreamInner {
     // a stream associated with the device.
     #[allow(dead_code)]
     device_id: AudioDeviceID,
+    // The configuration the stream runs with and the time it takes to play its buffer.
+    effective_config: EffectiveConfig,
+    latency: Duration,
 }
 
 // TODO need stronger error identification
@@ -539,6 +569,13 @@ impl Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.channel_selection.is_some()
+            || config.period_size.is_some()
+            || config.period_count.is_some()
+        {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+
         // The scope and element for working with a device's input stream.
         let scope = Scope::Output;
         let element = Element::Input;
@@ -704,6 +741,21 @@ impl Device {
                     SupportedBufferSize::Unknown => (),
                 }
             }
+            BufferSize::Latency(latency) => {
+                // Use the supported buffer size closest to the latency.
+                let buffer_size_range = get_io_buffer_frame_size_range(&audio_unit)?;
+                if let SupportedBufferSize::Range { min, max } = buffer_size_range {
+                    let v = crate::latency_frames(latency, config.sample_rate)
+                        .max(min)
+                        .min(max);
+                    audio_unit.set_property(
+                        kAudioDevicePropertyBufferFrameSize,
+                        scope,
+                        element,
+                        Some(&v),
+                    )?
+                }
+            }
             BufferSize::Default => (),
         }
 
@@ -748,12 +800,17 @@ impl Device {
             Ok(())
         })?;
 
+        let (effective_config, latency) =
+            effective_config(&audio_unit, scope, element, config, sample_format)?;
+
         audio_unit.start()?;
 
         Ok(Stream::new(StreamInner {
             playing: true,
             audio_unit,
             device_id: self.audio_device_id,
+            effective_config,
+            latency,
         }))
     }
 
@@ -768,6 +825,13 @@ impl Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.channel_selection.is_some()
+            || config.period_size.is_some()
+            || config.period_count.is_some()
+        {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+
         let mut audio_unit = audio_unit_from_device(self, false)?;
 
         // The scope and element for working with a device's output stream.
@@ -798,6 +862,21 @@ impl Device {
                     SupportedBufferSize::Unknown => (),
                 }
             }
+            BufferSize::Latency(latency) => {
+                // Use the supported buffer size closest to the latency.
+                let buffer_size_range = get_io_buffer_frame_size_range(&audio_unit)?;
+                if let SupportedBufferSize::Range { min, max } = buffer_size_range {
+                    let v = crate::latency_frames(latency, config.sample_rate)
+                        .max(min)
+                        .min(max);
+                    audio_unit.set_property(
+                        kAudioDevicePropertyBufferFrameSize,
+                        scope,
+                        element,
+                        Some(&v),
+                    )?
+                }
+            }
             BufferSize::Default => (),
         }
 
@@ -840,12 +919,17 @@ impl Device {
             Ok(())
         })?;
 
+        let (effective_config, latency) =
+            effective_config(&audio_unit, scope, element, config, sample_format)?;
+
         audio_unit.start()?;
 
         Ok(Stream::new(StreamInner {
             playing: true,
             audio_unit,
             device_id: self.audio_device_id,
+            effective_config,
+            latency,
         }))
     }
 }
@@ -911,6 +995,14 @@ impl StreamTrait for Stream {
         }
         Ok(())
     }
+
+    fn latency(&self) -> Option<Duration> {
+        Some(self.inner.borrow().latency)
+    }
+
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        Some(self.inner.borrow().effective_config.clone())
+    }
 }
 
 fn check_os_status(os_status: OSStatus) -> Result<(), BackendSpecificError> {
@@ -923,6 +1015,28 @@ fn check_os_status(os_status: OSStatus) -> Result<(), BackendSpecificError> {
     }
 }
 
+// The requested configuration with the buffer size the device settled on, along with the time
+// it takes to play or record that buffer.
+fn effective_config(
+    audio_unit: &AudioUnit,
+    scope: Scope,
+    element: Element,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> Result<(EffectiveConfig, Duration), coreaudio::Error> {
+    let frames: u32 =
+        audio_unit.get_property(kAudioDevicePropertyBufferFrameSize, scope, element)?;
+    let effective_config = EffectiveConfig {
+        config: StreamConfig {
+            buffer_size: BufferSize::Fixed(frames),
+            ..config.clone()
+        },
+        sample_format,
+    };
+    let latency = frames_to_duration(frames as usize, config.sample_rate);
+    Ok((effective_config, latency))
+}
+
 fn get_io_buffer_frame_size_range(
     audio_unit: &AudioUnit,
 ) -> Result<SupportedBufferSize, coreaudio::Error> {
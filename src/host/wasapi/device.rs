@@ -1,8 +1,9 @@
 use crate::{
-    BackendSpecificError, BufferSize, Data, DefaultStreamConfigError, DeviceNameError,
-    DevicesError, InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate, StreamConfig,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError, COMMON_SAMPLE_RATES,
+    BackendSpecificError, BufferSize, Data, DefaultStreamConfigError, DeviceId, DeviceIdError,
+    DeviceNameError, DevicesError, Endianness, InputCallbackInfo, OutputCallbackInfo, SampleFormat,
+    SampleRate, StreamConfig, SupportedBufferSize, SupportedPeriods, SupportedSampleRates,
+    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    COMMON_SAMPLE_RATES,
 };
 use std;
 use std::ffi::OsString;
@@ -77,6 +78,18 @@ impl DeviceTrait for Device {
         Device::name(self)
     }
 
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Device::id(self)
+    }
+
+    fn supports_input(&self) -> bool {
+        self.data_flow() == eCapture
+    }
+
+    fn supports_output(&self) -> bool {
+        self.data_flow() == eRender
+    }
+
     fn supported_input_configs(
         &self,
     ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
@@ -156,6 +169,17 @@ impl Drop for WaveFormatExPtr {
     }
 }
 
+// Use RAII to make sure CoTaskMemFree is called on the string returned by `GetId`.
+struct DeviceIdPtr(LPWSTR);
+
+impl Drop for DeviceIdPtr {
+    fn drop(&mut self) {
+        unsafe {
+            CoTaskMemFree(self.0 as *mut _);
+        }
+    }
+}
+
 impl WaveFormat {
     // Given a pointer to some format, returns a valid copy of the format.
     pub fn copy_from_waveformatex_ptr(ptr: *const mmreg::WAVEFORMATEX) -> Option<Self> {
@@ -303,12 +327,15 @@ unsafe fn format_from_waveformatex_ptr(
         (*waveformatex_ptr).wBitsPerSample,
         (*waveformatex_ptr).wFormatTag,
     ) {
+        (8, mmreg::WAVE_FORMAT_PCM) => SampleFormat::U8,
         (16, mmreg::WAVE_FORMAT_PCM) => SampleFormat::I16,
         (32, mmreg::WAVE_FORMAT_IEEE_FLOAT) => SampleFormat::F32,
         (n_bits, mmreg::WAVE_FORMAT_EXTENSIBLE) => {
             let waveformatextensible_ptr = waveformatex_ptr as *const mmreg::WAVEFORMATEXTENSIBLE;
             let sub = (*waveformatextensible_ptr).SubFormat;
-            if n_bits == 16 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_PCM) {
+            if n_bits == 8 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_PCM) {
+                SampleFormat::U8
+            } else if n_bits == 16 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_PCM) {
                 SampleFormat::I16
             } else if n_bits == 32 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT) {
                 SampleFormat::F32
@@ -325,6 +352,9 @@ unsafe fn format_from_waveformatex_ptr(
         sample_rate: SampleRate((*waveformatex_ptr).nSamplesPerSec),
         buffer_size: SupportedBufferSize::Unknown,
         sample_format,
+        endianness: Endianness::native(),
+        channel_layout: None,
+        periods: SupportedPeriods::Unknown,
     };
     Some(format)
 }
@@ -382,6 +412,27 @@ impl Device {
         }
     }
 
+    // The endpoint ID string, which stays the same across reboots.
+    pub fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        unsafe {
+            let mut id: LPWSTR = ptr::null_mut();
+            check_result_backend_specific((*self.device).GetId(&mut id))?;
+            let id = DeviceIdPtr(id);
+
+            let mut len = 0;
+            while *id.0.offset(len) != 0 {
+                len += 1;
+            }
+            let id_slice = slice::from_raw_parts(id.0, len as usize);
+            let id_os_string: OsString = OsStringExt::from_wide(id_slice);
+            let id_string = match id_os_string.into_string() {
+                Ok(string) => string,
+                Err(os_string) => os_string.to_string_lossy().into(),
+            };
+            Ok(DeviceId::new(id_string))
+        }
+    }
+
     #[inline]
     fn from_immdevice(device: *mut IMMDevice) -> Self {
         Device {
@@ -526,16 +577,22 @@ impl Device {
                     return Err(err.into());
                 }
             };
-            let mut supported_formats = Vec::with_capacity(supported_sample_rates.len());
-            for rate in supported_sample_rates {
-                supported_formats.push(SupportedStreamConfigRange {
-                    channels: format.channels.clone(),
-                    min_sample_rate: SampleRate(rate as _),
-                    max_sample_rate: SampleRate(rate as _),
-                    buffer_size: format.buffer_size.clone(),
-                    sample_format: format.sample_format.clone(),
-                })
-            }
+            supported_sample_rates.sort();
+            let supported_formats = vec![SupportedStreamConfigRange {
+                min_channels: format.channels.clone(),
+                max_channels: format.channels.clone(),
+                sample_rates: SupportedSampleRates::Discrete(
+                    supported_sample_rates
+                        .into_iter()
+                        .map(|rate| SampleRate(rate as _))
+                        .collect(),
+                ),
+                buffer_size: format.buffer_size.clone(),
+                sample_format: format.sample_format.clone(),
+                endianness: format.endianness,
+                channel_layouts: Vec::new(),
+                periods: SupportedPeriods::Unknown,
+            }];
             Ok(supported_formats.into_iter())
         }
     }
@@ -647,16 +704,25 @@ impl Device {
                 }
             };
 
-            match config.buffer_size {
+            // The buffer duration of the audio client, in units of 100 nanoseconds.
+            let buffer_duration = match config.buffer_size {
                 BufferSize::Fixed(_) => {
                     // TO DO: We need IAudioClient3 to get buffersize ranges first
                     // Otherwise the supported ranges are unknown. In the mean time
                     // the smallest buffersize is selected and used.
                     return Err(BuildStreamError::StreamConfigNotSupported);
                 }
-                BufferSize::Default => (),
+                BufferSize::Latency(latency) => (latency.as_nanos() / 100) as i64,
+                BufferSize::Default => 0,
             };
 
+            if config.channel_selection.is_some()
+                || config.period_size.is_some()
+                || config.period_count.is_some()
+            {
+                return Err(BuildStreamError::StreamConfigNotSupported);
+            }
+
             // Computing the format and initializing the device.
             let waveformatex = {
                 let format_attempt = config_to_waveformatextensible(config, sample_format)
@@ -674,7 +740,7 @@ impl Device {
                 let hresult = (*audio_client).Initialize(
                     share_mode,
                     AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
-                    0,
+                    buffer_duration,
                     0,
                     &format_attempt.Format,
                     ptr::null(),
@@ -809,16 +875,25 @@ impl Device {
                 }
             };
 
-            match config.buffer_size {
+            // The buffer duration of the audio client, in units of 100 nanoseconds.
+            let buffer_duration = match config.buffer_size {
                 BufferSize::Fixed(_) => {
                     // TO DO: We need IAudioClient3 to get buffersize ranges first
                     // Otherwise the supported ranges are unknown. In the mean time
                     // the smallest buffersize is selected and used.
                     return Err(BuildStreamError::StreamConfigNotSupported);
                 }
-                BufferSize::Default => (),
+                BufferSize::Latency(latency) => (latency.as_nanos() / 100) as i64,
+                BufferSize::Default => 0,
             };
 
+            if config.channel_selection.is_some()
+                || config.period_size.is_some()
+                || config.period_count.is_some()
+            {
+                return Err(BuildStreamError::StreamConfigNotSupported);
+            }
+
             // Computing the format and initializing the device.
             let waveformatex = {
                 let format_attempt = config_to_waveformatextensible(config, sample_format)
@@ -836,7 +911,7 @@ impl Device {
                 let hresult = (*audio_client).Initialize(
                     share_mode,
                     AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
-                    0,
+                    buffer_duration,
                     0,
                     &format_attempt.Format,
                     ptr::null(),
@@ -1220,9 +1295,17 @@ fn config_to_waveformatextensible(
     sample_format: SampleFormat,
 ) -> Option<mmreg::WAVEFORMATEXTENSIBLE> {
     let format_tag = match sample_format {
-        SampleFormat::I16 => mmreg::WAVE_FORMAT_PCM,
+        SampleFormat::U8 | SampleFormat::I16 => mmreg::WAVE_FORMAT_PCM,
         SampleFormat::F32 => mmreg::WAVE_FORMAT_EXTENSIBLE,
-        SampleFormat::U16 => return None,
+        SampleFormat::I8
+        | SampleFormat::U16
+        | SampleFormat::I24
+        | SampleFormat::I24Packed
+        | SampleFormat::I32
+        | SampleFormat::U32
+        | SampleFormat::F64
+        | SampleFormat::MuLaw
+        | SampleFormat::ALaw => return None,
     };
     let channels = config.channels as WORD;
     let sample_rate = config.sample_rate.0 as DWORD;
@@ -1231,13 +1314,21 @@ fn config_to_waveformatextensible(
     let block_align = channels * sample_bytes;
     let bits_per_sample = 8 * sample_bytes;
     let cb_size = match sample_format {
-        SampleFormat::I16 => 0,
+        SampleFormat::U8 | SampleFormat::I16 => 0,
         SampleFormat::F32 => {
             let extensible_size = mem::size_of::<mmreg::WAVEFORMATEXTENSIBLE>();
             let ex_size = mem::size_of::<mmreg::WAVEFORMATEX>();
             (extensible_size - ex_size) as WORD
         }
-        SampleFormat::U16 => return None,
+        SampleFormat::I8
+        | SampleFormat::U16
+        | SampleFormat::I24
+        | SampleFormat::I24Packed
+        | SampleFormat::I32
+        | SampleFormat::U32
+        | SampleFormat::F64
+        | SampleFormat::MuLaw
+        | SampleFormat::ALaw => return None,
     };
     let waveformatex = mmreg::WAVEFORMATEX {
         wFormatTag: format_tag,
@@ -1255,9 +1346,17 @@ fn config_to_waveformatextensible(
     let channel_mask = KSAUDIO_SPEAKER_DIRECTOUT;
 
     let sub_format = match sample_format {
-        SampleFormat::I16 => ksmedia::KSDATAFORMAT_SUBTYPE_PCM,
+        SampleFormat::U8 | SampleFormat::I16 => ksmedia::KSDATAFORMAT_SUBTYPE_PCM,
         SampleFormat::F32 => ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
-        SampleFormat::U16 => return None,
+        SampleFormat::I8
+        | SampleFormat::U16
+        | SampleFormat::I24
+        | SampleFormat::I24Packed
+        | SampleFormat::I32
+        | SampleFormat::U32
+        | SampleFormat::F64
+        | SampleFormat::MuLaw
+        | SampleFormat::ALaw => return None,
     };
     let waveformatextensible = mmreg::WAVEFORMATEXTENSIBLE {
         Format: waveformatex,
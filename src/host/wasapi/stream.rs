@@ -8,8 +8,8 @@ use super::winapi::um::winbase;
 use super::winapi::um::winnt;
 use crate::traits::StreamTrait;
 use crate::{
-    BackendSpecificError, Data, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, StreamError,
+    BackendSpecificError, BufferSize, Data, EffectiveConfig, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, StreamError,
 };
 use std::mem;
 use std::ptr;
@@ -31,6 +31,10 @@ pub struct Stream {
     // This event is signalled after a new entry is added to `commands`, so that the `run()`
     // method can be notified.
     pending_scheduled_event: winnt::HANDLE,
+
+    // The configuration the stream runs with and the time it takes to play its buffer.
+    effective_config: EffectiveConfig,
+    latency: std::time::Duration,
 }
 
 struct RunContext {
@@ -80,6 +84,19 @@ pub struct StreamInner {
     pub sample_format: SampleFormat,
 }
 
+impl StreamInner {
+    // The requested configuration with the buffer size WASAPI allocated.
+    fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            config: crate::StreamConfig {
+                buffer_size: BufferSize::Fixed(self.max_frames_in_buffer),
+                ..self.config.clone()
+            },
+            sample_format: self.sample_format,
+        }
+    }
+}
+
 impl Stream {
     pub(crate) fn new_input<D, E>(
         stream_inner: StreamInner,
@@ -93,6 +110,11 @@ impl Stream {
         let pending_scheduled_event =
             unsafe { synchapi::CreateEventA(ptr::null_mut(), 0, 0, ptr::null()) };
         let (tx, rx) = channel();
+        let effective_config = stream_inner.effective_config();
+        let latency = frames_to_duration(
+            stream_inner.max_frames_in_buffer,
+            stream_inner.config.sample_rate,
+        );
 
         let run_context = RunContext {
             handles: vec![pending_scheduled_event, stream_inner.event],
@@ -107,6 +129,8 @@ impl Stream {
             thread: Some(thread),
             commands: tx,
             pending_scheduled_event,
+            effective_config,
+            latency,
         }
     }
 
@@ -122,6 +146,11 @@ impl Stream {
         let pending_scheduled_event =
             unsafe { synchapi::CreateEventA(ptr::null_mut(), 0, 0, ptr::null()) };
         let (tx, rx) = channel();
+        let effective_config = stream_inner.effective_config();
+        let latency = frames_to_duration(
+            stream_inner.max_frames_in_buffer,
+            stream_inner.config.sample_rate,
+        );
 
         let run_context = RunContext {
             handles: vec![pending_scheduled_event, stream_inner.event],
@@ -136,6 +165,8 @@ impl Stream {
             thread: Some(thread),
             commands: tx,
             pending_scheduled_event,
+            effective_config,
+            latency,
         }
     }
 
@@ -170,6 +201,12 @@ impl StreamTrait for Stream {
         self.push_command(Command::PauseStream);
         Ok(())
     }
+    fn latency(&self) -> Option<std::time::Duration> {
+        Some(self.latency)
+    }
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        Some(self.effective_config.clone())
+    }
 }
 
 impl Drop for AudioClientFlow {
@@ -8,9 +8,10 @@ use self::wasm_bindgen::JsCast;
 use self::web_sys::{AudioContext, AudioContextOptions};
 use crate::{
     BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    DeviceNameError, DevicesError, Endianness, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
+    SupportedBufferSize, SupportedPeriods, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex, RwLock};
@@ -100,15 +101,19 @@ impl Device {
             min: MIN_BUFFER_SIZE,
             max: MAX_BUFFER_SIZE,
         };
-        let configs: Vec<_> = (MIN_CHANNELS..=MAX_CHANNELS)
-            .map(|channels| SupportedStreamConfigRange {
-                channels,
-                min_sample_rate: MIN_SAMPLE_RATE,
-                max_sample_rate: MAX_SAMPLE_RATE,
-                buffer_size: buffer_size.clone(),
-                sample_format: SUPPORTED_SAMPLE_FORMAT,
-            })
-            .collect();
+        let configs = vec![SupportedStreamConfigRange {
+            min_channels: MIN_CHANNELS,
+            max_channels: MAX_CHANNELS,
+            sample_rates: SupportedSampleRates::Range {
+                min: MIN_SAMPLE_RATE,
+                max: MAX_SAMPLE_RATE,
+            },
+            buffer_size,
+            sample_format: SUPPORTED_SAMPLE_FORMAT,
+            endianness: Endianness::native(),
+            channel_layouts: Vec::new(),
+            periods: SupportedPeriods::Unknown,
+        }];
         Ok(configs.into_iter())
     }
 
@@ -123,9 +128,10 @@ impl Device {
         const EXPECT: &str = "expected at least one valid webaudio stream config";
         let mut configs: Vec<_> = self.supported_output_configs().expect(EXPECT).collect();
         configs.sort_by(|a, b| a.cmp_default_heuristics(b));
-        let config = configs
-            .into_iter()
-            .next()
+        let range = configs.into_iter().next().expect(EXPECT);
+        let channels = range.default_channels();
+        let config = range
+            .with_channels(channels)
             .expect(EXPECT)
             .with_sample_rate(DEFAULT_SAMPLE_RATE);
         Ok(config)
@@ -199,15 +205,10 @@ impl DeviceTrait for Device {
 
         let n_channels = config.channels as usize;
 
-        let buffer_size_frames = match config.buffer_size {
-            BufferSize::Fixed(v) => {
-                if v == 0 {
-                    return Err(BuildStreamError::StreamConfigNotSupported);
-                } else {
-                    v as usize
-                }
-            }
-            BufferSize::Default => DEFAULT_BUFFER_SIZE,
+        let buffer_size_frames = match config.buffer_size.frames(config.sample_rate) {
+            Some(0) => return Err(BuildStreamError::StreamConfigNotSupported),
+            Some(v) => v as usize,
+            None => DEFAULT_BUFFER_SIZE,
         };
         let buffer_size_samples = buffer_size_frames * n_channels;
         let buffer_time_step_secs = buffer_time_step_secs(buffer_size_frames, config.sample_rate);
@@ -455,6 +456,9 @@ fn valid_config(conf: &StreamConfig, sample_format: SampleFormat) -> bool {
         && conf.sample_rate <= MAX_SAMPLE_RATE
         && conf.sample_rate >= MIN_SAMPLE_RATE
         && sample_format == SUPPORTED_SAMPLE_FORMAT
+        && conf.channel_selection.is_none()
+        && conf.period_size.is_none()
+        && conf.period_count.is_none()
 }
 
 fn buffer_time_step_secs(buffer_size_frames: usize, sample_rate: SampleRate) -> f64 {
@@ -0,0 +1,195 @@
+//! Speaker positions of the channels of a stream.
+
+use crate::ChannelCount;
+
+/// The position of the speaker a channel is meant to be played through or was recorded from.
+///
+/// The abbreviations used by ALSA are given for reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelPosition {
+    /// A mono channel (MONO).
+    Mono,
+    /// Front left (FL).
+    FrontLeft,
+    /// Front right (FR).
+    FrontRight,
+    /// Front center (FC).
+    FrontCenter,
+    /// Low frequency effects (LFE).
+    LowFrequency,
+    /// Rear left (RL).
+    RearLeft,
+    /// Rear right (RR).
+    RearRight,
+    /// Rear center (RC).
+    RearCenter,
+    /// Side left (SL).
+    SideLeft,
+    /// Side right (SR).
+    SideRight,
+    /// Front left of center (FLC).
+    FrontLeftOfCenter,
+    /// Front right of center (FRC).
+    FrontRightOfCenter,
+    /// Rear left of center (RLC).
+    RearLeftOfCenter,
+    /// Rear right of center (RRC).
+    RearRightOfCenter,
+    /// Front left wide (FLW).
+    FrontLeftWide,
+    /// Front right wide (FRW).
+    FrontRightWide,
+    /// Front left high (FLH).
+    FrontLeftHigh,
+    /// Front center high (FCH).
+    FrontCenterHigh,
+    /// Front right high (FRH).
+    FrontRightHigh,
+    /// Top center (TC).
+    TopCenter,
+    /// Top front left (TFL).
+    TopFrontLeft,
+    /// Top front right (TFR).
+    TopFrontRight,
+    /// Top front center (TFC).
+    TopFrontCenter,
+    /// Top rear left (TRL).
+    TopRearLeft,
+    /// Top rear right (TRR).
+    TopRearRight,
+    /// Top rear center (TRC).
+    TopRearCenter,
+    /// Top front left of center (TFLC).
+    TopFrontLeftOfCenter,
+    /// Top front right of center (TFRC).
+    TopFrontRightOfCenter,
+    /// Top side left (TSL).
+    TopSideLeft,
+    /// Top side right (TSR).
+    TopSideRight,
+    /// Left low frequency effects (LLFE).
+    LowFrequencyLeft,
+    /// Right low frequency effects (RLFE).
+    LowFrequencyRight,
+    /// Bottom center (BC).
+    BottomCenter,
+    /// Bottom left of center (BLC).
+    BottomLeftOfCenter,
+    /// Bottom right of center (BRC).
+    BottomRightOfCenter,
+    /// A channel that is not connected to any speaker (NA).
+    NotApplicable,
+    /// A channel whose position is unknown.
+    Unknown,
+}
+
+/// The speaker positions of the channels of a stream, in channel order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChannelLayout {
+    positions: Vec<ChannelPosition>,
+}
+
+impl ChannelLayout {
+    /// A layout with the given speaker positions, one per channel.
+    pub fn new(positions: Vec<ChannelPosition>) -> Self {
+        ChannelLayout { positions }
+    }
+
+    /// The standard layout for the given number of channels, if there is one.
+    ///
+    /// Channel counts of 1 to 8 follow the channel order of the WAVE format: mono, stereo, 3.0,
+    /// quadraphonic, 5.0, 5.1, 6.1 and 7.1.
+    pub fn standard(channels: ChannelCount) -> Option<Self> {
+        use self::ChannelPosition::*;
+        let positions: &[ChannelPosition] = match channels {
+            1 => &[Mono],
+            2 => &[FrontLeft, FrontRight],
+            3 => &[FrontLeft, FrontRight, FrontCenter],
+            4 => &[FrontLeft, FrontRight, RearLeft, RearRight],
+            5 => &[FrontLeft, FrontRight, FrontCenter, RearLeft, RearRight],
+            6 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                RearLeft,
+                RearRight,
+            ],
+            7 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                RearCenter,
+                SideLeft,
+                SideRight,
+            ],
+            8 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                RearLeft,
+                RearRight,
+                SideLeft,
+                SideRight,
+            ],
+            _ => return None,
+        };
+        Some(ChannelLayout::new(positions.to_vec()))
+    }
+
+    /// The number of channels in the layout.
+    pub fn channels(&self) -> ChannelCount {
+        self.positions.len() as ChannelCount
+    }
+
+    /// The speaker positions, one per channel.
+    pub fn positions(&self) -> &[ChannelPosition] {
+        &self.positions
+    }
+
+    /// The speaker position of the given channel.
+    pub fn position(&self, channel: usize) -> Option<ChannelPosition> {
+        self.positions.get(channel).cloned()
+    }
+
+    /// The index of the channel at the given speaker position.
+    pub fn channel(&self, position: ChannelPosition) -> Option<usize> {
+        self.positions.iter().position(|&p| p == position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChannelLayout, ChannelPosition};
+
+    #[test]
+    fn standard_layouts() {
+        let surround = ChannelLayout::standard(6).unwrap();
+        assert_eq!(surround.channels(), 6);
+        assert_eq!(surround.position(3), Some(ChannelPosition::LowFrequency));
+        assert_eq!(surround.channel(ChannelPosition::FrontCenter), Some(2));
+        assert_eq!(surround.channel(ChannelPosition::SideLeft), None);
+        let surround = ChannelLayout::standard(7).unwrap();
+        assert_eq!(
+            surround.positions(),
+            [
+                ChannelPosition::FrontLeft,
+                ChannelPosition::FrontRight,
+                ChannelPosition::FrontCenter,
+                ChannelPosition::LowFrequency,
+                ChannelPosition::RearCenter,
+                ChannelPosition::SideLeft,
+                ChannelPosition::SideRight,
+            ]
+        );
+        for channels in 1..=8 {
+            assert_eq!(
+                ChannelLayout::standard(channels).unwrap().channels(),
+                channels
+            );
+        }
+        assert!(ChannelLayout::standard(9).is_none());
+    }
+}
@@ -100,13 +100,30 @@
 //!     SampleFormat::F32 => device.build_output_stream(&config, write_silence::<f32>, err_fn),
 //!     SampleFormat::I16 => device.build_output_stream(&config, write_silence::<i16>, err_fn),
 //!     SampleFormat::U16 => device.build_output_stream(&config, write_silence::<u16>, err_fn),
+//!     SampleFormat::I8 => device.build_output_stream(&config, write_silence::<i8>, err_fn),
+//!     SampleFormat::U8 => device.build_output_stream(&config, write_silence::<u8>, err_fn),
+//!     SampleFormat::I24 => device.build_output_stream(&config, write_silence::<cpal::I24>, err_fn),
+//!     SampleFormat::I24Packed => {
+//!         device.build_output_stream(&config, write_silence::<cpal::I24Packed>, err_fn)
+//!     }
+//!     SampleFormat::I32 => device.build_output_stream(&config, write_silence::<i32>, err_fn),
+//!     SampleFormat::U32 => device.build_output_stream(&config, write_silence::<u32>, err_fn),
+//!     SampleFormat::F64 => device.build_output_stream(&config, write_silence::<f64>, err_fn),
+//!     SampleFormat::MuLaw | SampleFormat::ALaw => {
+//!         device.build_output_stream_raw(&config, sample_format, write_companded_silence, err_fn)
+//!     }
 //! }.unwrap();
 //!
 //! fn write_silence<T: Sample>(data: &mut [T], _: &cpal::OutputCallbackInfo) {
 //!     for sample in data.iter_mut() {
-//!         *sample = Sample::from(&0.0);
+//!         *sample = T::EQUILIBRIUM;
 //!     }
 //! }
+//!
+//! fn write_companded_silence(data: &mut Data, _: &cpal::OutputCallbackInfo) {
+//!     let silence = vec![0i16; data.len()];
+//!     data.encode_companded(&silence);
+//! }
 //! ```
 //!
 //! Not all platforms automatically run the stream upon creation. To ensure the stream has started,
@@ -152,18 +169,39 @@ extern crate lazy_static;
 extern crate stdweb;
 extern crate thiserror;
 
+pub use adapt::{AdaptedStream, StreamAdaptation};
+pub use buffer::{AudioBuffer, AudioBufferPool};
+pub use convert::convert_samples;
+pub use dither::{Dither, NoiseShaping};
 pub use error::*;
+pub use frames::{Frame, Frames, FramesMut};
+pub use layout::{ChannelLayout, ChannelPosition};
+pub use mix::ChannelMatrix;
+pub use negotiate::{ConfigMismatch, ConfigRequest, NegotiatedConfig, RejectedConfig};
 pub use platform::{
     available_hosts, default_host, host_from_id, Device, Devices, Host, HostId, Stream,
     SupportedInputConfigs, SupportedOutputConfigs, ALL_HOSTS,
 };
-pub use samples_formats::{Sample, SampleFormat};
-use std::convert::TryInto;
+pub use resample::ResampleQuality;
+pub use samples_formats::{Endianness, I24Packed, Sample, SampleFormat, I24};
+use std::convert::{Infallible, TryInto};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 
+mod adapt;
+mod buffer;
+mod convert;
+mod dither;
 mod error;
+mod frames;
 mod host;
+mod layout;
+mod mix;
+mod negotiate;
+mod planar;
 pub mod platform;
+mod resample;
 mod samples_formats;
 pub mod traits;
 
@@ -176,6 +214,64 @@ pub type OutputDevices<I> = std::iter::Filter<I, fn(&<I as Iterator>::Item) -> b
 /// Number of channels.
 pub type ChannelCount = u16;
 
+/// A stable identifier of a device, as returned by `DeviceTrait::id`.
+///
+/// Unlike its name, the identifier of a device stays the same across reboots and replugs as far as
+/// the host allows, so it can be stored to find the device again with `HostTrait::device_by_id`.
+/// Store it in its string form, which is produced by `Display` and parsed by `FromStr`.
+///
+/// Identifiers are only meaningful to the host that produced them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeviceId(String);
+
+/// Describes a device to people choosing between devices, as returned by
+/// `DeviceTrait::description`.
+///
+/// Hosts fill in what they know about the device and leave the rest `None` or `Unknown`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceDescription {
+    /// A human-readable name, e.g. "HDA Intel PCH, ALC892 Analog".
+    pub name: String,
+    /// The manufacturer of the device or the driver serving it.
+    pub driver: Option<String>,
+    /// How the device is attached to the system.
+    pub interface: DeviceInterface,
+    /// The index of the sound card the device belongs to.
+    pub card: Option<u32>,
+    /// The index of the device on its sound card.
+    pub device: Option<u32>,
+    /// The index of the subdevice on its device.
+    pub subdevice: Option<u32>,
+    /// Whether the device is a hardware endpoint or a plugin.
+    pub kind: DeviceKind,
+}
+
+/// How a device is attached to the system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceInterface {
+    /// A USB audio device.
+    Usb,
+    /// An HDMI or DisplayPort output of a graphics card.
+    Hdmi,
+    /// A sound card on the PCI bus, such as onboard audio.
+    Pci,
+    /// A software device not tied to a single sound card, such as a sound server.
+    Virtual,
+    /// The host cannot tell how the device is attached.
+    Unknown,
+}
+
+/// Whether a device accesses the hardware directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// The device is a hardware endpoint, accessed without conversions.
+    Hardware,
+    /// The device is a plugin, which may convert, mix or route audio on its way to the hardware.
+    Plugin,
+    /// The host cannot tell whether the device accesses the hardware directly.
+    Unknown,
+}
+
 /// The number of samples processed per second for a single channel of audio.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SampleRate(pub u32);
@@ -190,10 +286,29 @@ pub type FrameCount = u32;
 /// large, leading to latency issues. If low latency is desired, Fixed(BufferSize)
 /// should be used in accordance with the SupportedBufferSize range produced by
 /// the SupportedStreamConfig API.  
+///
+/// Latency asks for a buffer holding the given duration of audio at the stream's sample rate.
+/// Hosts pick the nearest buffer size they support, so use `StreamTrait::latency` to find out
+/// what a stream actually got. `LatencyProfile` names a few common targets.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum BufferSize {
     Default,
     Fixed(FrameCount),
+    Latency(Duration),
+}
+
+/// Common latency targets, trading responsiveness against CPU load and robustness against
+/// underruns.
+///
+/// Convert a profile into a `BufferSize::Latency` with `BufferSize::from`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LatencyProfile {
+    /// 10 ms, for live monitoring and instruments.
+    LowLatency,
+    /// 100 ms, suitable for most playback and recording.
+    Balanced,
+    /// 500 ms, for background playback where latency does not matter.
+    PowerSaving,
 }
 
 /// The set of parameters used to describe how to open a stream.
@@ -204,6 +319,52 @@ pub struct StreamConfig {
     pub channels: ChannelCount,
     pub sample_rate: SampleRate,
     pub buffer_size: BufferSize,
+    /// The speaker positions to open the device with, if any.
+    ///
+    /// `None` leaves the device's channel map as is. Otherwise the layout must have `channels`
+    /// channels and be one of the `SupportedStreamConfigRange::channel_layouts` of the device.
+    /// Hosts that cannot set channel maps ignore the layout, so use `StreamTrait::channel_layout`
+    /// to find out the layout a stream was actually opened with.
+    pub channel_layout: Option<ChannelLayout>,
+    /// The device channels to exchange with the callback, in the order they appear in each frame.
+    ///
+    /// `None` exchanges all `channels` channels. Otherwise the device is still opened with
+    /// `channels` channels, but each frame passed to the callback only holds the listed ones and
+    /// output channels that are not listed are kept silent. The list must not be empty, and each
+    /// index must be below `channels` and appear only once, or building the stream fails with
+    /// `BuildStreamError::InvalidArgument`. Only the ALSA host supports selecting channels. Other
+    /// hosts and adapted streams fail with `BuildStreamError::StreamConfigNotSupported`.
+    pub channel_selection: Option<Vec<ChannelCount>>,
+    /// The number of frames between wakeups of the stream, for hosts that split the buffer into
+    /// periods.
+    ///
+    /// `None` lets the host pick a period size to suit `buffer_size`. Only the ALSA host applies
+    /// periods, and the supported values are given by `SupportedStreamConfigRange::periods`. Other
+    /// hosts and adapted streams fail with `BuildStreamError::StreamConfigNotSupported`.
+    pub period_size: Option<FrameCount>,
+    /// The number of periods the buffer is split into, for hosts that split the buffer into
+    /// periods.
+    ///
+    /// `None` lets the host pick a period count to suit `buffer_size`. Like `period_size`, this is
+    /// only supported by the ALSA host.
+    pub period_count: Option<u32>,
+    /// Whether to fail with `BuildStreamError::StreamConfigNotSupported` rather than run with a
+    /// channel count, sample rate, fixed buffer size or period setting near the requested one.
+    ///
+    /// Latency targets and the default buffer size are never strict. The ALSA host checks the
+    /// values the device settled on, while other hosts apply the configuration as given or reject
+    /// it. Use `StreamTrait::effective_config` to find out what a stream is running with.
+    pub strict: bool,
+}
+
+/// The configuration a stream is running with, as returned by `StreamTrait::effective_config`.
+///
+/// The buffer size is reported as `BufferSize::Fixed` and the periods as counted in frames,
+/// regardless of how they were requested.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EffectiveConfig {
+    pub config: StreamConfig,
+    pub sample_format: SampleFormat,
 }
 
 /// Describes the minimum and maximum supported buffer size for the device
@@ -218,19 +379,53 @@ pub enum SupportedBufferSize {
     Unknown,
 }
 
+/// Describes the period sizes and counts supported by a device whose buffer is split into
+/// periods, waking the stream up once per period.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SupportedPeriods {
+    Range {
+        /// Smallest period size, in frames.
+        min_size: FrameCount,
+        /// Largest period size, in frames.
+        max_size: FrameCount,
+        /// Fewest periods the buffer can be split into.
+        min_count: u32,
+        /// Most periods the buffer can be split into.
+        max_count: u32,
+    },
+    /// The host does not use periods or does not report their limits.
+    Unknown,
+}
+
+/// Describes the sample rates supported by a `SupportedStreamConfigRange`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SupportedSampleRates {
+    /// Any sample rate between `min` and `max` inclusive.
+    Range { min: SampleRate, max: SampleRate },
+    /// Only the listed sample rates, in ascending order. The list is never empty.
+    Discrete(Vec<SampleRate>),
+}
+
 /// Describes a range of supported stream configurations, retrieved via the
 /// `Device::supported_input/output_configs` method.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SupportedStreamConfigRange {
-    pub(crate) channels: ChannelCount,
-    /// Minimum value for the samples rate of the supported formats.
-    pub(crate) min_sample_rate: SampleRate,
-    /// Maximum value for the samples rate of the supported formats.
-    pub(crate) max_sample_rate: SampleRate,
+    /// Minimum number of channels of the supported formats.
+    pub(crate) min_channels: ChannelCount,
+    /// Maximum number of channels of the supported formats.
+    pub(crate) max_channels: ChannelCount,
+    /// Sample rates of the supported formats.
+    pub(crate) sample_rates: SupportedSampleRates,
     /// Buffersize ranges supported by the device
     pub(crate) buffer_size: SupportedBufferSize,
+    /// Period sizes and counts supported by the device.
+    pub(crate) periods: SupportedPeriods,
     /// Type of data expected by the device.
     pub(crate) sample_format: SampleFormat,
+    /// Byte order of the samples exchanged with the device.
+    pub(crate) endianness: Endianness,
+    /// Channel layouts the device can be opened with.
+    pub(crate) channel_layouts: Vec<ChannelLayout>,
 }
 
 /// Describes a single supported stream configuration, retrieved via either a
@@ -240,7 +435,10 @@ pub struct SupportedStreamConfig {
     channels: ChannelCount,
     sample_rate: SampleRate,
     buffer_size: SupportedBufferSize,
+    periods: SupportedPeriods,
     sample_format: SampleFormat,
+    endianness: Endianness,
+    channel_layout: Option<ChannelLayout>,
 }
 
 /// A buffer of dynamically typed audio data, passed to raw stream callbacks.
@@ -254,6 +452,20 @@ pub struct Data {
     sample_format: SampleFormat,
 }
 
+/// A buffer of dynamically typed, non-interleaved audio data, passed to planar stream callbacks.
+///
+/// Unlike `Data`, which holds the samples of all channels interleaved frame by frame, a
+/// `PlanarData` holds one contiguous slice of samples per channel. Raw planar input stream
+/// callbacks receive `&PlanarData`, while raw planar output stream callbacks expect `&mut
+/// PlanarData`.
+#[derive(Debug)]
+pub struct PlanarData {
+    planes: *const *mut (),
+    channels: usize,
+    frames: usize,
+    sample_format: SampleFormat,
+}
+
 /// A monotonic time instance associated with a stream, retrieved from either:
 ///
 /// 1. A timestamp provided to the stream's underlying audio data callback or
@@ -312,6 +524,113 @@ pub struct OutputCallbackInfo {
     timestamp: OutputStreamTimestamp,
 }
 
+impl DeviceId {
+    /// The identifier with the given string form.
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        DeviceId(id.into())
+    }
+
+    /// The string form of the identifier.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(DeviceId::new(s))
+    }
+}
+
+impl DeviceDescription {
+    /// A description holding nothing but the given name.
+    pub fn from_name<S: Into<String>>(name: S) -> Self {
+        DeviceDescription {
+            name: name.into(),
+            driver: None,
+            interface: DeviceInterface::Unknown,
+            card: None,
+            device: None,
+            subdevice: None,
+            kind: DeviceKind::Unknown,
+        }
+    }
+}
+
+impl BufferSize {
+    /// The buffer size in frames at the given sample rate, or `None` for `BufferSize::Default`.
+    ///
+    /// Latencies are rounded to the nearest frame.
+    pub fn frames(&self, sample_rate: SampleRate) -> Option<FrameCount> {
+        match *self {
+            BufferSize::Default => None,
+            BufferSize::Fixed(frames) => Some(frames),
+            BufferSize::Latency(latency) => Some(latency_frames(latency, sample_rate)),
+        }
+    }
+}
+
+impl From<LatencyProfile> for BufferSize {
+    fn from(profile: LatencyProfile) -> Self {
+        BufferSize::Latency(profile.latency())
+    }
+}
+
+impl LatencyProfile {
+    /// The buffer latency the profile aims for.
+    pub fn latency(self) -> Duration {
+        match self {
+            LatencyProfile::LowLatency => Duration::from_millis(10),
+            LatencyProfile::Balanced => Duration::from_millis(100),
+            LatencyProfile::PowerSaving => Duration::from_millis(500),
+        }
+    }
+}
+
+// The number of frames lasting the given duration at the given sample rate, to the nearest frame.
+pub(crate) fn latency_frames(latency: Duration, sample_rate: SampleRate) -> FrameCount {
+    let frames = (latency.as_secs_f64() * sample_rate.0 as f64).round();
+    frames.min(FrameCount::MAX as f64) as FrameCount
+}
+
+impl StreamConfig {
+    /// The number of channels in each frame passed to the callback.
+    pub fn callback_channels(&self) -> ChannelCount {
+        match self.channel_selection {
+            Some(ref selection) => selection.len() as ChannelCount,
+            None => self.channels,
+        }
+    }
+
+    // Whether `channel_selection` is not empty and each of its indices is below `channels` and
+    // appears only once.
+    pub(crate) fn channel_selection_is_valid(&self) -> bool {
+        let selection = match self.channel_selection {
+            Some(ref selection) if selection.is_empty() => return false,
+            Some(ref selection) => selection,
+            None => return true,
+        };
+        let mut selected = vec![false; self.channels as usize];
+        selection
+            .iter()
+            .all(|&channel| match selected.get_mut(channel as usize) {
+                Some(selected) if !*selected => {
+                    *selected = true;
+                    true
+                }
+                _ => false,
+            })
+    }
+}
+
 impl SupportedStreamConfig {
     pub fn channels(&self) -> ChannelCount {
         self.channels
@@ -325,15 +644,38 @@ impl SupportedStreamConfig {
         &self.buffer_size
     }
 
+    /// The period sizes and counts supported by the device.
+    pub fn periods(&self) -> &SupportedPeriods {
+        &self.periods
+    }
+
     pub fn sample_format(&self) -> SampleFormat {
         self.sample_format
     }
 
+    /// The byte order in which samples are exchanged with the device.
+    ///
+    /// Stream callbacks always receive native-endian samples. If this differs from
+    /// `Endianness::native()`, the host byte-swaps samples between the device and the callback.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The speaker positions of the channels, if known.
+    pub fn channel_layout(&self) -> Option<&ChannelLayout> {
+        self.channel_layout.as_ref()
+    }
+
     pub fn config(&self) -> StreamConfig {
         StreamConfig {
             channels: self.channels,
             sample_rate: self.sample_rate,
             buffer_size: BufferSize::Default,
+            channel_layout: self.channel_layout.clone(),
+            channel_selection: None,
+            period_size: None,
+            period_count: None,
+            strict: false,
         }
     }
 }
@@ -382,17 +724,17 @@ impl StreamInstant {
     fn from_nanos(nanos: i64) -> Self {
         let secs = nanos / 1_000_000_000;
         let subsec_nanos = nanos - secs * 1_000_000_000;
-        Self::new(secs as i64, subsec_nanos as u32)
+        Self::new(secs, subsec_nanos as u32)
     }
 
     #[allow(dead_code)]
     fn from_nanos_i128(nanos: i128) -> Option<Self> {
         let secs = nanos / 1_000_000_000;
-        if secs > std::i64::MAX as i128 || secs < std::i64::MIN as i128 {
+        if secs > i64::MAX as i128 || secs < i64::MIN as i128 {
             None
         } else {
             let subsec_nanos = nanos - secs * 1_000_000_000;
-            debug_assert!(subsec_nanos < std::u32::MAX as i128);
+            debug_assert!(subsec_nanos < u32::MAX as i128);
             Some(Self::new(secs as i64, subsec_nanos as u32))
         }
     }
@@ -458,9 +800,15 @@ impl Data {
         self.len
     }
 
+    /// Whether or not the buffer contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     /// The raw slice of memory representing the underlying audio data as a slice of bytes.
     ///
-    /// It is up to the user to interpret the slice of memory based on `Data::sample_format`.
+    /// It is up to the user to interpret the slice of memory based on `Data::sample_format`. This
+    /// is the only way to access companded `MuLaw` and `ALaw` data without decoding it.
     pub fn bytes(&self) -> &[u8] {
         let len = self.len * self.sample_format.sample_size();
         // The safety of this block relies on correct construction of the `Data` instance. See
@@ -478,6 +826,106 @@ impl Data {
         unsafe { std::slice::from_raw_parts_mut(self.data as *mut u8, len) }
     }
 
+    /// Decodes `MuLaw` or `ALaw` companded data into the given slice of linear samples.
+    ///
+    /// Returns `None` if the sample format of the data is not companded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` does not have the same length as the buffer.
+    pub fn decode_companded<T>(&self, output: &mut [T]) -> Option<()>
+    where
+        T: Sample,
+    {
+        let decode: fn(u8) -> i16 = match self.sample_format {
+            SampleFormat::MuLaw => samples_formats::mu_law_to_linear,
+            SampleFormat::ALaw => samples_formats::a_law_to_linear,
+            _ => return None,
+        };
+        assert_eq!(
+            output.len(),
+            self.len,
+            "output length must match the buffer length"
+        );
+        for (sample, &byte) in output.iter_mut().zip(self.bytes()) {
+            *sample = Sample::from(&decode(byte));
+        }
+        Some(())
+    }
+
+    /// Encodes the given linear samples into `MuLaw` or `ALaw` companded data.
+    ///
+    /// Returns `None` if the sample format of the data is not companded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` does not have the same length as the buffer.
+    pub fn encode_companded<T>(&mut self, input: &[T]) -> Option<()>
+    where
+        T: Sample,
+    {
+        let encode: fn(i16) -> u8 = match self.sample_format {
+            SampleFormat::MuLaw => samples_formats::linear_to_mu_law,
+            SampleFormat::ALaw => samples_formats::linear_to_a_law,
+            _ => return None,
+        };
+        assert_eq!(
+            input.len(),
+            self.len,
+            "input length must match the buffer length"
+        );
+        for (byte, sample) in self.bytes_mut().iter_mut().zip(input) {
+            *byte = encode(sample.to_i16());
+        }
+        Some(())
+    }
+
+    /// Access the data as a sequence of frames of `channels` samples of type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, or if the
+    /// length of the buffer is not a multiple of `channels`.
+    pub fn frames<T>(&self, channels: ChannelCount) -> Option<Frames<'_, T>>
+    where
+        T: Sample,
+    {
+        self.as_slice()
+            .and_then(|samples| Frames::new(samples, channels))
+    }
+
+    /// Access the data as a sequence of frames of `channels` samples of type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, or if the
+    /// length of the buffer is not a multiple of `channels`.
+    pub fn frames_mut<T>(&mut self, channels: ChannelCount) -> Option<FramesMut<'_, T>>
+    where
+        T: Sample,
+    {
+        self.as_slice_mut()
+            .and_then(|samples| FramesMut::new(samples, channels))
+    }
+
+    /// Access the data as a slice of fixed-size frames, e.g. `[f32; 2]` for stereo.
+    ///
+    /// Returns `None` if the sample type of the frame does not match the expected sample format,
+    /// or if the length of the buffer is not a multiple of `F::CHANNELS`.
+    pub fn as_frames<F>(&self) -> Option<&[F]>
+    where
+        F: Frame,
+    {
+        self.as_slice().and_then(frames::as_frames)
+    }
+
+    /// Access the data as a slice of fixed-size frames, e.g. `[f32; 2]` for stereo.
+    ///
+    /// Returns `None` if the sample type of the frame does not match the expected sample format,
+    /// or if the length of the buffer is not a multiple of `F::CHANNELS`.
+    pub fn as_frames_mut<F>(&mut self) -> Option<&mut [F]>
+    where
+        F: Frame,
+    {
+        self.as_slice_mut().and_then(frames::as_frames_mut)
+    }
+
     /// Access the data as a slice of sample type `T`.
     ///
     /// Returns `None` if the sample type does not match the expected sample format.
@@ -516,49 +964,308 @@ impl Data {
     }
 }
 
+impl PlanarData {
+    // Internal constructor for host implementations to use.
+    //
+    // The following requirements must be met in order for the safety of `PlanarData`'s public
+    // API.
+    //
+    // - The `planes` pointer must point to an array of `channels` pointers, each pointing to the
+    //   first sample of a distinct, non-overlapping channel buffer.
+    // - Each channel buffer must contain `frames` samples in the format specified via the
+    //   `sample_format` argument.
+    // - The `sample_format` must correctly represent the underlying sample data delivered/expected
+    //   by the stream.
+    pub(crate) unsafe fn from_parts(
+        planes: *const *mut (),
+        channels: usize,
+        frames: usize,
+        sample_format: SampleFormat,
+    ) -> Self {
+        PlanarData {
+            planes,
+            channels,
+            frames,
+            sample_format,
+        }
+    }
+
+    /// The sample format of the internal audio data.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of channels, i.e. the number of slices in the buffer.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The length of each channel of the buffer in samples.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Whether or not the buffer contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.channels == 0 || self.frames == 0
+    }
+
+    /// The raw slice of memory representing the given channel as a slice of bytes.
+    ///
+    /// It is up to the user to interpret the slice of memory based on `PlanarData::sample_format`.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel_bytes(&self, channel: usize) -> &[u8] {
+        let len = self.frames * self.sample_format.sample_size();
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { std::slice::from_raw_parts(self.plane(channel) as *const u8, len) }
+    }
+
+    /// The raw slice of memory representing the given channel as a slice of bytes.
+    ///
+    /// It is up to the user to interpret the slice of memory based on `PlanarData::sample_format`.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel_bytes_mut(&mut self, channel: usize) -> &mut [u8] {
+        let len = self.frames * self.sample_format.sample_size();
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { std::slice::from_raw_parts_mut(self.plane(channel) as *mut u8, len) }
+    }
+
+    /// Access the given channel as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel<T>(&self, channel: usize) -> Option<&[T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT == self.sample_format {
+            let data = self.plane(channel) as *const T;
+            // The safety of this block relies on correct construction of the `PlanarData`
+            // instance. See the unsafe `from_parts` constructor for these requirements.
+            unsafe { Some(std::slice::from_raw_parts(data, self.frames)) }
+        } else {
+            None
+        }
+    }
+
+    /// Access the given channel as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format.
+    ///
+    /// **panic!**s if `channel` is out of range.
+    pub fn channel_mut<T>(&mut self, channel: usize) -> Option<&mut [T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT == self.sample_format {
+            let data = self.plane(channel) as *mut T;
+            // The safety of this block relies on correct construction of the `PlanarData`
+            // instance. See the unsafe `from_parts` constructor for these requirements.
+            unsafe { Some(std::slice::from_raw_parts_mut(data, self.frames)) }
+        } else {
+            None
+        }
+    }
+
+    fn plane(&self, channel: usize) -> *mut () {
+        assert!(
+            channel < self.channels,
+            "channel {} out of range for {} channels",
+            channel,
+            self.channels
+        );
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { *self.planes.add(channel) }
+    }
+}
+
+impl SupportedSampleRates {
+    /// The lowest supported sample rate.
+    pub fn min(&self) -> SampleRate {
+        match *self {
+            SupportedSampleRates::Range { min, .. } => min,
+            SupportedSampleRates::Discrete(ref rates) => rates[0],
+        }
+    }
+
+    /// The highest supported sample rate.
+    pub fn max(&self) -> SampleRate {
+        match *self {
+            SupportedSampleRates::Range { max, .. } => max,
+            SupportedSampleRates::Discrete(ref rates) => rates[rates.len() - 1],
+        }
+    }
+
+    /// Whether the given sample rate is supported.
+    pub fn contains(&self, sample_rate: SampleRate) -> bool {
+        match *self {
+            SupportedSampleRates::Range { min, max } => min <= sample_rate && sample_rate <= max,
+            SupportedSampleRates::Discrete(ref rates) => rates.contains(&sample_rate),
+        }
+    }
+
+    /// The supported sample rate closest to the given one.
+    pub fn closest(&self, sample_rate: SampleRate) -> SampleRate {
+        match *self {
+            SupportedSampleRates::Range { min, max } => sample_rate.max(min).min(max),
+            SupportedSampleRates::Discrete(ref rates) => *rates
+                .iter()
+                .min_by_key(|rate| (rate.0 as i64 - sample_rate.0 as i64).abs())
+                .expect("discrete sample rates are never empty"),
+        }
+    }
+}
+
 impl SupportedStreamConfigRange {
+    /// The number of channels used by configurations retrieved from this range.
+    ///
+    /// For a range of a single channel count this is that count. A range spanning several counts
+    /// uses stereo if supported, then mono, and otherwise its largest count. Narrow the range with
+    /// `with_channels` to use another count.
     pub fn channels(&self) -> ChannelCount {
-        self.channels
+        self.default_channels()
+    }
+
+    /// The smallest number of channels supported.
+    pub fn min_channels(&self) -> ChannelCount {
+        self.min_channels
+    }
+
+    /// The largest number of channels supported.
+    pub fn max_channels(&self) -> ChannelCount {
+        self.max_channels
+    }
+
+    /// Whether the given number of channels is supported.
+    pub fn supports_channels(&self, channels: ChannelCount) -> bool {
+        self.min_channels <= channels && channels <= self.max_channels
+    }
+
+    /// The supported sample rates.
+    pub fn sample_rates(&self) -> &SupportedSampleRates {
+        &self.sample_rates
     }
 
     pub fn min_sample_rate(&self) -> SampleRate {
-        self.min_sample_rate
+        self.sample_rates.min()
     }
 
     pub fn max_sample_rate(&self) -> SampleRate {
-        self.max_sample_rate
+        self.sample_rates.max()
+    }
+
+    /// Whether the given sample rate is supported.
+    pub fn supports_sample_rate(&self, sample_rate: SampleRate) -> bool {
+        self.sample_rates.contains(sample_rate)
     }
 
     pub fn buffer_size(&self) -> &SupportedBufferSize {
         &self.buffer_size
     }
 
+    /// The period sizes and counts supported by the device.
+    pub fn periods(&self) -> &SupportedPeriods {
+        &self.periods
+    }
+
     pub fn sample_format(&self) -> SampleFormat {
         self.sample_format
     }
 
+    /// The byte order in which samples are exchanged with the device.
+    ///
+    /// Stream callbacks always receive native-endian samples. If this differs from
+    /// `Endianness::native()`, the host byte-swaps samples between the device and the callback.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The channel layouts the device can be opened with, if known.
+    ///
+    /// Configurations retrieved from this range use the first layout with a matching channel count.
+    pub fn channel_layouts(&self) -> &[ChannelLayout] {
+        &self.channel_layouts
+    }
+
+    /// Narrow this range down to the given number of channels.
+    ///
+    /// Returns `None` if the given number of channels is not supported.
+    pub fn with_channels(mut self, channels: ChannelCount) -> Option<Self> {
+        if !self.supports_channels(channels) {
+            return None;
+        }
+        self.min_channels = channels;
+        self.max_channels = channels;
+        self.channel_layouts
+            .retain(|layout| layout.channels() == channels);
+        Some(self)
+    }
+
+    // The first layout matching the channel count of configurations retrieved from this range.
+    fn default_layout(&self) -> Option<ChannelLayout> {
+        let channels = self.default_channels();
+        self.channel_layouts
+            .iter()
+            .find(|layout| layout.channels() == channels)
+            .cloned()
+    }
+
+    // The channel count favoured by `cmp_default_heuristics`.
+    pub(crate) fn default_channels(&self) -> ChannelCount {
+        if self.supports_channels(2) {
+            2
+        } else if self.supports_channels(1) {
+            1
+        } else {
+            self.max_channels
+        }
+    }
+
     /// Retrieve a `SupportedStreamConfig` with the given sample rate and buffer size.
     ///
     /// **panic!**s if the given `sample_rate` is outside the range specified within this
     /// `SupportedStreamConfigRange` instance.
     pub fn with_sample_rate(self, sample_rate: SampleRate) -> SupportedStreamConfig {
-        assert!(self.min_sample_rate <= sample_rate && sample_rate <= self.max_sample_rate);
-        SupportedStreamConfig {
-            channels: self.channels,
+        self.try_with_sample_rate(sample_rate)
+            .expect("sample rate outside of the supported range")
+    }
+
+    /// Retrieve a `SupportedStreamConfig` with the given sample rate and buffer size.
+    ///
+    /// Returns `None` if the given `sample_rate` is outside the range specified within this
+    /// `SupportedStreamConfigRange` instance.
+    pub fn try_with_sample_rate(self, sample_rate: SampleRate) -> Option<SupportedStreamConfig> {
+        if !self.supports_sample_rate(sample_rate) {
+            return None;
+        }
+        Some(SupportedStreamConfig {
+            channels: self.default_channels(),
             sample_rate,
             sample_format: self.sample_format,
+            endianness: self.endianness,
+            channel_layout: self.default_layout(),
             buffer_size: self.buffer_size,
-        }
+            periods: self.periods,
+        })
     }
 
     /// Turns this `SupportedStreamConfigRange` into a `SupportedStreamConfig` corresponding to the maximum samples rate.
     #[inline]
     pub fn with_max_sample_rate(self) -> SupportedStreamConfig {
         SupportedStreamConfig {
-            channels: self.channels,
-            sample_rate: self.max_sample_rate,
+            channels: self.default_channels(),
+            sample_rate: self.sample_rates.max(),
             sample_format: self.sample_format,
+            endianness: self.endianness,
+            channel_layout: self.default_layout(),
             buffer_size: self.buffer_size,
+            periods: self.periods,
         }
     }
 
@@ -573,8 +1280,8 @@ impl SupportedStreamConfigRange {
     ///
     /// **Channels**:
     ///
-    /// - Stereo
-    /// - Mono
+    /// - Stereo supported
+    /// - Mono supported
     /// - Max available channels
     ///
     /// **Sample format**:
@@ -582,25 +1289,29 @@ impl SupportedStreamConfigRange {
     /// - i16
     /// - u16
     ///
+    /// **Byte order**:
+    ///
+    /// - Native endianness
+    ///
     /// **Sample rate**:
     ///
-    /// - 44100 (cd quality)
+    /// - 44100 (cd quality) supported
     /// - Max sample rate
     pub fn cmp_default_heuristics(&self, other: &Self) -> std::cmp::Ordering {
         use std::cmp::Ordering::Equal;
         use SampleFormat::{F32, I16, U16};
 
-        let cmp_stereo = (self.channels == 2).cmp(&(other.channels == 2));
+        let cmp_stereo = self.supports_channels(2).cmp(&other.supports_channels(2));
         if cmp_stereo != Equal {
             return cmp_stereo;
         }
 
-        let cmp_mono = (self.channels == 1).cmp(&(other.channels == 1));
+        let cmp_mono = self.supports_channels(1).cmp(&other.supports_channels(1));
         if cmp_mono != Equal {
             return cmp_mono;
         }
 
-        let cmp_channels = self.channels.cmp(&other.channels);
+        let cmp_channels = self.max_channels.cmp(&other.max_channels);
         if cmp_channels != Equal {
             return cmp_channels;
         }
@@ -620,57 +1331,66 @@ impl SupportedStreamConfigRange {
             return cmp_u16;
         }
 
+        let cmp_native = self
+            .endianness
+            .is_native()
+            .cmp(&other.endianness.is_native());
+        if cmp_native != Equal {
+            return cmp_native;
+        }
+
         const HZ_44100: SampleRate = SampleRate(44_100);
-        let r44100_in_self = self.min_sample_rate <= HZ_44100 && HZ_44100 <= self.max_sample_rate;
-        let r44100_in_other =
-            other.min_sample_rate <= HZ_44100 && HZ_44100 <= other.max_sample_rate;
-        let cmp_r44100 = r44100_in_self.cmp(&r44100_in_other);
+        let cmp_r44100 = self
+            .supports_sample_rate(HZ_44100)
+            .cmp(&other.supports_sample_rate(HZ_44100));
         if cmp_r44100 != Equal {
             return cmp_r44100;
         }
 
-        self.max_sample_rate.cmp(&other.max_sample_rate)
+        self.max_sample_rate().cmp(&other.max_sample_rate())
+    }
+}
+
+// A range of `channels` channels in `sample_format` at 8 to 48 kHz, shared by the tests of the
+// crate.
+#[cfg(test)]
+pub(crate) fn test_config_range(
+    channels: ChannelCount,
+    sample_format: SampleFormat,
+) -> SupportedStreamConfigRange {
+    SupportedStreamConfigRange {
+        min_channels: channels,
+        max_channels: channels,
+        sample_rates: SupportedSampleRates::Range {
+            min: SampleRate(8_000),
+            max: SampleRate(48_000),
+        },
+        buffer_size: SupportedBufferSize::Range { min: 64, max: 4096 },
+        sample_format,
+        endianness: Endianness::native(),
+        channel_layouts: Vec::new(),
+        periods: SupportedPeriods::Unknown,
     }
 }
 
 #[test]
 fn test_cmp_default_heuristics() {
-    let mut formats = vec![
-        SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
-            channels: 2,
-            min_sample_rate: SampleRate(1),
-            max_sample_rate: SampleRate(96000),
-            sample_format: SampleFormat::F32,
-        },
-        SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
-            channels: 1,
-            min_sample_rate: SampleRate(1),
-            max_sample_rate: SampleRate(96000),
-            sample_format: SampleFormat::F32,
-        },
-        SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
-            channels: 2,
-            min_sample_rate: SampleRate(1),
-            max_sample_rate: SampleRate(96000),
-            sample_format: SampleFormat::I16,
-        },
-        SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
-            channels: 2,
-            min_sample_rate: SampleRate(1),
-            max_sample_rate: SampleRate(96000),
-            sample_format: SampleFormat::U16,
-        },
-        SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
-            channels: 2,
-            min_sample_rate: SampleRate(1),
-            max_sample_rate: SampleRate(22050),
-            sample_format: SampleFormat::F32,
+    let range = |channels, max_rate, sample_format, endianness| SupportedStreamConfigRange {
+        sample_rates: SupportedSampleRates::Range {
+            min: SampleRate(1),
+            max: SampleRate(max_rate),
         },
+        endianness,
+        ..test_config_range(channels, sample_format)
+    };
+    let native = Endianness::native();
+    let mut formats = [
+        range(2, 96000, SampleFormat::F32, native),
+        range(1, 96000, SampleFormat::F32, native),
+        range(2, 96000, SampleFormat::I16, native),
+        range(2, 96000, SampleFormat::U16, native),
+        range(2, 22050, SampleFormat::F32, native),
+        range(2, 96000, SampleFormat::F32, native.swapped()),
     ];
 
     formats.sort_by(|a, b| a.cmp_default_heuristics(b));
@@ -693,13 +1413,73 @@ fn test_cmp_default_heuristics() {
 
     assert_eq!(formats[3].sample_format(), SampleFormat::F32);
     assert_eq!(formats[3].min_sample_rate(), SampleRate(1));
-    assert_eq!(formats[3].max_sample_rate(), SampleRate(22050));
+    assert_eq!(formats[3].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[3].channels(), 2);
+    assert_eq!(formats[3].endianness(), Endianness::native().swapped());
 
     assert_eq!(formats[4].sample_format(), SampleFormat::F32);
     assert_eq!(formats[4].min_sample_rate(), SampleRate(1));
-    assert_eq!(formats[4].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[4].max_sample_rate(), SampleRate(22050));
     assert_eq!(formats[4].channels(), 2);
+
+    assert_eq!(formats[5].sample_format(), SampleFormat::F32);
+    assert_eq!(formats[5].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[5].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[5].channels(), 2);
+    assert_eq!(formats[5].endianness(), Endianness::native());
+}
+
+#[test]
+fn test_channel_ranges_and_discrete_rates() {
+    let range = SupportedStreamConfigRange {
+        min_channels: 1,
+        max_channels: 8,
+        sample_rates: SupportedSampleRates::Discrete(vec![SampleRate(44100), SampleRate(96000)]),
+        ..test_config_range(1, SampleFormat::I16)
+    };
+    assert!(range.supports_channels(6));
+    assert!(!range.supports_channels(9));
+    assert!(range.supports_sample_rate(SampleRate(96000)));
+    assert!(!range.supports_sample_rate(SampleRate(48000)));
+    assert_eq!(
+        range.sample_rates().closest(SampleRate(80000)),
+        SampleRate(96000)
+    );
+    assert_eq!(range.min_sample_rate(), SampleRate(44100));
+    assert!(range
+        .clone()
+        .try_with_sample_rate(SampleRate(48000))
+        .is_none());
+    assert!(range.clone().with_channels(9).is_none());
+
+    let config = range
+        .clone()
+        .with_channels(6)
+        .unwrap()
+        .with_max_sample_rate();
+    assert_eq!(config.channels(), 6);
+    assert_eq!(config.sample_rate(), SampleRate(96000));
+
+    // Spanning ranges default to stereo rather than their largest channel count.
+    assert_eq!(range.channels(), 2);
+    assert_eq!(range.clone().with_max_sample_rate().channels(), 2);
+    let surround = SupportedStreamConfigRange {
+        min_channels: 4,
+        max_channels: 8,
+        ..range
+    };
+    assert_eq!(surround.channels(), 8);
+}
+
+#[test]
+fn test_buffer_size_frames() {
+    let rate = SampleRate(48000);
+    assert_eq!(BufferSize::Default.frames(rate), None);
+    assert_eq!(BufferSize::Fixed(256).frames(rate), Some(256));
+    let latency = BufferSize::Latency(Duration::from_micros(5_010));
+    assert_eq!(latency.frames(rate), Some(240));
+    let balanced = BufferSize::from(LatencyProfile::Balanced);
+    assert_eq!(balanced.frames(rate), Some(4800));
 }
 
 impl From<SupportedStreamConfig> for StreamConfig {
@@ -733,8 +1513,8 @@ const COMMON_SAMPLE_RATES: &'static [SampleRate] = &[
 fn test_stream_instant() {
     let a = StreamInstant::new(2, 0);
     let b = StreamInstant::new(-2, 0);
-    let min = StreamInstant::new(std::i64::MIN, 0);
-    let max = StreamInstant::new(std::i64::MAX, 0);
+    let min = StreamInstant::new(i64::MIN, 0);
+    let max = StreamInstant::new(i64::MAX, 0);
     assert_eq!(
         a.sub(Duration::from_secs(1)),
         Some(StreamInstant::new(1, 0))
@@ -762,3 +1542,27 @@ fn test_stream_instant() {
     );
     assert_eq!(max.add(Duration::from_secs(1)), None);
 }
+
+#[test]
+fn test_channel_selection_is_validated() {
+    let mut config = StreamConfig {
+        channels: 4,
+        sample_rate: SampleRate(48_000),
+        buffer_size: BufferSize::Default,
+        channel_layout: None,
+        channel_selection: None,
+        period_size: None,
+        period_count: None,
+        strict: false,
+    };
+    assert!(config.channel_selection_is_valid());
+    config.channel_selection = Some(vec![3, 0]);
+    assert!(config.channel_selection_is_valid());
+    assert_eq!(config.callback_channels(), 2);
+    config.channel_selection = Some(vec![]);
+    assert!(!config.channel_selection_is_valid());
+    config.channel_selection = Some(vec![4]);
+    assert!(!config.channel_selection_is_valid());
+    config.channel_selection = Some(vec![1, 1]);
+    assert!(!config.channel_selection_is_valid());
+}
@@ -0,0 +1,255 @@
+//! Channel up- and downmixing for adapted streams.
+
+use crate::{ChannelCount, ChannelLayout, ChannelPosition};
+use std::f32::consts::FRAC_1_SQRT_2;
+
+/// A matrix of gains mapping each frame of one channel count onto another.
+///
+/// Output channel `o` of a frame is the sum of every input channel `i` multiplied by
+/// `gain(o, i)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelMatrix {
+    inputs: ChannelCount,
+    outputs: ChannelCount,
+    // One row of `inputs` gains per output channel.
+    gains: Vec<f32>,
+}
+
+impl ChannelMatrix {
+    /// Creates a matrix from its gains, given as one row of `inputs` gains per output channel.
+    ///
+    /// Returns `None` if `gains` does not hold exactly `inputs * outputs` gains.
+    pub fn new(inputs: ChannelCount, outputs: ChannelCount, gains: Vec<f32>) -> Option<Self> {
+        if gains.len() != inputs as usize * outputs as usize {
+            return None;
+        }
+        Some(ChannelMatrix {
+            inputs,
+            outputs,
+            gains,
+        })
+    }
+
+    /// The standard matrix for mixing between the given channel counts.
+    ///
+    /// Channel counts with a `ChannelLayout::standard` layout are mixed as by `from_layouts`. For
+    /// other channel counts each input channel is routed to the output channel with the same
+    /// index, if there is one.
+    pub fn standard(inputs: ChannelCount, outputs: ChannelCount) -> Self {
+        match (
+            ChannelLayout::standard(inputs),
+            ChannelLayout::standard(outputs),
+        ) {
+            (Some(input), Some(output)) => Self::from_layouts(&input, &output),
+            _ => {
+                let mut gains = vec![0.0; inputs as usize * outputs as usize];
+                for channel in 0..inputs.min(outputs) as usize {
+                    gains[channel * inputs as usize + channel] = 1.0;
+                }
+                ChannelMatrix {
+                    inputs,
+                    outputs,
+                    gains,
+                }
+            }
+        }
+    }
+
+    /// The matrix for mixing between the given speaker layouts.
+    ///
+    /// Speakers missing from the output are folded into the nearest remaining speakers following
+    /// ITU-R BS.775, e.g. the center channel into left and right at -3 dB, and LFE channels are
+    /// dropped. Speakers missing from the input are left silent, except that a mono or center input
+    /// is spread across left and right. Downmixes are normalised so that no output channel can
+    /// exceed full scale.
+    pub fn from_layouts(input: &ChannelLayout, output: &ChannelLayout) -> Self {
+        let inputs = input.channels();
+        let outputs = output.channels();
+        let mut gains = vec![0.0; inputs as usize * outputs as usize];
+        for (i, &position) in input.positions().iter().enumerate() {
+            for (o, gain) in route(position, output.positions(), 0) {
+                gains[o * inputs as usize + i] += gain;
+            }
+        }
+        let loudest = gains
+            .chunks(inputs.max(1) as usize)
+            .map(|row| row.iter().sum::<f32>())
+            .fold(0.0, f32::max);
+        if loudest > 1.0 {
+            for gain in gains.iter_mut() {
+                *gain /= loudest;
+            }
+        }
+        ChannelMatrix {
+            inputs,
+            outputs,
+            gains,
+        }
+    }
+
+    /// The number of channels of the frames the matrix is applied to.
+    pub fn inputs(&self) -> ChannelCount {
+        self.inputs
+    }
+
+    /// The number of channels of the frames the matrix produces.
+    pub fn outputs(&self) -> ChannelCount {
+        self.outputs
+    }
+
+    /// The gain with which the given input channel contributes to the given output channel.
+    ///
+    /// **panic!**s if either channel is out of range.
+    pub fn gain(&self, output: ChannelCount, input: ChannelCount) -> f32 {
+        assert!(output < self.outputs && input < self.inputs);
+        self.gains[output as usize * self.inputs as usize + input as usize]
+    }
+
+    /// Mixes the interleaved frames of `input` into `output`, which must hold the same number of
+    /// frames.
+    pub(crate) fn apply(&self, input: &[f32], output: &mut [f32]) {
+        let inputs = self.inputs as usize;
+        let outputs = self.outputs as usize;
+        debug_assert_eq!(input.len() / inputs, output.len() / outputs);
+        for (src, dst) in input.chunks(inputs).zip(output.chunks_mut(outputs)) {
+            for (sample, row) in dst.iter_mut().zip(self.gains.chunks(inputs)) {
+                *sample = row.iter().zip(src).map(|(gain, s)| gain * s).sum();
+            }
+        }
+    }
+}
+
+// The output channels the given speaker is mixed into, along with their gains.
+fn route(position: ChannelPosition, outputs: &[ChannelPosition], depth: u8) -> Vec<(usize, f32)> {
+    use crate::ChannelPosition::*;
+    if let Some(index) = outputs.iter().position(|&p| p == position) {
+        return vec![(index, 1.0)];
+    }
+    // Fallbacks lead back to the front speakers within a few steps, unless the output has none.
+    if depth > 3 {
+        return Vec::new();
+    }
+    let has = |p: ChannelPosition| outputs.contains(&p);
+    let fallbacks: &[(ChannelPosition, f32)] = match position {
+        Mono if has(FrontCenter) => &[(FrontCenter, 1.0)],
+        FrontCenter if has(Mono) => &[(Mono, 1.0)],
+        Mono | FrontCenter => &[(FrontLeft, FRAC_1_SQRT_2), (FrontRight, FRAC_1_SQRT_2)],
+        FrontLeft | FrontRight => &[(FrontCenter, FRAC_1_SQRT_2)],
+        RearLeft if has(SideLeft) => &[(SideLeft, 1.0)],
+        RearRight if has(SideRight) => &[(SideRight, 1.0)],
+        SideLeft if has(RearLeft) => &[(RearLeft, 1.0)],
+        SideRight if has(RearRight) => &[(RearRight, 1.0)],
+        RearLeft | SideLeft => &[(FrontLeft, FRAC_1_SQRT_2)],
+        RearRight | SideRight => &[(FrontRight, FRAC_1_SQRT_2)],
+        RearCenter => &[(RearLeft, FRAC_1_SQRT_2), (RearRight, FRAC_1_SQRT_2)],
+        FrontLeftOfCenter | FrontLeftWide | FrontLeftHigh | TopFrontLeft | TopFrontLeftOfCenter => {
+            &[(FrontLeft, 1.0)]
+        }
+        FrontRightOfCenter
+        | FrontRightWide
+        | FrontRightHigh
+        | TopFrontRight
+        | TopFrontRightOfCenter => &[(FrontRight, 1.0)],
+        FrontCenterHigh | TopFrontCenter | TopCenter | BottomCenter => &[(FrontCenter, 1.0)],
+        BottomLeftOfCenter => &[(FrontLeft, 1.0)],
+        BottomRightOfCenter => &[(FrontRight, 1.0)],
+        RearLeftOfCenter | TopRearLeft | TopSideLeft => &[(RearLeft, 1.0)],
+        RearRightOfCenter | TopRearRight | TopSideRight => &[(RearRight, 1.0)],
+        TopRearCenter => &[(RearCenter, 1.0)],
+        LowFrequency | LowFrequencyLeft | LowFrequencyRight | NotApplicable | Unknown => &[],
+    };
+    let mut routes = Vec::new();
+    for &(fallback, gain) in fallbacks {
+        for (index, fallback_gain) in route(fallback, outputs, depth + 1) {
+            routes.push((index, gain * fallback_gain));
+        }
+    }
+    routes
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChannelMatrix;
+    use crate::{ChannelLayout, ChannelPosition};
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn mono_and_stereo() {
+        let up = ChannelMatrix::standard(1, 2);
+        assert_close(up.gain(0, 0), FRAC_1_SQRT_2);
+        assert_close(up.gain(1, 0), FRAC_1_SQRT_2);
+
+        let down = ChannelMatrix::standard(2, 1);
+        assert_close(down.gain(0, 0), 0.5);
+        assert_close(down.gain(0, 1), 0.5);
+
+        let same = ChannelMatrix::standard(2, 2);
+        assert_eq!(
+            same,
+            ChannelMatrix::new(2, 2, vec![1.0, 0.0, 0.0, 1.0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn surround_to_stereo() {
+        let matrix = ChannelMatrix::standard(6, 2);
+        let total = 1.0 + 2.0 * FRAC_1_SQRT_2;
+        // Each side takes its front and back channel and half the power of the center channel.
+        // The LFE channel is dropped.
+        let left = [1.0, 0.0, FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0];
+        let right = [0.0, 1.0, FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2];
+        for input in 0..6 {
+            assert_close(matrix.gain(0, input), left[input as usize] / total);
+            assert_close(matrix.gain(1, input), right[input as usize] / total);
+        }
+    }
+
+    #[test]
+    fn stereo_to_surround_leaves_other_channels_silent() {
+        let matrix = ChannelMatrix::standard(2, 6);
+        let mut output = [1.0; 12];
+        matrix.apply(&[0.25, -0.5, 1.0, 0.0], &mut output);
+        assert_eq!(
+            output,
+            [0.25, -0.5, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn unknown_layouts_map_by_index() {
+        let matrix = ChannelMatrix::standard(10, 12);
+        for output in 0..12 {
+            for input in 0..10 {
+                let expected = if input == output { 1.0 } else { 0.0 };
+                assert_eq!(matrix.gain(output, input), expected);
+            }
+        }
+        assert!(ChannelMatrix::new(2, 3, vec![0.0; 5]).is_none());
+    }
+
+    #[test]
+    fn layouts_are_matched_by_position() {
+        use crate::ChannelPosition::*;
+        let input = ChannelLayout::new(vec![FrontRight, FrontLeft, LowFrequency]);
+        let output = ChannelLayout::new(vec![FrontLeft, FrontRight]);
+        let matrix = ChannelMatrix::from_layouts(&input, &output);
+        assert_eq!(
+            matrix,
+            ChannelMatrix::new(3, 2, vec![0.0, 1.0, 0.0, 1.0, 0.0, 0.0]).unwrap()
+        );
+
+        // Outputs without any front speakers must not send routing in circles.
+        let rear = ChannelLayout::new(vec![ChannelPosition::RearLeft]);
+        let matrix = ChannelMatrix::from_layouts(&ChannelLayout::standard(1).unwrap(), &rear);
+        assert_eq!(matrix.gain(0, 0), 0.0);
+    }
+}
@@ -0,0 +1,444 @@
+//! Negotiating a stream configuration from a set of preferences.
+//!
+//! A `ConfigRequest` lists the sample rates, sample formats and channel counts an application can
+//! work with in order of preference, along with an optional buffer size or latency target. The
+//! request is matched against the configurations supported by a device, yielding either the best
+//! match or the reasons each supported configuration was rejected.
+
+use crate::{
+    latency_frames, BufferSize, ChannelCount, Endianness, FrameCount, NegotiateConfigError,
+    SampleFormat, SampleRate, StreamConfig, SupportedBufferSize, SupportedPeriods,
+    SupportedStreamConfig, SupportedStreamConfigRange,
+};
+use std::time::Duration;
+
+/// The stream configuration an application would like to open, listing acceptable values in order
+/// of preference.
+///
+/// Any parameter left empty accepts every value supported by the device. When several supported
+/// configurations match, the one matching the most preferred channel count wins, followed by the
+/// sample rate and then the sample format. Unconstrained sample rates default to the highest rate
+/// supported.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigRequest {
+    sample_rates: Vec<SampleRate>,
+    sample_formats: Vec<SampleFormat>,
+    channels: Vec<ChannelCount>,
+    buffer: Option<BufferTarget>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BufferTarget {
+    Frames(FrameCount),
+    Latency(Duration),
+}
+
+/// The outcome of a successful negotiation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NegotiatedConfig {
+    supported: SupportedStreamConfig,
+    buffer_size: BufferSize,
+}
+
+/// A supported configuration that did not match a `ConfigRequest`, along with the reasons why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RejectedConfig {
+    /// The supported configuration.
+    pub range: SupportedStreamConfigRange,
+    /// The parameters of the request it failed to satisfy.
+    pub mismatches: Vec<ConfigMismatch>,
+}
+
+/// A parameter of a `ConfigRequest` that a supported configuration failed to satisfy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigMismatch {
+    /// None of the requested channel counts is supported.
+    Channels,
+    /// None of the requested sample rates lies within the supported range.
+    SampleRate,
+    /// None of the requested sample formats is supported.
+    SampleFormat,
+}
+
+impl ConfigRequest {
+    /// A request accepting any supported configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The acceptable sample rates, most preferred first.
+    pub fn sample_rates<I>(mut self, rates: I) -> Self
+    where
+        I: IntoIterator<Item = SampleRate>,
+    {
+        self.sample_rates = rates.into_iter().collect();
+        self
+    }
+
+    /// The acceptable sample formats, most preferred first.
+    pub fn sample_formats<I>(mut self, formats: I) -> Self
+    where
+        I: IntoIterator<Item = SampleFormat>,
+    {
+        self.sample_formats = formats.into_iter().collect();
+        self
+    }
+
+    /// The acceptable channel counts, most preferred first.
+    pub fn channels<I>(mut self, channels: I) -> Self
+    where
+        I: IntoIterator<Item = ChannelCount>,
+    {
+        self.channels = channels.into_iter().collect();
+        self
+    }
+
+    /// Aim for a buffer of the given number of frames.
+    ///
+    /// The buffer size is clamped to the range supported by the device. If the device does not
+    /// report its supported range, the host's default buffer size is used.
+    pub fn buffer_size(mut self, frames: FrameCount) -> Self {
+        self.buffer = Some(BufferTarget::Frames(frames));
+        self
+    }
+
+    /// Aim for a buffer holding the given duration of audio at the negotiated sample rate.
+    ///
+    /// This replaces any target set with `buffer_size` and is subject to the same clamping. If the
+    /// device does not report its supported range, the latency is passed on as
+    /// `BufferSize::Latency` for the host to translate.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.buffer = Some(BufferTarget::Latency(latency));
+        self
+    }
+}
+
+impl NegotiatedConfig {
+    /// The configuration to build a stream with, including the negotiated buffer size.
+    pub fn config(&self) -> StreamConfig {
+        StreamConfig {
+            buffer_size: self.buffer_size.clone(),
+            ..self.supported.config()
+        }
+    }
+
+    /// The sample format to build a stream with.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.supported.sample_format()
+    }
+
+    /// The negotiated buffer size.
+    pub fn buffer_size(&self) -> &BufferSize {
+        &self.buffer_size
+    }
+
+    /// The supported configuration that was chosen.
+    pub fn supported_config(&self) -> &SupportedStreamConfig {
+        &self.supported
+    }
+}
+
+/// Chooses the supported configuration that best matches `request`.
+pub(crate) fn negotiate<I>(
+    ranges: I,
+    request: &ConfigRequest,
+) -> Result<NegotiatedConfig, NegotiateConfigError>
+where
+    I: IntoIterator<Item = SupportedStreamConfigRange>,
+{
+    let mut best = None;
+    let mut rejected = Vec::new();
+    for range in ranges {
+        let channels = if request.channels.is_empty() {
+            Some((0, range.default_channels()))
+        } else {
+            first_supported(&request.channels, |&c| range.supports_channels(c))
+        };
+        let format = preference(&request.sample_formats, |&f| f == range.sample_format);
+        let rate = if request.sample_rates.is_empty() {
+            Some((0, range.max_sample_rate()))
+        } else {
+            first_supported(&request.sample_rates, |&r| range.supports_sample_rate(r))
+        };
+
+        let ((channels_rank, channels), format, (rate_rank, rate)) = match (channels, format, rate)
+        {
+            (Some(channels), Some(format), Some(rate)) => (channels, format, rate),
+            _ => {
+                let mut mismatches = Vec::new();
+                if channels.is_none() {
+                    mismatches.push(ConfigMismatch::Channels);
+                }
+                if rate.is_none() {
+                    mismatches.push(ConfigMismatch::SampleRate);
+                }
+                if format.is_none() {
+                    mismatches.push(ConfigMismatch::SampleFormat);
+                }
+                rejected.push(RejectedConfig { range, mismatches });
+                continue;
+            }
+        };
+
+        let buffer_size = buffer_size(&range.buffer_size, request.buffer, rate);
+        let key = (
+            channels_rank,
+            rate_rank,
+            format,
+            buffer_distance(&range.buffer_size, request.buffer, rate),
+            range.endianness != Endianness::native(),
+        );
+        // Ties go to the configuration the device lists first.
+        let better = match best {
+            Some((ref best_key, _)) => key < *best_key,
+            None => true,
+        };
+        if better {
+            let supported = range
+                .with_channels(channels)
+                .expect("channel count is supported")
+                .with_sample_rate(rate);
+            best = Some((
+                key,
+                NegotiatedConfig {
+                    supported,
+                    buffer_size,
+                },
+            ));
+        }
+    }
+    best.map(|(_, config)| config)
+        .ok_or(NegotiateConfigError::NoMatchingConfig { rejected })
+}
+
+/// Whether `range` supports opening a stream with `config` and `sample_format`.
+///
+/// The fixed buffer size and period settings are only checked against ranges reporting their
+/// limits. The channel selection is checked to be valid for `config.channels`, but not whether the
+/// host supports selecting channels at all.
+pub(crate) fn supports(
+    range: &SupportedStreamConfigRange,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> bool {
+    let buffer_size = match (&config.buffer_size, &range.buffer_size) {
+        (&BufferSize::Fixed(frames), &SupportedBufferSize::Range { min, max }) => {
+            min <= frames && frames <= max
+        }
+        _ => true,
+    };
+    let within = |value: Option<u32>, min, max| match value {
+        Some(value) => min <= value && value <= max,
+        None => true,
+    };
+    let periods = match range.periods {
+        SupportedPeriods::Range {
+            min_size,
+            max_size,
+            min_count,
+            max_count,
+        } => {
+            within(config.period_size, min_size, max_size)
+                && within(config.period_count, min_count, max_count)
+        }
+        SupportedPeriods::Unknown => true,
+    };
+    range.supports_channels(config.channels)
+        && range.sample_format == sample_format
+        && range.supports_sample_rate(config.sample_rate)
+        && buffer_size
+        && periods
+        && config.channel_selection_is_valid()
+}
+
+// The index of the first value in `preferences` matching `f`. An empty list accepts anything.
+fn preference<T, F>(preferences: &[T], f: F) -> Option<usize>
+where
+    F: FnMut(&T) -> bool,
+{
+    if preferences.is_empty() {
+        Some(0)
+    } else {
+        preferences.iter().position(f)
+    }
+}
+
+// The index of the first value in `preferences` matching `f`, along with the value.
+fn first_supported<T, F>(preferences: &[T], f: F) -> Option<(usize, T)>
+where
+    T: Copy,
+    F: FnMut(&T) -> bool,
+{
+    preferences
+        .iter()
+        .position(f)
+        .map(|index| (index, preferences[index]))
+}
+
+fn target_frames(target: BufferTarget, rate: SampleRate) -> FrameCount {
+    match target {
+        BufferTarget::Frames(frames) => frames,
+        BufferTarget::Latency(latency) => latency_frames(latency, rate),
+    }
+}
+
+fn buffer_size(
+    supported: &SupportedBufferSize,
+    target: Option<BufferTarget>,
+    rate: SampleRate,
+) -> BufferSize {
+    match (supported, target) {
+        (&SupportedBufferSize::Range { min, max }, Some(target)) => {
+            BufferSize::Fixed(target_frames(target, rate).max(min).min(max))
+        }
+        // Without a known range the host still gets to pick the buffer closest to the latency.
+        (&SupportedBufferSize::Unknown, Some(BufferTarget::Latency(latency))) => {
+            BufferSize::Latency(latency)
+        }
+        _ => BufferSize::Default,
+    }
+}
+
+// How far the buffer size target lies outside the supported range, in frames. Ranges containing
+// the target are preferred over unknown ones.
+fn buffer_distance(
+    supported: &SupportedBufferSize,
+    target: Option<BufferTarget>,
+    rate: SampleRate,
+) -> FrameCount {
+    match (supported, target) {
+        (_, None) => 0,
+        (&SupportedBufferSize::Range { min, max }, Some(target)) => {
+            let frames = target_frames(target, rate);
+            min.saturating_sub(frames).max(frames.saturating_sub(max))
+        }
+        (&SupportedBufferSize::Unknown, Some(_)) => FrameCount::MAX,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{negotiate, supports, ConfigMismatch, ConfigRequest};
+    use crate::{
+        test_config_range, BufferSize, NegotiateConfigError, SampleFormat, SampleRate,
+        StreamConfig, SupportedPeriods, SupportedSampleRates, SupportedStreamConfigRange,
+    };
+    use std::time::Duration;
+
+    fn range(
+        channels: u16,
+        min_rate: u32,
+        max_rate: u32,
+        sample_format: SampleFormat,
+    ) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange {
+            sample_rates: SupportedSampleRates::Range {
+                min: SampleRate(min_rate),
+                max: SampleRate(max_rate),
+            },
+            ..test_config_range(channels, sample_format)
+        }
+    }
+
+    #[test]
+    fn preferences_are_ranked() {
+        let ranges = vec![
+            range(2, 44_100, 44_100, SampleFormat::I16),
+            range(2, 8_000, 48_000, SampleFormat::F32),
+            range(1, 8_000, 96_000, SampleFormat::F32),
+        ];
+        let request = ConfigRequest::new()
+            .channels(vec![2, 1])
+            .sample_rates(vec![SampleRate(96_000), SampleRate(48_000)])
+            .sample_formats(vec![SampleFormat::F32, SampleFormat::I16]);
+        let negotiated = negotiate(ranges.clone(), &request).unwrap();
+        assert_eq!(negotiated.config().channels, 2);
+        assert_eq!(negotiated.config().sample_rate, SampleRate(48_000));
+        assert_eq!(negotiated.sample_format(), SampleFormat::F32);
+        assert_eq!(*negotiated.buffer_size(), BufferSize::Default);
+
+        // Unconstrained parameters accept anything, defaulting to the highest sample rate.
+        let negotiated = negotiate(ranges, &ConfigRequest::new().channels(vec![1])).unwrap();
+        assert_eq!(negotiated.config().sample_rate, SampleRate(96_000));
+    }
+
+    #[test]
+    fn rejections_are_explained() {
+        let ranges = vec![
+            range(2, 44_100, 48_000, SampleFormat::I16),
+            range(6, 8_000, 22_050, SampleFormat::F32),
+        ];
+        let request = ConfigRequest::new()
+            .channels(vec![2])
+            .sample_rates(vec![SampleRate(48_000)])
+            .sample_formats(vec![SampleFormat::F32]);
+        match negotiate(ranges, &request) {
+            Err(NegotiateConfigError::NoMatchingConfig { rejected }) => {
+                assert_eq!(rejected.len(), 2);
+                assert_eq!(rejected[0].mismatches, [ConfigMismatch::SampleFormat]);
+                assert_eq!(
+                    rejected[1].mismatches,
+                    [ConfigMismatch::Channels, ConfigMismatch::SampleRate]
+                );
+            }
+            _ => panic!("expected NoMatchingConfig"),
+        }
+    }
+
+    #[test]
+    fn buffer_size_follows_latency_target() {
+        let ranges = vec![range(2, 48_000, 48_000, SampleFormat::F32)];
+        let request = ConfigRequest::new().latency(Duration::from_millis(5));
+        let negotiated = negotiate(ranges.clone(), &request).unwrap();
+        assert_eq!(*negotiated.buffer_size(), BufferSize::Fixed(240));
+        assert_eq!(negotiated.config().buffer_size, BufferSize::Fixed(240));
+
+        let request = ConfigRequest::new().buffer_size(16);
+        let negotiated = negotiate(ranges, &request).unwrap();
+        assert_eq!(*negotiated.buffer_size(), BufferSize::Fixed(64));
+    }
+
+    #[test]
+    fn config_support_is_checked() {
+        let range = range(2, 8_000, 48_000, SampleFormat::F32);
+        let mut config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(44_100),
+            buffer_size: BufferSize::Fixed(256),
+            channel_layout: None,
+            channel_selection: None,
+            period_size: None,
+            period_count: None,
+            strict: false,
+        };
+        assert!(supports(&range, &config, SampleFormat::F32));
+        assert!(!supports(&range, &config, SampleFormat::I16));
+        config.buffer_size = BufferSize::Fixed(8192);
+        assert!(!supports(&range, &config, SampleFormat::F32));
+        config.buffer_size = BufferSize::Default;
+        config.channel_selection = Some(vec![1, 1]);
+        assert!(!supports(&range, &config, SampleFormat::F32));
+        config.channel_selection = Some(vec![1, 0]);
+        assert!(supports(&range, &config, SampleFormat::F32));
+        config.period_size = Some(128);
+        config.period_count = Some(2);
+        assert!(supports(&range, &config, SampleFormat::F32));
+        let periods = SupportedStreamConfigRange {
+            periods: SupportedPeriods::Range {
+                min_size: 32,
+                max_size: 1024,
+                min_count: 2,
+                max_count: 4,
+            },
+            ..range.clone()
+        };
+        assert!(supports(&periods, &config, SampleFormat::F32));
+        config.period_count = Some(8);
+        assert!(!supports(&periods, &config, SampleFormat::F32));
+        assert!(range
+            .clone()
+            .try_with_sample_rate(SampleRate(96_000))
+            .is_none());
+        assert!(range.try_with_sample_rate(SampleRate(48_000)).is_some());
+    }
+}
@@ -0,0 +1,269 @@
+//! Conversion between interleaved buffers and `PlanarData` for hosts that only deliver
+//! interleaved audio, and the owned planes that back `PlanarData` where a host needs them.
+
+use crate::traits::DeviceTrait;
+use crate::{
+    BuildStreamError, InputCallbackInfo, OutputCallbackInfo, PlanarData, SampleFormat,
+    StreamConfig, StreamError,
+};
+
+/// Owned storage for the channels of a `PlanarData`, reused across stream callbacks.
+///
+/// The buffer only grows, so once a stream has delivered its largest period no further allocation
+/// takes place on the audio thread.
+pub(crate) struct PlanarBuffer {
+    // Backing memory, aligned for the largest sample type. Each plane starts on a word boundary.
+    data: Vec<u64>,
+    // Pointers to the first sample of each channel in `data`.
+    planes: Vec<*mut ()>,
+    frames: usize,
+    sample_format: SampleFormat,
+}
+
+// The raw pointers in `planes` only ever point into `data`, which is owned by the buffer.
+unsafe impl Send for PlanarBuffer {}
+
+impl PlanarBuffer {
+    pub(crate) fn new(channels: usize, sample_format: SampleFormat) -> Self {
+        PlanarBuffer {
+            data: Vec::new(),
+            planes: vec![std::ptr::null_mut(); channels],
+            frames: 0,
+            sample_format,
+        }
+    }
+
+    /// Splits the interleaved samples in `interleaved` into one plane per channel.
+    ///
+    /// The length of `interleaved` must be a whole number of frames.
+    pub(crate) fn deinterleave(&mut self, interleaved: &[u8]) {
+        let sample_size = self.sample_format.sample_size();
+        let frame_size = sample_size * self.planes.len();
+        debug_assert_eq!(interleaved.len() % frame_size, 0);
+        self.resize(interleaved.len() / frame_size);
+        let plane_size = self.plane_words() * 8;
+        let data = self.bytes_mut();
+        for (frame_ix, frame) in interleaved.chunks(frame_size).enumerate() {
+            for (ch_ix, sample) in frame.chunks(sample_size).enumerate() {
+                let start = ch_ix * plane_size + frame_ix * sample_size;
+                data[start..start + sample_size].copy_from_slice(sample);
+            }
+        }
+    }
+
+    /// Writes the planes back into `interleaved`, which must be the same length as the buffer
+    /// that was last passed to `deinterleave` or `resize`.
+    pub(crate) fn interleave(&self, interleaved: &mut [u8]) {
+        let sample_size = self.sample_format.sample_size();
+        let frame_size = sample_size * self.planes.len();
+        debug_assert_eq!(interleaved.len(), self.frames * frame_size);
+        let plane_size = self.plane_words() * 8;
+        let data = self.bytes();
+        for (frame_ix, frame) in interleaved.chunks_mut(frame_size).enumerate() {
+            for (ch_ix, sample) in frame.chunks_mut(sample_size).enumerate() {
+                let start = ch_ix * plane_size + frame_ix * sample_size;
+                sample.copy_from_slice(&data[start..start + sample_size]);
+            }
+        }
+    }
+
+    /// Sets the number of frames held by each plane.
+    ///
+    /// The contents of the planes are unspecified afterwards.
+    pub(crate) fn resize(&mut self, frames: usize) {
+        self.frames = frames;
+        let plane_words = self.plane_words();
+        let len = plane_words * self.planes.len();
+        if self.data.len() < len {
+            self.data.resize(len, 0);
+        }
+        let base = self.data.as_mut_ptr();
+        for (ch_ix, plane) in self.planes.iter_mut().enumerate() {
+            // Safe as `ch_ix * plane_words` never exceeds the length of `data`.
+            *plane = unsafe { base.add(ch_ix * plane_words) } as *mut ();
+        }
+    }
+
+    // The number of 8 byte words taken up by each plane, rounded up so that every plane is
+    // aligned for the largest sample type.
+    fn plane_words(&self) -> usize {
+        (self.frames * self.sample_format.sample_size() + 7) >> 3
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let len = self.data.len() * std::mem::size_of::<u64>();
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const u8, len) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.data.len() * std::mem::size_of::<u64>();
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_mut_ptr() as *mut u8, len) }
+    }
+
+    /// Pointers to the first sample of each plane, valid until the buffer is next resized.
+    pub(crate) fn planes(&self) -> &[*mut ()] {
+        &self.planes
+    }
+
+    /// The bytes of the given plane.
+    pub(crate) fn plane_bytes_mut(&mut self, channel: usize) -> &mut [u8] {
+        let start = channel * self.plane_words() * 8;
+        let len = self.frames * self.sample_format.sample_size();
+        &mut self.bytes_mut()[start..start + len]
+    }
+
+    /// A view of the planes, valid until the buffer is next modified.
+    pub(crate) fn planar_data(&mut self) -> PlanarData {
+        // The planes point into `data` and hold `frames` samples each, as ensured by `resize`.
+        unsafe {
+            PlanarData::from_parts(
+                self.planes.as_ptr(),
+                self.planes.len(),
+                self.frames,
+                self.sample_format,
+            )
+        }
+    }
+}
+
+/// Builds a planar input stream on top of `build_input_stream_raw`, deinterleaving each buffer.
+///
+/// This is the default for hosts that only capture interleaved audio.
+pub(crate) fn build_input_stream_deinterleaved<Dev, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    error_callback: E,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait + ?Sized,
+    D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    if !config.channel_selection_is_valid() {
+        return Err(BuildStreamError::InvalidArgument);
+    }
+    if config.callback_channels() == 0 {
+        return Err(BuildStreamError::StreamConfigNotSupported);
+    }
+    let mut planar = PlanarBuffer::new(config.callback_channels() as usize, sample_format);
+    device.build_input_stream_raw(
+        config,
+        sample_format,
+        move |data, info| {
+            planar.deinterleave(data.bytes());
+            data_callback(&planar.planar_data(), info);
+        },
+        error_callback,
+    )
+}
+
+/// Builds a planar output stream on top of `build_output_stream_raw`, interleaving each buffer.
+///
+/// This is the default for hosts that only play interleaved audio.
+pub(crate) fn build_output_stream_interleaved<Dev, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    error_callback: E,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait + ?Sized,
+    D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    if !config.channel_selection_is_valid() {
+        return Err(BuildStreamError::InvalidArgument);
+    }
+    if config.callback_channels() == 0 {
+        return Err(BuildStreamError::StreamConfigNotSupported);
+    }
+    let channels = config.callback_channels() as usize;
+    let mut planar = PlanarBuffer::new(channels, sample_format);
+    device.build_output_stream_raw(
+        config,
+        sample_format,
+        move |data, info| {
+            planar.resize(data.len() / channels);
+            data_callback(&mut planar.planar_data(), info);
+            planar.interleave(data.bytes_mut());
+        },
+        error_callback,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlanarBuffer;
+    use crate::SampleFormat;
+
+    fn to_bytes(samples: &[i16]) -> Vec<u8> {
+        samples
+            .iter()
+            .flat_map(|s| s.to_ne_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn deinterleave_splits_channels() {
+        let interleaved = to_bytes(&[0, 10, 20, 1, 11, 21, 2, 12, 22]);
+        let mut buffer = PlanarBuffer::new(3, SampleFormat::I16);
+        buffer.deinterleave(&interleaved);
+        let data = buffer.planar_data();
+        assert_eq!(data.channels(), 3);
+        assert_eq!(data.frames(), 3);
+        assert_eq!(data.channel::<i16>(0), Some(&[0, 1, 2][..]));
+        assert_eq!(data.channel::<i16>(1), Some(&[10, 11, 12][..]));
+        assert_eq!(data.channel::<i16>(2), Some(&[20, 21, 22][..]));
+        assert_eq!(data.channel::<f32>(0), None);
+    }
+
+    #[test]
+    fn interleave_round_trips_with_changing_lengths() {
+        let mut buffer = PlanarBuffer::new(2, SampleFormat::I16);
+        for &frames in &[4usize, 1, 3] {
+            let samples: Vec<i16> = (0..frames as i16 * 2).collect();
+            let interleaved = to_bytes(&samples);
+            buffer.deinterleave(&interleaved);
+            let mut output = vec![0u8; interleaved.len()];
+            buffer.interleave(&mut output);
+            assert_eq!(output, interleaved);
+        }
+    }
+
+    #[test]
+    fn planes_are_aligned_for_their_samples() {
+        let mut buffer = PlanarBuffer::new(3, SampleFormat::F64);
+        for &frames in &[0usize, 1, 5] {
+            buffer.resize(frames);
+            let data = buffer.planar_data();
+            for ch in 0..3 {
+                let plane = data.channel::<f64>(ch).unwrap();
+                assert_eq!(plane.len(), frames);
+                assert_eq!(plane.as_ptr() as usize % std::mem::align_of::<f64>(), 0);
+            }
+        }
+        // Planes of odd byte lengths still start on word boundaries.
+        let mut buffer = PlanarBuffer::new(2, SampleFormat::I24Packed);
+        buffer.resize(3);
+        assert_eq!(buffer.planes[1] as usize % 8, 0);
+    }
+
+    #[test]
+    fn planar_data_writes_are_interleaved() {
+        let mut buffer = PlanarBuffer::new(2, SampleFormat::I16);
+        buffer.resize(2);
+        {
+            let mut data = buffer.planar_data();
+            data.channel_mut::<i16>(0).unwrap().copy_from_slice(&[1, 2]);
+            data.channel_mut::<i16>(1)
+                .unwrap()
+                .copy_from_slice(&[-1, -2]);
+        }
+        let mut output = vec![0u8; 8];
+        buffer.interleave(&mut output);
+        assert_eq!(output, to_bytes(&[1, -1, 2, -2]));
+    }
+}
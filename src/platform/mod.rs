@@ -215,6 +215,38 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn id(&self) -> Result<crate::DeviceId, crate::DeviceIdError> {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.id(),
+                    )*
+                }
+            }
+
+            fn description(&self) -> Result<crate::DeviceDescription, crate::DeviceDescriptionError> {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.description(),
+                    )*
+                }
+            }
+
+            fn supports_input(&self) -> bool {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.supports_input(),
+                    )*
+                }
+            }
+
+            fn supports_output(&self) -> bool {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.supports_output(),
+                    )*
+                }
+            }
+
             fn supported_input_configs(&self) -> Result<Self::SupportedInputConfigs, crate::SupportedStreamConfigsError> {
                 match self.0 {
                     $(
@@ -306,6 +338,58 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn build_input_stream_planar<D, E>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&crate::PlanarData, &crate::InputCallbackInfo) + Send + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_input_stream_planar(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_output_stream_planar<D, E>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&mut crate::PlanarData, &crate::OutputCallbackInfo) + Send + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_output_stream_planar(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
         }
 
         impl crate::traits::HostTrait for Host {
@@ -345,6 +429,16 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn device_by_id(&self, id: &crate::DeviceId) -> Option<Self::Device> {
+                match self.0 {
+                    $(
+                        HostInner::$HostVariant(ref h) => {
+                            h.device_by_id(id).map(DeviceInner::$HostVariant).map(Device::from)
+                        }
+                    )*
+                }
+            }
         }
 
         impl crate::traits::StreamTrait for Stream {
@@ -367,6 +461,36 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn channel_layout(&self) -> Option<crate::ChannelLayout> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.channel_layout()
+                        }
+                    )*
+                }
+            }
+
+            fn latency(&self) -> Option<std::time::Duration> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.latency()
+                        }
+                    )*
+                }
+            }
+
+            fn effective_config(&self) -> Option<crate::EffectiveConfig> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.effective_config()
+                        }
+                    )*
+                }
+            }
         }
 
         impl From<DeviceInner> for Device {
@@ -449,7 +573,8 @@ macro_rules! impl_platform_host {
 #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
 mod platform_impl {
     pub use crate::host::alsa::{
-        Device as AlsaDevice, Devices as AlsaDevices, Host as AlsaHost, Stream as AlsaStream,
+        Device as AlsaDevice, Devices as AlsaDevices, Enumeration as AlsaEnumeration,
+        Host as AlsaHost, PcmName as AlsaPcmName, PcmPlugin as AlsaPcmPlugin, Stream as AlsaStream,
         SupportedInputConfigs as AlsaSupportedInputConfigs,
         SupportedOutputConfigs as AlsaSupportedOutputConfigs,
     };
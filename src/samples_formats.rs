@@ -1,66 +1,874 @@
+use std::hash::{Hash, Hasher};
 use std::mem;
 
 /// Format that each sample has.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SampleFormat {
+    /// The value 0 corresponds to 0.
+    I8,
     /// The value 0 corresponds to 0.
     I16,
+    /// 24-bit samples stored in the lower three bytes of a 32-bit word. See `I24`.
+    ///
+    /// The value 0 corresponds to 0.
+    I24,
+    /// 24-bit samples packed into three bytes. See `I24Packed`.
+    ///
+    /// The value 0 corresponds to 0.
+    I24Packed,
+    /// The value 0 corresponds to 0.
+    I32,
+    /// The value 0 corresponds to 128.
+    U8,
     /// The value 0 corresponds to 32768.
     U16,
+    /// The value 0 corresponds to 2147483648.
+    U32,
     /// The boundaries are (-1.0, 1.0).
     F32,
+    /// The boundaries are (-1.0, 1.0).
+    F64,
+    /// 8-bit G.711 mu-law companded samples.
+    ///
+    /// There is no `Sample` type for this format. Use `Data::decode_companded` and
+    /// `Data::encode_companded` to convert to and from linear samples.
+    MuLaw,
+    /// 8-bit G.711 A-law companded samples.
+    ///
+    /// There is no `Sample` type for this format. Use `Data::decode_companded` and
+    /// `Data::encode_companded` to convert to and from linear samples.
+    ALaw,
 }
 
 impl SampleFormat {
     /// Returns the size in bytes of a sample of this format.
     #[inline]
     pub fn sample_size(&self) -> usize {
+        match *self {
+            SampleFormat::I8 => mem::size_of::<i8>(),
+            SampleFormat::I16 => mem::size_of::<i16>(),
+            SampleFormat::I24 => mem::size_of::<I24>(),
+            SampleFormat::I24Packed => mem::size_of::<I24Packed>(),
+            SampleFormat::I32 => mem::size_of::<i32>(),
+            SampleFormat::U8 => mem::size_of::<u8>(),
+            SampleFormat::U16 => mem::size_of::<u16>(),
+            SampleFormat::U32 => mem::size_of::<u32>(),
+            SampleFormat::F32 => mem::size_of::<f32>(),
+            SampleFormat::F64 => mem::size_of::<f64>(),
+            SampleFormat::MuLaw | SampleFormat::ALaw => mem::size_of::<u8>(),
+        }
+    }
+
+    /// Fills the given buffer of samples of this format in native byte order with silence.
+    pub(crate) fn fill_silence(self, buffer: &mut [u8]) {
+        let u8_silence = [<u8 as Sample>::EQUILIBRIUM];
+        let u16_silence = <u16 as Sample>::EQUILIBRIUM.to_ne_bytes();
+        let u32_silence = <u32 as Sample>::EQUILIBRIUM.to_ne_bytes();
+        let mu_law_silence = [linear_to_mu_law(0)];
+        let a_law_silence = [linear_to_a_law(0)];
+        let silence: &[u8] = match self {
+            SampleFormat::U8 => &u8_silence,
+            SampleFormat::U16 => &u16_silence,
+            SampleFormat::U32 => &u32_silence,
+            SampleFormat::MuLaw => &mu_law_silence,
+            SampleFormat::ALaw => &a_law_silence,
+            _ => {
+                // Silence is all zero bytes for signed integer and floating point samples.
+                for byte in buffer.iter_mut() {
+                    *byte = 0;
+                }
+                return;
+            }
+        };
+        for sample in buffer.chunks_exact_mut(silence.len()) {
+            sample.copy_from_slice(silence);
+        }
+    }
+}
+
+/// The byte order of multi-byte samples as they are exchanged with an audio device.
+///
+/// Stream callbacks always see samples in the native byte order of the target platform. Hosts
+/// that open a device in the opposite byte order convert the samples on the fly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+impl Endianness {
+    /// The byte order of the target platform.
+    #[inline]
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    /// Whether or not this is the byte order of the target platform.
+    #[inline]
+    pub fn is_native(self) -> bool {
+        self == Endianness::native()
+    }
+
+    /// The opposite byte order.
+    #[inline]
+    pub fn swapped(self) -> Self {
         match self {
-            &SampleFormat::I16 => mem::size_of::<i16>(),
-            &SampleFormat::U16 => mem::size_of::<u16>(),
-            &SampleFormat::F32 => mem::size_of::<f32>(),
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        }
+    }
+}
+
+/// A signed 24-bit sample stored in the lower three bytes of an `i32`.
+///
+/// This is the layout that ALSA refers to as `S24_LE`/`S24_BE`, where each 24-bit sample occupies
+/// a full 32-bit word. The upper byte is ignored when reading the value, including when comparing
+/// and hashing samples.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct I24(i32);
+
+/// A signed 24-bit sample packed into three bytes in native byte order.
+///
+/// This is the layout that ALSA refers to as `S24_3LE`/`S24_3BE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct I24Packed([u8; 3]);
+
+impl I24 {
+    /// The lowest value representable by a 24-bit sample.
+    pub const MIN: I24 = I24(-8_388_608);
+    /// The highest value representable by a 24-bit sample.
+    pub const MAX: I24 = I24(8_388_607);
+
+    /// Creates a 24-bit sample from the given value.
+    ///
+    /// Returns `None` if the value lies outside of the range `I24::MIN..=I24::MAX`.
+    #[inline]
+    pub fn new(value: i32) -> Option<Self> {
+        if (I24::MIN.0..=I24::MAX.0).contains(&value) {
+            Some(I24(value))
+        } else {
+            None
+        }
+    }
+
+    /// The value of the sample, sign-extended to an `i32`.
+    #[inline]
+    pub fn inner(self) -> i32 {
+        (self.0 << 8) >> 8
+    }
+}
+
+impl PartialEq for I24 {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for I24 {}
+
+impl Hash for I24 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+impl I24Packed {
+    /// Creates a packed sample from its three bytes in native byte order.
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 3]) -> Self {
+        I24Packed(bytes)
+    }
+
+    /// The three bytes of the sample in native byte order.
+    #[inline]
+    pub fn to_bytes(self) -> [u8; 3] {
+        self.0
+    }
+}
+
+/// Trait for containers that contain PCM data.
+///
+/// # Safety
+///
+/// `FORMAT` must correctly describe the memory representation of the implementing type, as raw
+/// audio buffers are reinterpreted as slices of this type based on their `SampleFormat`.
+pub unsafe trait Sample: Copy + Clone {
+    /// The `SampleFormat` corresponding to this data type.
+    const FORMAT: SampleFormat;
+
+    /// The value of silence, i.e. the centre of the range of this sample type.
+    ///
+    /// This is 0 for signed and floating point types and e.g. 32768 for `u16`.
+    const EQUILIBRIUM: Self;
+    /// The lowest full-scale value, corresponding to `-1.0` as a floating point sample.
+    const FULL_SCALE_MIN: Self;
+    /// The highest full-scale value, corresponding to `1.0` as a floating point sample.
+    const FULL_SCALE_MAX: Self;
+
+    /// Turns the sample into its equivalent as a floating-point.
+    fn to_f32(&self) -> f32;
+    /// Turns the sample into its equivalent as a double-precision floating-point.
+    fn to_f64(&self) -> f64;
+    /// Converts this sample into a standard i8 sample.
+    fn to_i8(&self) -> i8;
+    /// Converts this sample into a standard i16 sample.
+    fn to_i16(&self) -> i16;
+    /// Converts this sample into a 24-bit sample.
+    fn to_i24(&self) -> I24;
+    /// Converts this sample into a standard i32 sample.
+    fn to_i32(&self) -> i32;
+    /// Converts this sample into a standard u8 sample.
+    fn to_u8(&self) -> u8;
+    /// Converts this sample into a standard u16 sample.
+    fn to_u16(&self) -> u16;
+    /// Converts this sample into a standard u32 sample.
+    fn to_u32(&self) -> u32;
+
+    /// Adds two samples, treating `EQUILIBRIUM` as zero.
+    ///
+    /// Integer samples saturate at `FULL_SCALE_MIN` and `FULL_SCALE_MAX`. Floating point samples
+    /// are not clamped, as they can exceed full scale without loss.
+    fn add_amp(self, other: Self) -> Self;
+
+    /// Multiplies the amplitude of the sample, i.e. its distance from `EQUILIBRIUM`, by `amp`.
+    ///
+    /// Integer samples saturate at `FULL_SCALE_MIN` and `FULL_SCALE_MAX`.
+    fn mul_amp(self, amp: f32) -> Self;
+
+    /// Converts any sample type to this one by calling `to_i8`, `to_i16`, `to_i24`, `to_i32`,
+    /// `to_u8`, `to_u16`, `to_u32`, `to_f32` or `to_f64`.
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample;
+
+    /// Converts this sample into any other sample type.
+    ///
+    /// This is equivalent to `U::from(self)`, but reads more naturally in a chain of operations.
+    #[inline]
+    fn to_sample<U>(&self) -> U
+    where
+        U: Sample,
+    {
+        U::from(self)
+    }
+}
+
+unsafe impl Sample for u8 {
+    const FORMAT: SampleFormat = SampleFormat::U8;
+    const EQUILIBRIUM: Self = 128;
+    const FULL_SCALE_MIN: Self = 0;
+    const FULL_SCALE_MAX: Self = u8::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_i8().to_f32()
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i8().to_f64()
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        (*self as i16 - 128) as i8
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        self.to_i8().to_i16()
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        self.to_i8().to_i24()
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.to_i8().to_i32()
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        *self
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        (*self as u16) << 8
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        (*self as u32) << 24
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        (self.to_i8().add_amp(other.to_i8())).to_u8()
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        self.to_i8().mul_amp(amp).to_u8()
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_u8()
+    }
+}
+
+unsafe impl Sample for u16 {
+    const FORMAT: SampleFormat = SampleFormat::U16;
+    const EQUILIBRIUM: Self = 32768;
+    const FULL_SCALE_MIN: Self = 0;
+    const FULL_SCALE_MAX: Self = u16::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_i16().to_f32()
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i16().to_f64()
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        self.to_u8().to_i8()
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        if *self >= 32768 {
+            (*self - 32768) as i16
+        } else {
+            (*self as i16) - 32767 - 1
+        }
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        self.to_i16().to_i24()
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.to_i16().to_i32()
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        (*self >> 8) as u8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        *self
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        (*self as u32) << 16
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        (self.to_i16().add_amp(other.to_i16())).to_u16()
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        self.to_i16().mul_amp(amp).to_u16()
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_u16()
+    }
+}
+
+unsafe impl Sample for i8 {
+    const FORMAT: SampleFormat = SampleFormat::I8;
+    const EQUILIBRIUM: Self = 0;
+    const FULL_SCALE_MIN: Self = i8::MIN;
+    const FULL_SCALE_MAX: Self = i8::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        if *self < 0 {
+            *self as f32 / -(i8::MIN as f32)
+        } else {
+            *self as f32 / i8::MAX as f32
+        }
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i8::MIN as f64)
+        } else {
+            *self as f64 / i8::MAX as f64
+        }
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        *self
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (*self as i16) << 8
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        I24((*self as i32) << 16)
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (*self as i32) << 24
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        (*self as i16 + 128) as u8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.to_i16().to_u16()
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        self.to_i32().to_u32()
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        i8::saturating_add(self, other)
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        (self as f32 * amp) as i8
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_i8()
+    }
+}
+
+unsafe impl Sample for i16 {
+    const FORMAT: SampleFormat = SampleFormat::I16;
+    const EQUILIBRIUM: Self = 0;
+    const FULL_SCALE_MIN: Self = i16::MIN;
+    const FULL_SCALE_MAX: Self = i16::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        if *self < 0 {
+            *self as f32 / -(i16::MIN as f32)
+        } else {
+            *self as f32 / i16::MAX as f32
+        }
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i16::MIN as f64)
+        } else {
+            *self as f64 / i16::MAX as f64
+        }
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        (*self >> 8) as i8
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        *self
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        I24((*self as i32) << 8)
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (*self as i32) << 16
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        self.to_i8().to_u8()
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        if *self < 0 {
+            (*self - i16::MIN) as u16
+        } else {
+            (*self as u16) + 32768
+        }
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        self.to_i32().to_u32()
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        i16::saturating_add(self, other)
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        (self as f32 * amp) as i16
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_i16()
+    }
+}
+
+unsafe impl Sample for f32 {
+    const FORMAT: SampleFormat = SampleFormat::F32;
+    const EQUILIBRIUM: Self = 0.0;
+    const FULL_SCALE_MIN: Self = -1.0;
+    const FULL_SCALE_MAX: Self = 1.0;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        *self
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        if *self >= 0.0 {
+            (*self * i8::MAX as f32) as i8
+        } else {
+            (-*self * i8::MIN as f32) as i8
+        }
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        if *self >= 0.0 {
+            (*self * i16::MAX as f32) as i16
+        } else {
+            (-*self * i16::MIN as f32) as i16
+        }
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        let value = if *self >= 0.0 {
+            (*self * I24::MAX.0 as f32) as i32
+        } else {
+            (-*self * I24::MIN.0 as f32) as i32
+        };
+        I24(value.clamp(I24::MIN.0, I24::MAX.0))
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.to_f64().to_i32()
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        (((*self + 1.0) * 0.5) * u8::MAX as f32).round() as u8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        (((*self + 1.0) * 0.5) * u16::MAX as f32).round() as u16
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        self.to_f64().to_u32()
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        self + other
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        self * amp
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_f32()
+    }
+}
+
+unsafe impl Sample for u32 {
+    const FORMAT: SampleFormat = SampleFormat::U32;
+    const EQUILIBRIUM: Self = 2_147_483_648;
+    const FULL_SCALE_MIN: Self = 0;
+    const FULL_SCALE_MAX: Self = u32::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_i32().to_f32()
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i32().to_f64()
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        self.to_i32().to_i8()
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        self.to_i32().to_i16()
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        self.to_i32().to_i24()
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        if *self >= 2_147_483_648 {
+            (*self - 2_147_483_648) as i32
+        } else {
+            (*self as i32) - i32::MAX - 1
+        }
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        (*self >> 24) as u8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        (*self >> 16) as u16
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        *self
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        (self.to_i32().add_amp(other.to_i32())).to_u32()
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        self.to_i32().mul_amp(amp).to_u32()
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_u32()
+    }
+}
+
+unsafe impl Sample for i32 {
+    const FORMAT: SampleFormat = SampleFormat::I32;
+    const EQUILIBRIUM: Self = 0;
+    const FULL_SCALE_MIN: Self = i32::MIN;
+    const FULL_SCALE_MAX: Self = i32::MAX;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i32::MIN as f64)
+        } else {
+            *self as f64 / i32::MAX as f64
+        }
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        (*self >> 24) as i8
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (*self >> 16) as i16
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        I24(*self >> 8)
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        *self
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        self.to_i8().to_u8()
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.to_i16().to_u16()
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        if *self < 0 {
+            (*self - i32::MIN) as u32
+        } else {
+            (*self as u32) + 2_147_483_648
         }
     }
-}
 
-/// Trait for containers that contain PCM data.
-pub unsafe trait Sample: Copy + Clone {
-    /// The `SampleFormat` corresponding to this data type.
-    const FORMAT: SampleFormat;
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        i32::saturating_add(self, other)
+    }
 
-    /// Turns the sample into its equivalent as a floating-point.
-    fn to_f32(&self) -> f32;
-    /// Converts this sample into a standard i16 sample.
-    fn to_i16(&self) -> i16;
-    /// Converts this sample into a standard u16 sample.
-    fn to_u16(&self) -> u16;
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        (self as f64 * amp as f64) as i32
+    }
 
-    /// Converts any sample type to this one by calling `to_i16`, `to_u16` or `to_f32`.
-    fn from<S>(&S) -> Self
+    #[inline]
+    fn from<S>(sample: &S) -> Self
     where
-        S: Sample;
+        S: Sample,
+    {
+        sample.to_i32()
+    }
 }
 
-unsafe impl Sample for u16 {
-    const FORMAT: SampleFormat = SampleFormat::U16;
+unsafe impl Sample for f64 {
+    const FORMAT: SampleFormat = SampleFormat::F64;
+    const EQUILIBRIUM: Self = 0.0;
+    const FULL_SCALE_MIN: Self = -1.0;
+    const FULL_SCALE_MAX: Self = 1.0;
 
     #[inline]
     fn to_f32(&self) -> f32 {
-        self.to_i16().to_f32()
+        *self as f32
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        self.to_f32().to_i8()
     }
 
     #[inline]
     fn to_i16(&self) -> i16 {
-        if *self >= 32768 {
-            (*self - 32768) as i16
+        self.to_f32().to_i16()
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        let value = if *self >= 0.0 {
+            (*self * I24::MAX.0 as f64) as i32
         } else {
-            (*self as i16) - 32767 - 1
+            (-*self * I24::MIN.0 as f64) as i32
+        };
+        I24(value.clamp(I24::MIN.0, I24::MAX.0))
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        if *self >= 0.0 {
+            (*self * i32::MAX as f64) as i32
+        } else {
+            (-*self * i32::MIN as f64) as i32
         }
     }
 
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        self.to_f32().to_u8()
+    }
+
     #[inline]
     fn to_u16(&self) -> u16 {
-        *self
+        self.to_f32().to_u16()
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        (((*self + 1.0) * 0.5) * u32::MAX as f64).round() as u32
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        self + other
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        self * amp as f64
     }
 
     #[inline]
@@ -68,34 +876,80 @@ unsafe impl Sample for u16 {
     where
         S: Sample,
     {
-        sample.to_u16()
+        sample.to_f64()
     }
 }
 
-unsafe impl Sample for i16 {
-    const FORMAT: SampleFormat = SampleFormat::I16;
+unsafe impl Sample for I24 {
+    const FORMAT: SampleFormat = SampleFormat::I24;
+    const EQUILIBRIUM: Self = I24(0);
+    const FULL_SCALE_MIN: Self = I24::MIN;
+    const FULL_SCALE_MAX: Self = I24::MAX;
 
     #[inline]
     fn to_f32(&self) -> f32 {
-        if *self < 0 {
-            *self as f32 / -(::std::i16::MIN as f32)
+        let value = self.inner();
+        if value < 0 {
+            value as f32 / -(I24::MIN.0 as f32)
+        } else {
+            value as f32 / I24::MAX.0 as f32
+        }
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        let value = self.inner();
+        if value < 0 {
+            value as f64 / -(I24::MIN.0 as f64)
         } else {
-            *self as f32 / ::std::i16::MAX as f32
+            value as f64 / I24::MAX.0 as f64
         }
     }
 
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        (self.inner() >> 16) as i8
+    }
+
     #[inline]
     fn to_i16(&self) -> i16 {
-        *self
+        (self.inner() >> 8) as i16
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        I24(self.inner())
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.inner() << 8
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        self.to_i8().to_u8()
     }
 
     #[inline]
     fn to_u16(&self) -> u16 {
-        if *self < 0 {
-            (*self - ::std::i16::MIN) as u16
-        } else {
-            (*self as u16) + 32768
-        }
+        self.to_i16().to_u16()
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        self.to_i32().to_u32()
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        I24((self.inner() + other.inner()).clamp(I24::MIN.0, I24::MAX.0))
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        let value = (self.inner() as f64 * amp as f64) as i32;
+        I24(value.clamp(I24::MIN.0, I24::MAX.0))
     }
 
     #[inline]
@@ -103,30 +957,83 @@ unsafe impl Sample for i16 {
     where
         S: Sample,
     {
-        sample.to_i16()
+        sample.to_i24()
     }
 }
 
-unsafe impl Sample for f32 {
-    const FORMAT: SampleFormat = SampleFormat::F32;
+unsafe impl Sample for I24Packed {
+    const FORMAT: SampleFormat = SampleFormat::I24Packed;
+    const EQUILIBRIUM: Self = I24Packed([0; 3]);
+    const FULL_SCALE_MIN: Self = I24Packed(if cfg!(target_endian = "big") {
+        [0x80, 0x00, 0x00]
+    } else {
+        [0x00, 0x00, 0x80]
+    });
+    const FULL_SCALE_MAX: Self = I24Packed(if cfg!(target_endian = "big") {
+        [0x7F, 0xFF, 0xFF]
+    } else {
+        [0xFF, 0xFF, 0x7F]
+    });
 
     #[inline]
     fn to_f32(&self) -> f32 {
-        *self
+        self.to_i24().to_f32()
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i24().to_f64()
+    }
+
+    #[inline]
+    fn to_i8(&self) -> i8 {
+        self.to_i24().to_i8()
     }
 
     #[inline]
     fn to_i16(&self) -> i16 {
-        if *self >= 0.0 {
-            (*self * ::std::i16::MAX as f32) as i16
+        self.to_i24().to_i16()
+    }
+
+    #[inline]
+    fn to_i24(&self) -> I24 {
+        let [b0, b1, b2] = self.0;
+        let (hi, mid, lo) = if cfg!(target_endian = "big") {
+            (b0, b1, b2)
         } else {
-            (-*self * ::std::i16::MIN as f32) as i16
-        }
+            (b2, b1, b0)
+        };
+        I24((hi as i8 as i32) << 16 | (mid as i32) << 8 | lo as i32)
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.to_i24().to_i32()
+    }
+
+    #[inline]
+    fn to_u8(&self) -> u8 {
+        self.to_i24().to_u8()
     }
 
     #[inline]
     fn to_u16(&self) -> u16 {
-        (((*self + 1.0) * 0.5) * ::std::u16::MAX as f32).round() as u16
+        self.to_i24().to_u16()
+    }
+
+    #[inline]
+    fn to_u32(&self) -> u32 {
+        self.to_i24().to_u32()
+    }
+
+    #[inline]
+    fn add_amp(self, other: Self) -> Self {
+        Sample::from(&self.to_i24().add_amp(other.to_i24()))
+    }
+
+    #[inline]
+    fn mul_amp(self, amp: f32) -> Self {
+        Sample::from(&self.to_i24().mul_amp(amp))
     }
 
     #[inline]
@@ -134,13 +1041,211 @@ unsafe impl Sample for f32 {
     where
         S: Sample,
     {
-        sample.to_f32()
+        let value = sample.to_i24().inner();
+        let (hi, mid, lo) = ((value >> 16) as u8, (value >> 8) as u8, value as u8);
+        if cfg!(target_endian = "big") {
+            I24Packed([hi, mid, lo])
+        } else {
+            I24Packed([lo, mid, hi])
+        }
+    }
+}
+
+/// Decodes a G.711 mu-law byte into a linear 16-bit sample.
+#[inline]
+pub(crate) fn mu_law_to_linear(byte: u8) -> i16 {
+    MU_LAW_TO_LINEAR[byte as usize]
+}
+
+/// Decodes a G.711 A-law byte into a linear 16-bit sample.
+#[inline]
+pub(crate) fn a_law_to_linear(byte: u8) -> i16 {
+    A_LAW_TO_LINEAR[byte as usize]
+}
+
+/// Encodes a linear 16-bit sample as a G.711 mu-law byte.
+pub(crate) fn linear_to_mu_law(sample: i16) -> u8 {
+    const CLIP: i16 = 8159;
+    const BIAS: i16 = 0x84 >> 2;
+    // mu-law works on 14-bit magnitudes.
+    let value = sample >> 2;
+    let (value, mask) = if value < 0 {
+        (-value, 0x7F)
+    } else {
+        (value, 0xFF)
+    };
+    let value = value.min(CLIP) + BIAS;
+    let segment = g711_segment(value, 0x3F);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let mantissa = (value >> (segment + 1)) & 0x0F;
+    ((segment << 4) | mantissa as u8) ^ mask
+}
+
+/// Encodes a linear 16-bit sample as a G.711 A-law byte.
+pub(crate) fn linear_to_a_law(sample: i16) -> u8 {
+    // A-law works on 13-bit magnitudes.
+    let value = sample >> 3;
+    let (value, mask) = if value >= 0 {
+        (value, 0xD5)
+    } else {
+        (-value - 1, 0x55)
+    };
+    let segment = g711_segment(value, 0x1F);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let mantissa = if segment < 2 {
+        (value >> 1) & 0x0F
+    } else {
+        (value >> segment) & 0x0F
+    };
+    ((segment << 4) | mantissa as u8) ^ mask
+}
+
+/// Finds the G.711 segment of the given magnitude, where each segment's upper bound doubles
+/// starting from `first_end`.
+///
+/// Returns 8 if the value lies beyond the last segment.
+fn g711_segment(value: i16, first_end: i16) -> u8 {
+    let value = value as i32;
+    let mut end = first_end as i32;
+    let mut segment = 0;
+    while segment < 8 && value > end {
+        end = (end << 1) | 1;
+        segment += 1;
     }
+    segment
 }
 
+/// G.711 mu-law decode table, indexed by the encoded byte.
+const MU_LAW_TO_LINEAR: [i16; 256] = [
+    -32124, -31100, -30076, -29052, -28028, -27004, -25980, -24956, -23932, -22908, -21884, -20860,
+    -19836, -18812, -17788, -16764, -15996, -15484, -14972, -14460, -13948, -13436, -12924, -12412,
+    -11900, -11388, -10876, -10364, -9852, -9340, -8828, -8316, -7932, -7676, -7420, -7164, -6908,
+    -6652, -6396, -6140, -5884, -5628, -5372, -5116, -4860, -4604, -4348, -4092, -3900, -3772,
+    -3644, -3516, -3388, -3260, -3132, -3004, -2876, -2748, -2620, -2492, -2364, -2236, -2108,
+    -1980, -1884, -1820, -1756, -1692, -1628, -1564, -1500, -1436, -1372, -1308, -1244, -1180,
+    -1116, -1052, -988, -924, -876, -844, -812, -780, -748, -716, -684, -652, -620, -588, -556,
+    -524, -492, -460, -428, -396, -372, -356, -340, -324, -308, -292, -276, -260, -244, -228, -212,
+    -196, -180, -164, -148, -132, -120, -112, -104, -96, -88, -80, -72, -64, -56, -48, -40, -32,
+    -24, -16, -8, 0, 32124, 31100, 30076, 29052, 28028, 27004, 25980, 24956, 23932, 22908, 21884,
+    20860, 19836, 18812, 17788, 16764, 15996, 15484, 14972, 14460, 13948, 13436, 12924, 12412,
+    11900, 11388, 10876, 10364, 9852, 9340, 8828, 8316, 7932, 7676, 7420, 7164, 6908, 6652, 6396,
+    6140, 5884, 5628, 5372, 5116, 4860, 4604, 4348, 4092, 3900, 3772, 3644, 3516, 3388, 3260, 3132,
+    3004, 2876, 2748, 2620, 2492, 2364, 2236, 2108, 1980, 1884, 1820, 1756, 1692, 1628, 1564, 1500,
+    1436, 1372, 1308, 1244, 1180, 1116, 1052, 988, 924, 876, 844, 812, 780, 748, 716, 684, 652,
+    620, 588, 556, 524, 492, 460, 428, 396, 372, 356, 340, 324, 308, 292, 276, 260, 244, 228, 212,
+    196, 180, 164, 148, 132, 120, 112, 104, 96, 88, 80, 72, 64, 56, 48, 40, 32, 24, 16, 8, 0,
+];
+
+/// G.711 A-law decode table, indexed by the encoded byte.
+const A_LAW_TO_LINEAR: [i16; 256] = [
+    -5504, -5248, -6016, -5760, -4480, -4224, -4992, -4736, -7552, -7296, -8064, -7808, -6528,
+    -6272, -7040, -6784, -2752, -2624, -3008, -2880, -2240, -2112, -2496, -2368, -3776, -3648,
+    -4032, -3904, -3264, -3136, -3520, -3392, -22016, -20992, -24064, -23040, -17920, -16896,
+    -19968, -18944, -30208, -29184, -32256, -31232, -26112, -25088, -28160, -27136, -11008, -10496,
+    -12032, -11520, -8960, -8448, -9984, -9472, -15104, -14592, -16128, -15616, -13056, -12544,
+    -14080, -13568, -344, -328, -376, -360, -280, -264, -312, -296, -472, -456, -504, -488, -408,
+    -392, -440, -424, -88, -72, -120, -104, -24, -8, -56, -40, -216, -200, -248, -232, -152, -136,
+    -184, -168, -1376, -1312, -1504, -1440, -1120, -1056, -1248, -1184, -1888, -1824, -2016, -1952,
+    -1632, -1568, -1760, -1696, -688, -656, -752, -720, -560, -528, -624, -592, -944, -912, -1008,
+    -976, -816, -784, -880, -848, 5504, 5248, 6016, 5760, 4480, 4224, 4992, 4736, 7552, 7296, 8064,
+    7808, 6528, 6272, 7040, 6784, 2752, 2624, 3008, 2880, 2240, 2112, 2496, 2368, 3776, 3648, 4032,
+    3904, 3264, 3136, 3520, 3392, 22016, 20992, 24064, 23040, 17920, 16896, 19968, 18944, 30208,
+    29184, 32256, 31232, 26112, 25088, 28160, 27136, 11008, 10496, 12032, 11520, 8960, 8448, 9984,
+    9472, 15104, 14592, 16128, 15616, 13056, 12544, 14080, 13568, 344, 328, 376, 360, 280, 264,
+    312, 296, 472, 456, 504, 488, 408, 392, 440, 424, 88, 72, 120, 104, 24, 8, 56, 40, 216, 200,
+    248, 232, 152, 136, 184, 168, 1376, 1312, 1504, 1440, 1120, 1056, 1248, 1184, 1888, 1824, 2016,
+    1952, 1632, 1568, 1760, 1696, 688, 656, 752, 720, 560, 528, 624, 592, 944, 912, 1008, 976, 816,
+    784, 880, 848,
+];
+
 #[cfg(test)]
 mod test {
-    use super::Sample;
+    use super::{
+        a_law_to_linear, linear_to_a_law, linear_to_mu_law, mu_law_to_linear, I24Packed, Sample,
+        SampleFormat, I24,
+    };
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    #[test]
+    fn i8_to_i16() {
+        assert_eq!(0i8.to_i16(), 0);
+        assert_eq!((-64i8).to_i16(), -16384);
+        assert_eq!(127i8.to_i16(), 32512);
+        assert_eq!((-128i8).to_i16(), -32768);
+    }
+
+    #[test]
+    fn i8_to_u8() {
+        assert_eq!(0i8.to_u8(), 128);
+        assert_eq!((-64i8).to_u8(), 64);
+        assert_eq!(127i8.to_u8(), 255);
+        assert_eq!((-128i8).to_u8(), 0);
+    }
+
+    #[test]
+    fn i8_to_f32() {
+        assert_eq!(0i8.to_f32(), 0.0);
+        assert_eq!((-64i8).to_f32(), -0.5);
+        assert_eq!(127i8.to_f32(), 1.0);
+        assert_eq!((-128i8).to_f32(), -1.0);
+    }
+
+    #[test]
+    fn u8_to_i8() {
+        assert_eq!(128u8.to_i8(), 0);
+        assert_eq!(64u8.to_i8(), -64);
+        assert_eq!(255u8.to_i8(), 127);
+        assert_eq!(0u8.to_i8(), -128);
+    }
+
+    #[test]
+    fn u8_to_u16() {
+        assert_eq!(0u8.to_u16(), 0);
+        assert_eq!(128u8.to_u16(), 32768);
+        assert_eq!(255u8.to_u16(), 65280);
+    }
+
+    #[test]
+    fn u8_to_f32() {
+        assert_eq!(0u8.to_f32(), -1.0);
+        assert_eq!(128u8.to_f32(), 0.0);
+        assert_eq!(255u8.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn i16_to_i8() {
+        assert_eq!(0i16.to_i8(), 0);
+        assert_eq!((-16384i16).to_i8(), -64);
+        assert_eq!(32767i16.to_i8(), 127);
+        assert_eq!((-32768i16).to_i8(), -128);
+    }
+
+    #[test]
+    fn u16_to_u8() {
+        assert_eq!(0u16.to_u8(), 0);
+        assert_eq!(32768u16.to_u8(), 128);
+        assert_eq!(65535u16.to_u8(), 255);
+    }
+
+    #[test]
+    fn f32_to_i8() {
+        assert_eq!(0.0f32.to_i8(), 0);
+        assert_eq!((-0.5f32).to_i8(), i8::MIN / 2);
+        assert_eq!(1.0f32.to_i8(), i8::MAX);
+        assert_eq!((-1.0f32).to_i8(), i8::MIN);
+    }
+
+    #[test]
+    fn f32_to_u8() {
+        assert_eq!((-1.0f32).to_u8(), 0);
+        assert_eq!(0.0f32.to_u8(), 128);
+        assert_eq!(1.0f32.to_u8(), 255);
+    }
 
     #[test]
     fn i16_to_i16() {
@@ -192,9 +1297,9 @@ mod test {
     #[test]
     fn f32_to_i16() {
         assert_eq!(0.0f32.to_i16(), 0);
-        assert_eq!((-0.5f32).to_i16(), ::std::i16::MIN / 2);
-        assert_eq!(1.0f32.to_i16(), ::std::i16::MAX);
-        assert_eq!((-1.0f32).to_i16(), ::std::i16::MIN);
+        assert_eq!((-0.5f32).to_i16(), i16::MIN / 2);
+        assert_eq!(1.0f32.to_i16(), i16::MAX);
+        assert_eq!((-1.0f32).to_i16(), i16::MIN);
     }
 
     #[test]
@@ -210,4 +1315,248 @@ mod test {
         assert_eq!((-0.7f32).to_f32(), -0.7);
         assert_eq!(1.0f32.to_f32(), 1.0);
     }
+
+    #[test]
+    fn i24_to_i16() {
+        assert_eq!(I24::new(0).unwrap().to_i16(), 0);
+        assert_eq!(I24::new(-4_194_304).unwrap().to_i16(), -16384);
+        assert_eq!(I24::MAX.to_i16(), 32767);
+        assert_eq!(I24::MIN.to_i16(), -32768);
+    }
+
+    #[test]
+    fn i24_to_f32() {
+        assert_eq!(I24::new(0).unwrap().to_f32(), 0.0);
+        assert_eq!(I24::new(-4_194_304).unwrap().to_f32(), -0.5);
+        assert_eq!(I24::MAX.to_f32(), 1.0);
+        assert_eq!(I24::MIN.to_f32(), -1.0);
+    }
+
+    #[test]
+    fn i24_ignores_upper_byte() {
+        let sample = I24(0x7f80_0000);
+        assert_eq!(sample.inner(), -8_388_608);
+        assert_eq!(sample.to_i16(), i16::MIN);
+        assert_eq!(sample, I24::MIN);
+        assert_ne!(sample, I24(0x0080_0001));
+        let hash = |sample: I24| {
+            let mut hasher = DefaultHasher::new();
+            sample.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(sample), hash(I24::MIN));
+    }
+
+    #[test]
+    fn i16_to_i24() {
+        assert_eq!(0i16.to_i24(), I24::new(0).unwrap());
+        assert_eq!((-16384i16).to_i24(), I24::new(-4_194_304).unwrap());
+        assert_eq!(32767i16.to_i24(), I24::new(8_388_352).unwrap());
+        assert_eq!((-32768i16).to_i24(), I24::MIN);
+    }
+
+    #[test]
+    fn f32_to_i24() {
+        assert_eq!(0.0f32.to_i24(), I24::new(0).unwrap());
+        assert_eq!((-0.5f32).to_i24(), I24::new(-4_194_304).unwrap());
+        assert_eq!(1.0f32.to_i24(), I24::MAX);
+        assert_eq!((-1.0f32).to_i24(), I24::MIN);
+        assert_eq!(2.0f32.to_i24(), I24::MAX);
+        assert_eq!((-2.0f32).to_i24(), I24::MIN);
+    }
+
+    #[test]
+    fn i24_packed_round_trip() {
+        for &value in &[0, 1, -1, 0x12_3456, -0x12_3456, 8_388_607, -8_388_608] {
+            let sample = I24::new(value).unwrap();
+            let packed: I24Packed = Sample::from(&sample);
+            assert_eq!(packed.to_i24(), sample);
+        }
+        let packed: I24Packed = Sample::from(&I24::new(0x12_3456).unwrap());
+        if cfg!(target_endian = "big") {
+            assert_eq!(packed.to_bytes(), [0x12, 0x34, 0x56]);
+        } else {
+            assert_eq!(packed.to_bytes(), [0x56, 0x34, 0x12]);
+        }
+    }
+
+    #[test]
+    fn i16_to_i32() {
+        assert_eq!(0i16.to_i32(), 0);
+        assert_eq!((-16384i16).to_i32(), -1_073_741_824);
+        assert_eq!(32767i16.to_i32(), 2_147_418_112);
+        assert_eq!((-32768i16).to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn i32_to_i16() {
+        assert_eq!(0i32.to_i16(), 0);
+        assert_eq!((-1_073_741_824i32).to_i16(), -16384);
+        assert_eq!(i32::MAX.to_i16(), 32767);
+        assert_eq!(i32::MIN.to_i16(), -32768);
+    }
+
+    #[test]
+    fn i32_to_u32() {
+        assert_eq!(0i32.to_u32(), 2_147_483_648);
+        assert_eq!((-1_073_741_824i32).to_u32(), 1_073_741_824);
+        assert_eq!(i32::MAX.to_u32(), u32::MAX);
+        assert_eq!(i32::MIN.to_u32(), 0);
+    }
+
+    #[test]
+    fn u32_to_i32() {
+        assert_eq!(2_147_483_648u32.to_i32(), 0);
+        assert_eq!(1_073_741_824u32.to_i32(), -1_073_741_824);
+        assert_eq!(u32::MAX.to_i32(), i32::MAX);
+        assert_eq!(0u32.to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn i32_to_f64() {
+        assert_eq!(0i32.to_f64(), 0.0);
+        assert_eq!((-1_073_741_824i32).to_f64(), -0.5);
+        assert_eq!(i32::MAX.to_f64(), 1.0);
+        assert_eq!(i32::MIN.to_f64(), -1.0);
+    }
+
+    #[test]
+    fn f64_to_i32() {
+        assert_eq!(0.0f64.to_i32(), 0);
+        assert_eq!((-0.5f64).to_i32(), -1_073_741_824);
+        assert_eq!(1.0f64.to_i32(), i32::MAX);
+        assert_eq!((-1.0f64).to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn f64_to_i24() {
+        assert_eq!(0.0f64.to_i24(), I24::new(0).unwrap());
+        assert_eq!(1.0f64.to_i24(), I24::MAX);
+        assert_eq!((-1.0f64).to_i24(), I24::MIN);
+        // Just below an LSB boundary, which rounding through `f32` would cross.
+        let value = 4_194_303.9f64 / I24::MAX.0 as f64;
+        assert_eq!(value.to_i24(), I24::new(4_194_303).unwrap());
+    }
+
+    #[test]
+    fn f64_to_u32() {
+        assert_eq!((-1.0f64).to_u32(), 0);
+        assert_eq!(0.0f64.to_u32(), 2_147_483_648);
+        assert_eq!(1.0f64.to_u32(), u32::MAX);
+    }
+
+    #[test]
+    fn i24_to_i32() {
+        assert_eq!(I24::MAX.to_i32(), 2_147_483_392);
+        assert_eq!(I24::MIN.to_i32(), i32::MIN);
+        assert_eq!(0i32.to_i24(), I24::new(0).unwrap());
+        assert_eq!(i32::MIN.to_i24(), I24::MIN);
+    }
+
+    #[test]
+    fn mu_law_to_linear_values() {
+        assert_eq!(mu_law_to_linear(0xFF), 0);
+        assert_eq!(mu_law_to_linear(0x7F), 0);
+        assert_eq!(mu_law_to_linear(0x80), 32124);
+        assert_eq!(mu_law_to_linear(0x00), -32124);
+    }
+
+    #[test]
+    fn linear_to_mu_law_values() {
+        assert_eq!(linear_to_mu_law(0), 0xFF);
+        assert_eq!(linear_to_mu_law(i16::MAX), 0x80);
+        assert_eq!(linear_to_mu_law(i16::MIN), 0x00);
+    }
+
+    #[test]
+    fn mu_law_round_trip() {
+        // 0x7F is negative zero and encodes back to positive zero.
+        for byte in (0..=255u8).filter(|&b| b != 0x7F) {
+            assert_eq!(linear_to_mu_law(mu_law_to_linear(byte)), byte);
+        }
+    }
+
+    #[test]
+    fn a_law_to_linear_values() {
+        assert_eq!(a_law_to_linear(0xD5), 8);
+        assert_eq!(a_law_to_linear(0x55), -8);
+        assert_eq!(a_law_to_linear(0xAA), 32256);
+        assert_eq!(a_law_to_linear(0x2A), -32256);
+    }
+
+    #[test]
+    fn linear_to_a_law_values() {
+        assert_eq!(linear_to_a_law(0), 0xD5);
+        assert_eq!(linear_to_a_law(i16::MAX), 0xAA);
+        assert_eq!(linear_to_a_law(i16::MIN), 0x2A);
+    }
+
+    #[test]
+    fn a_law_round_trip() {
+        for byte in 0..=255u8 {
+            assert_eq!(linear_to_a_law(a_law_to_linear(byte)), byte);
+        }
+    }
+
+    #[test]
+    fn equilibrium() {
+        assert_eq!(<u8 as Sample>::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(<u16 as Sample>::EQUILIBRIUM, 32768);
+        assert_eq!(<u32 as Sample>::EQUILIBRIUM.to_i32(), 0);
+        assert_eq!(
+            <I24Packed as Sample>::EQUILIBRIUM.to_i24(),
+            I24::new(0).unwrap()
+        );
+        assert_eq!(<f64 as Sample>::EQUILIBRIUM.to_i16(), 0);
+    }
+
+    #[test]
+    fn fill_silence() {
+        let mut buffer = [1; 8];
+        SampleFormat::U16.fill_silence(&mut buffer);
+        assert_eq!(buffer, [32768u16.to_ne_bytes(); 4].concat()[..]);
+        SampleFormat::MuLaw.fill_silence(&mut buffer);
+        assert_eq!(buffer, [0xFF; 8]);
+        SampleFormat::F32.fill_silence(&mut buffer);
+        assert_eq!(buffer, [0; 8]);
+    }
+
+    #[test]
+    fn full_scale() {
+        assert_eq!(<f32 as Sample>::FULL_SCALE_MAX, 1.0);
+        assert_eq!(<f32 as Sample>::FULL_SCALE_MIN, -1.0);
+        assert_eq!(<I24Packed as Sample>::FULL_SCALE_MAX.to_i24(), I24::MAX);
+        assert_eq!(<I24Packed as Sample>::FULL_SCALE_MIN.to_i24(), I24::MIN);
+        assert_eq!(<u16 as Sample>::FULL_SCALE_MIN.to_i16(), i16::MIN);
+        assert_eq!(<i32 as Sample>::FULL_SCALE_MAX.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn add_amp() {
+        assert_eq!(1000i16.add_amp(-3000), -2000);
+        assert_eq!(30000i16.add_amp(30000), i16::MAX);
+        assert_eq!((-30000i16).add_amp(-30000), i16::MIN);
+        assert_eq!(32768u16.add_amp(40000), 40000);
+        assert_eq!(60000u16.add_amp(60000), u16::MAX);
+        assert_eq!(0u16.add_amp(0), 0);
+        assert_eq!(I24::MAX.add_amp(I24::MAX), I24::MAX);
+        assert_eq!(0.75f32.add_amp(0.75), 1.5);
+    }
+
+    #[test]
+    fn mul_amp() {
+        assert_eq!(16384i16.mul_amp(0.5), 8192);
+        assert_eq!(20000i16.mul_amp(2.0), i16::MAX);
+        assert_eq!(65535u16.mul_amp(0.0), 32768);
+        assert_eq!(0u8.mul_amp(0.5), 64);
+        assert_eq!(I24::MIN.mul_amp(0.5).inner(), -4_194_304);
+        assert_eq!(0.5f64.mul_amp(0.5), 0.25);
+    }
+
+    #[test]
+    fn to_sample() {
+        assert_eq!(0.5f32.to_sample::<i16>(), 16383);
+        assert_eq!((-1.0f32).to_sample::<u8>(), 0);
+        assert_eq!(i16::MIN.to_sample::<I24>(), I24::MIN);
+    }
 }
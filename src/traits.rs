@@ -1,8 +1,13 @@
 //! The suite of traits allowing CPAL to abstract over hosts, devices, event loops and stream IDs.
 
+use adapt::{self, AdaptedStream, Direction, InputAdapter, OutputAdapter, StreamAdaptation};
+use negotiate;
+use std::time::Duration;
 use {
-    BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, InputDevices, OutputCallbackInfo, OutputDevices, PauseStreamError,
+    BuildStreamError, ChannelLayout, ConfigRequest, Data, DefaultStreamConfigError,
+    DeviceDescription, DeviceDescriptionError, DeviceId, DeviceIdError, DeviceNameError,
+    DevicesError, EffectiveConfig, Frame, InputCallbackInfo, InputDevices, NegotiateConfigError,
+    NegotiatedConfig, OutputCallbackInfo, OutputDevices, PauseStreamError, PlanarData,
     PlayStreamError, Sample, SampleFormat, StreamConfig, StreamError, SupportedStreamConfig,
     SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
@@ -50,32 +55,33 @@ pub trait HostTrait {
     /// Returns `None` if no output device is available.
     fn default_output_device(&self) -> Option<Self::Device>;
 
-    /// An iterator yielding all `Device`s currently available to the system that support one or more
-    /// input stream formats.
+    /// The available device with the given identifier, as returned by `DeviceTrait::id`.
+    ///
+    /// Returns `None` if no such device is available, e.g. because it has been unplugged.
+    fn device_by_id(&self, id: &DeviceId) -> Option<Self::Device> {
+        self.devices()
+            .ok()?
+            .find(|device| device.id().ok().as_ref() == Some(id))
+    }
+
+    /// An iterator yielding all `Device`s currently available to the system that support input, as
+    /// reported by `DeviceTrait::supports_input`.
     ///
     /// Can be empty if the system does not support audio input.
     fn input_devices(&self) -> Result<InputDevices<Self::Devices>, DevicesError> {
-        fn supports_input<D: DeviceTrait>(device: &D) -> bool {
-            device
-                .supported_input_configs()
-                .map(|mut iter| iter.next().is_some())
-                .unwrap_or(false)
-        }
-        Ok(self.devices()?.filter(supports_input::<Self::Device>))
+        Ok(self
+            .devices()?
+            .filter(<Self::Device as DeviceTrait>::supports_input))
     }
 
-    /// An iterator yielding all `Device`s currently available to the system that support one or more
-    /// output stream formats.
+    /// An iterator yielding all `Device`s currently available to the system that support output, as
+    /// reported by `DeviceTrait::supports_output`.
     ///
     /// Can be empty if the system does not support audio output.
     fn output_devices(&self) -> Result<OutputDevices<Self::Devices>, DevicesError> {
-        fn supports_output<D: DeviceTrait>(device: &D) -> bool {
-            device
-                .supported_output_configs()
-                .map(|mut iter| iter.next().is_some())
-                .unwrap_or(false)
-        }
-        Ok(self.devices()?.filter(supports_output::<Self::Device>))
+        Ok(self
+            .devices()?
+            .filter(<Self::Device as DeviceTrait>::supports_output))
     }
 }
 
@@ -94,6 +100,40 @@ pub trait DeviceTrait {
     /// The human-readable name of the device.
     fn name(&self) -> Result<String, DeviceNameError>;
 
+    /// A stable identifier of the device, which can be resolved with `HostTrait::device_by_id`.
+    ///
+    /// Hosts without stable identifiers use the name of the device.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(self.name()?))
+    }
+
+    /// A description of the device meant for showing to users.
+    ///
+    /// Hosts without further information describe the device by its name alone.
+    fn description(&self) -> Result<DeviceDescription, DeviceDescriptionError> {
+        Ok(DeviceDescription::from_name(self.name()?))
+    }
+
+    /// Whether the device can be opened for input.
+    ///
+    /// Hosts that know the direction of their devices answer without opening the device. Others
+    /// check for at least one supported input stream config.
+    fn supports_input(&self) -> bool {
+        self.supported_input_configs()
+            .map(|mut iter| iter.next().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Whether the device can be opened for output.
+    ///
+    /// Hosts that know the direction of their devices answer without opening the device. Others
+    /// check for at least one supported output stream config.
+    fn supports_output(&self) -> bool {
+        self.supported_output_configs()
+            .map(|mut iter| iter.next().is_some())
+            .unwrap_or(false)
+    }
+
     /// An iterator yielding formats that are supported by the backend.
     ///
     /// Can return an error if the device is no longer valid (eg. it has been disconnected).
@@ -114,6 +154,64 @@ pub trait DeviceTrait {
     /// The default output stream format for the device.
     fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError>;
 
+    /// Whether the device supports opening an input stream with the given configuration and
+    /// sample format.
+    ///
+    /// The period settings are checked against `SupportedStreamConfigRange::periods` where the
+    /// device reports them, and the channel selection is checked to be valid, though not whether
+    /// the host supports selecting channels. Returns `false` if the supported configurations
+    /// cannot be retrieved.
+    fn is_input_config_supported(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> bool {
+        self.supported_input_configs()
+            .map(|mut ranges| {
+                ranges.any(|range| negotiate::supports(&range, config, sample_format))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Whether the device supports opening an output stream with the given configuration and
+    /// sample format.
+    ///
+    /// The period settings are checked against `SupportedStreamConfigRange::periods` where the
+    /// device reports them, and the channel selection is checked to be valid, though not whether
+    /// the host supports selecting channels. Returns `false` if the supported configurations
+    /// cannot be retrieved.
+    fn is_output_config_supported(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> bool {
+        self.supported_output_configs()
+            .map(|mut ranges| {
+                ranges.any(|range| negotiate::supports(&range, config, sample_format))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Choose the supported input stream configuration that best matches `request`.
+    ///
+    /// If none matches, the error lists the reasons each supported configuration was rejected.
+    fn negotiate_input_config(
+        &self,
+        request: &ConfigRequest,
+    ) -> Result<NegotiatedConfig, NegotiateConfigError> {
+        negotiate::negotiate(self.supported_input_configs()?, request)
+    }
+
+    /// Choose the supported output stream configuration that best matches `request`.
+    ///
+    /// If none matches, the error lists the reasons each supported configuration was rejected.
+    fn negotiate_output_config(
+        &self,
+        request: &ConfigRequest,
+    ) -> Result<NegotiatedConfig, NegotiateConfigError> {
+        negotiate::negotiate(self.supported_output_configs()?, request)
+    }
+
     /// Create an input stream.
     fn build_input_stream<T, D, E>(
         &self,
@@ -166,6 +264,134 @@ pub trait DeviceTrait {
         )
     }
 
+    /// Create an input stream that delivers fixed-size frames, e.g. `[f32; 2]` for stereo.
+    ///
+    /// Returns `BuildStreamError::ChannelCountMismatch` if `F::CHANNELS` does not match the
+    /// channel count of `config`.
+    fn build_input_stream_frames<F, D, E>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        F: Frame,
+        D: FnMut(&[F], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_frame_channels::<F>(config)?;
+        self.build_input_stream_raw(
+            config,
+            <F::Sample as Sample>::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_frames()
+                        .expect("host supplied incorrect sample type or partial frame"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Create an output stream that expects fixed-size frames, e.g. `[f32; 2]` for stereo.
+    ///
+    /// Returns `BuildStreamError::ChannelCountMismatch` if `F::CHANNELS` does not match the
+    /// channel count of `config`.
+    fn build_output_stream_frames<F, D, E>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        F: Frame,
+        D: FnMut(&mut [F], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_frame_channels::<F>(config)?;
+        self.build_output_stream_raw(
+            config,
+            <F::Sample as Sample>::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_frames_mut()
+                        .expect("host supplied incorrect sample type or partial frame"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Create an input stream, adapting the requested configuration to the device as permitted by
+    /// `adaptation`.
+    ///
+    /// The returned `AdaptedStream` describes the configuration the device was opened with.
+    fn build_input_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        adaptation: &StreamAdaptation,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<AdaptedStream<Self::Stream>, BuildStreamError>
+    where
+        T: Sample + Send + 'static,
+        D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let ranges = self.supported_input_configs().map_err(adapt::configs_err)?;
+        let device_config =
+            adapt::choose_device_config(ranges, config, T::FORMAT, adaptation, Direction::Input)?;
+        let mut adapter = InputAdapter::<T>::new(config, &device_config, adaptation);
+        let device_stream_config = StreamConfig {
+            buffer_size: config.buffer_size.clone(),
+            ..device_config.config()
+        };
+        let stream = self.build_input_stream_raw(
+            &device_stream_config,
+            device_config.sample_format(),
+            move |data, info| adapter.process(data, info, &mut data_callback),
+            error_callback,
+        )?;
+        Ok(AdaptedStream::new(stream, device_config))
+    }
+
+    /// Create an output stream, adapting the requested configuration to the device as permitted by
+    /// `adaptation`.
+    ///
+    /// The returned `AdaptedStream` describes the configuration the device was opened with.
+    fn build_output_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        adaptation: &StreamAdaptation,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<AdaptedStream<Self::Stream>, BuildStreamError>
+    where
+        T: Sample + Send + 'static,
+        D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let ranges = self
+            .supported_output_configs()
+            .map_err(adapt::configs_err)?;
+        let device_config =
+            adapt::choose_device_config(ranges, config, T::FORMAT, adaptation, Direction::Output)?;
+        let mut adapter = OutputAdapter::<T>::new(config, &device_config, adaptation);
+        let device_stream_config = StreamConfig {
+            buffer_size: config.buffer_size.clone(),
+            ..device_config.config()
+        };
+        let stream = self.build_output_stream_raw(
+            &device_stream_config,
+            device_config.sample_format(),
+            move |data, info| adapter.process(data, info, &mut data_callback),
+            error_callback,
+        )?;
+        Ok(AdaptedStream::new(stream, device_config))
+    }
+
     /// Create a dynamically typed input stream.
     fn build_input_stream_raw<D, E>(
         &self,
@@ -189,6 +415,71 @@ pub trait DeviceTrait {
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static;
+
+    /// Create a dynamically typed input stream that delivers one slice of samples per channel.
+    ///
+    /// By default this is built on `build_input_stream_raw`, deinterleaving each buffer into one
+    /// owned by the stream before calling `data_callback`. The ASIO host, and the ALSA host for
+    /// devices with non-interleaved access, hand their buffers through directly.
+    fn build_input_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&PlanarData, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        crate::planar::build_input_stream_deinterleaved(
+            self,
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    /// Create a dynamically typed output stream that expects one slice of samples per channel.
+    ///
+    /// By default this is built on `build_output_stream_raw`, interleaving each buffer after
+    /// calling `data_callback`. The ASIO host, and the ALSA host for devices with non-interleaved
+    /// access, hand their buffers through directly. In either case the contents of the buffer are
+    /// unspecified when passed to `data_callback`, which must write every sample of every channel.
+    fn build_output_stream_planar<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut PlanarData, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        crate::planar::build_output_stream_interleaved(
+            self,
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+        )
+    }
+}
+
+// Ensures that the channel count of the frame type `F` matches that of the callback.
+fn check_frame_channels<F>(config: &StreamConfig) -> Result<(), BuildStreamError>
+where
+    F: Frame,
+{
+    if config.callback_channels() != F::CHANNELS {
+        return Err(BuildStreamError::ChannelCountMismatch {
+            frame_channels: F::CHANNELS,
+            config_channels: config.callback_channels(),
+        });
+    }
+    Ok(())
 }
 
 /// A stream created from `Device`, with methods to control playback.
@@ -205,4 +496,28 @@ pub trait StreamTrait {
     /// Note: Not all devices support suspending the stream at the hardware level. This method may
     /// fail in these cases.
     fn pause(&self) -> Result<(), PauseStreamError>;
+
+    /// The channel map the stream was opened with, if the host reports one.
+    ///
+    /// This is the layout of the device's channels. The stream of an `AdaptedStream` may use a
+    /// different layout from the data passed to the callback.
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        None
+    }
+
+    /// The time it takes to play or record the device buffer, if the host reports it.
+    ///
+    /// This reflects the buffer size the host settled on, which may differ from the requested
+    /// `BufferSize`.
+    fn latency(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The configuration the stream is running with, if the host reports it.
+    ///
+    /// Unless `StreamConfig::strict` is set, this may differ from the requested configuration
+    /// where the host settled on nearby values.
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        None
+    }
 }